@@ -21,25 +21,20 @@ enum HelloCommands {
     Bye(ByeArgs),
 }
 
-fn handle(_sh: &dyn Shell, matches: &ArgMatches) -> HandlerResult {
-    match HelloCommands::from_arg_matches(matches) {
-        Ok(HelloCommands::Hello(args)) => {
+fn handle(_sh: &dyn Shell, cmd: HelloCommands) -> HandlerResult {
+    match cmd {
+        HelloCommands::Hello(args) => {
             println!("Hello, {}!", args.name);
             HANDLER_SUCCESS
         }
-        Ok(HelloCommands::Bye(args)) => {
+        HelloCommands::Bye(args) => {
             let bye = "bye, ".repeat(args.count as usize + 1);
             println!("Bye, {}{}!", bye, args.name);
             HANDLER_SUCCESS
         }
-        Err(_) => Err(ShellError::CommandNotFound),
     }
 }
 
 fn main() -> Result<ExitCode, ShellError> {
-    shell_config!("hello")
-        .cli_cmds(Arc::new(HelloCommands::augment_subcommands))
-        .cli_handler(Arc::new(handle))
-        .build()
-        .run()
+    shell_config!("hello").cli_command(handle).build()?.run()
 }
@@ -8,7 +8,11 @@
 //!
 //! | Flag | Default | Description |
 //! |------|---------|-------------|
+//! | `inotify` | off | On Linux, `HostVfs::watch` uses inotify (via [`rustix`](https://docs.rs/rustix)) instead of polling. Other platforms and backends keep the `PollWatcher`. |
+//! | `readline` | off | Interactive line editing for the REPL via [`rustyline`](https://docs.rs/rustyline): cursor movement, kill/yank, reverse-i-search, and persistent history (see [`get_history_path`]). Without it, the REPL reads plain lines from stdin. |
+//! | `tar` | off | `ArchiveVfs` for tar archives, plain or gzip-compressed, and `mount --archive` in the shell. |
 //! | `tracing-log` | off | Bridges the [`log`](https://docs.rs/log) crate to [`tracing`] so libraries that use `log::*` macros are captured by the tracing subscriber. |
+//! | `zip` | off | `ArchiveVfs` for zip archives, and `mount --archive` in the shell. |
 
 #![warn(missing_docs)]
 // Be very strict about safety
@@ -21,15 +25,25 @@
 mod parse;
 mod shell;
 mod util;
+mod vfs;
 
 pub mod prelude;
 
 pub use parse::{
-    shell_parse_arg, shell_parse_arg_bytes, shell_parse_line, shell_parse_line_bytes,
+    shell_parse_arg, shell_parse_arg_bytes, shell_parse_line, shell_parse_line_bytes, shell_quote,
     ShellParseError,
 };
 pub use shell::{
-    Augmentor, Handler, HandlerResult, Shell, ShellConfig, ShellError, Vfs, VfsLookup,
-    HANDLER_SUCCESS,
+    Augmentor, EditValidator, Handler, HandlerResult, HostAccess, HostPolicy, Shell, ShellConfig,
+    ShellError, ValueCompleter, VfsLookup, VfsOpener, HANDLER_SUCCESS,
+};
+pub use util::{
+    get_cmd_basename, get_cmd_fallback, get_history_path, init_tracing, make_env_ident,
+};
+#[cfg(any(feature = "tar", feature = "zip"))]
+pub use vfs::ArchiveVfs;
+pub use vfs::{
+    HostVfs, MemSnapshot, MemVfs, MountTable, OverlayVfs, PollWatcher, Vfs, VfsChange,
+    VfsChangeKind, VfsDirEntry, VfsError, VfsFileType, VfsMetadata, VfsResult, VfsWatcher,
+    VfsWriteMode,
 };
-pub use util::{get_cmd_basename, get_cmd_fallback, init_tracing, make_env_ident};
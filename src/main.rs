@@ -6,62 +6,55 @@
 #![forbid(unsafe_code)]
 
 use esh::prelude::*;
-/// Note that in this example, the `-p` flag is trusted input —
-/// do not expose it to untrusted users for security sandboxing.
-use std::path::{Path, PathBuf};
-// use std::process::ExitCode;
-
-// use clap::{ArgMatches, Args, Parser};
-// use esh::prelude::*;
-// use tracing::info;
-use vfs_kit::{DirFS, FsBackend};
-
-struct DirFsVfs(DirFS);
-
-impl Vfs for DirFsVfs {
-    fn cwd(&self) -> &Path {
-        self.0.cwd()
-    }
-}
-
-fn parse_vfs_root(os_str: &str) -> Result<PathBuf, String> {
-    let native_path = match PathBuf::from(os_str).canonicalize() {
-        Ok(p) => p,
-        Err(e) => {
-            return Err(format!("cannot open path '{os_str}': {e}"));
-        }
-    };
-    if !native_path.is_dir() {
-        return Err(format!("not a directory: '{os_str}'"));
-    }
-    Ok(native_path)
-}
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct CliArgs {
-    #[arg(short='p', long="path", default_value=".", value_parser = parse_vfs_root,
+    // Opened by `create_vfs` when a command needs it, so a bad path does not
+    // break commands like `version`
+    #[arg(short='p', long="path", default_value=".", value_hint = clap::ValueHint::DirPath,
           help="Path to open a VFS on")]
     vfs_path: PathBuf,
+
+    #[arg(
+        long = "overlay",
+        help = "Keep changes in memory until `commit`, see `diff` and `discard`"
+    )]
+    overlay: bool,
 }
 
 fn create_vfs(matches: &ArgMatches) -> Result<Box<dyn Vfs>, ShellError> {
     let root_path = matches
         .get_one::<PathBuf>("vfs_path")
         .ok_or_else(|| ShellError::Internal("missing vfs_path argument".into()))?;
-    let mut fs = DirFS::new(root_path).map_err(|e| {
-        ShellError::Internal(format!("can't open VFS at '{}': {e}", root_path.display()))
-    })?;
-    info!("Created DirFS with root {root_path:?}");
-    fs.set_auto_clean(false);
-    Ok(Box::new(DirFsVfs(fs)))
+    // Confines all VFS paths to the directory, so users of the shell never
+    // see the rest of the host
+    let fs = HostVfs::new(root_path)?;
+    info!("Created HostVfs with root {root_path:?}");
+    if matches.get_flag("overlay") {
+        return Ok(Box::new(OverlayVfs::new(fs, MemVfs::new())));
+    }
+    Ok(Box::new(fs))
 }
 
-fn main() -> Result<ExitCode, ShellError> {
+/// Open `mem:` as an empty in-memory VFS, and `file://PATH` or `PATH` as a
+/// host directory, for the interactive `open` command.
+fn open_vfs(spec: &str) -> Result<Box<dyn Vfs>, ShellError> {
+    if spec == "mem:" {
+        return Ok(Box::new(MemVfs::new()));
+    }
+    let path = spec.strip_prefix("file://").unwrap_or(spec);
+    Ok(Box::new(HostVfs::new(path)?))
+}
+
+fn main() -> ExitCode {
     let cfg = shell_config!()
         .cli_args(Arc::new(CliArgs::augment_args))
-        .vfs_lookup(Arc::new(create_vfs));
-    let sh = cfg.build();
-
-    sh.run()
+        .vfs_lookup(Arc::new(create_vfs))
+        .vfs_opener(Arc::new(open_vfs));
+    cfg.build().and_then(|sh| sh.run()).unwrap_or_else(|e| {
+        eprintln!("error: {e}");
+        ExitCode::FAILURE
+    })
 }
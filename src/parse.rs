@@ -1,4 +1,5 @@
 use std::ffi::OsString;
+use std::fmt::Write;
 use std::iter::Peekable;
 use std::str::Chars;
 
@@ -253,6 +254,199 @@ pub fn shell_parse_line_bytes(input: &str) -> Result<Vec<Vec<u8>>, ShellParseErr
     Ok(words)
 }
 
+/// Quote `word` so that [`shell_parse_line`] turns it back into exactly one
+/// word equal to `word`.
+///
+/// Words made of plain characters are returned unchanged. Otherwise the word
+/// is wrapped in single quotes, or in double quotes with backslash escapes
+/// when it contains single quotes or control characters.
+///
+/// # Examples
+///
+/// ```
+/// # use esh::{shell_parse_line, shell_quote, ShellParseError};
+/// assert_eq!(shell_quote("plain.txt"), "plain.txt");
+/// assert_eq!(shell_quote("my file"), "'my file'");
+/// assert_eq!(shell_quote("it's"), r#""it's""#);
+/// assert_eq!(shell_parse_line(&shell_quote("tab\there"))?, vec!["tab\there"]);
+/// # Ok::<(), ShellParseError>(())
+/// ```
+#[must_use]
+pub fn shell_quote(word: &str) -> String {
+    if !word.is_empty() && word.chars().all(is_plain_char) {
+        word.to_string()
+    } else {
+        shell_quote_as(word, Some('\''), true)
+    }
+}
+
+/// Quote `word` in a particular style: `None` escapes special characters
+/// with backslashes, `Some('\'')` and `Some('"')` wrap the word in that
+/// quote. Single quotes fall back to double quotes when the word cannot be
+/// represented literally. With `close == false` the closing quote is left
+/// off, so the user can keep typing (used for completion of partial words).
+#[allow(clippy::redundant_pub_crate)] // for completion, not re-exported
+pub(crate) fn shell_quote_as(word: &str, quote: Option<char>, close: bool) -> String {
+    let mut out = String::with_capacity(word.len() + 2);
+    match quote {
+        Some('\'') if !word.chars().any(|c| c == '\'' || c.is_control()) => {
+            out.push('\'');
+            out.push_str(word);
+            if close {
+                out.push('\'');
+            }
+        }
+        Some(_) => {
+            out.push('"');
+            for c in word.chars() {
+                match c {
+                    '"' | '\\' => {
+                        out.push('\\');
+                        out.push(c);
+                    }
+                    c if c.is_control() => push_control_escape(&mut out, c),
+                    c => out.push(c),
+                }
+            }
+            if close {
+                out.push('"');
+            }
+        }
+        None => {
+            for c in word.chars() {
+                match c {
+                    c if c.is_control() => push_control_escape(&mut out, c),
+                    c if is_plain_char(c) => out.push(c),
+                    c => {
+                        out.push('\\');
+                        out.push(c);
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Characters that never need quoting.
+fn is_plain_char(c: char) -> bool {
+    c.is_ascii_alphanumeric()
+        || matches!(
+            c,
+            '-' | '_' | '.' | '/' | ':' | '@' | '%' | '+' | '=' | ',' | '~' | '^'
+        )
+        || (!c.is_ascii() && !c.is_whitespace() && !c.is_control())
+}
+
+fn push_control_escape(out: &mut String, c: char) {
+    match c {
+        '\n' => out.push_str("\\n"),
+        '\t' => out.push_str("\\t"),
+        '\r' => out.push_str("\\r"),
+        c => {
+            let _ = write!(out, "\\u{{{:x}}}", u32::from(c));
+        }
+    }
+}
+
+/// The result of splitting an incomplete line, as typed so far.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::redundant_pub_crate)]
+pub(crate) struct PartialLine {
+    /// Complete words before the word being typed.
+    pub words: Vec<String>,
+    /// Byte offset in the input where the word being typed starts.
+    pub start: usize,
+    /// The word being typed, with quotes and escapes already processed.
+    pub prefix: String,
+    /// The quote character the word being typed starts with, if any.
+    pub quote: Option<char>,
+}
+
+/// Split an incomplete line for completion purposes.
+///
+/// Unlike [`shell_parse_line`], an unterminated quote or a trailing
+/// backslash in the last word is tolerated: the quote is treated as closed
+/// and the backslash is dropped. Returns `None` if the line cannot be parsed
+/// or the end of the line is inside a `#` comment.
+#[allow(clippy::redundant_pub_crate)]
+pub(crate) fn shell_parse_partial(input: &str) -> Option<PartialLine> {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Quote {
+        None,
+        Single,
+        Double,
+    }
+
+    let mut quote = Quote::None;
+    let mut word_start: Option<usize> = None;
+    let mut escaped = false;
+
+    for (i, c) in input.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match quote {
+            Quote::Single => {
+                if c == '\'' {
+                    quote = Quote::None;
+                }
+            }
+            Quote::Double => match c {
+                '"' => quote = Quote::None,
+                '\\' => escaped = true,
+                _ => {}
+            },
+            Quote::None => match c {
+                ' ' | '\t' | '\n' | '\r' => word_start = None,
+                '#' if word_start.is_none() => return None,
+                _ => {
+                    word_start.get_or_insert(i);
+                    match c {
+                        '\'' => quote = Quote::Single,
+                        '"' => quote = Quote::Double,
+                        '\\' => escaped = true,
+                        _ => {}
+                    }
+                }
+            },
+        }
+    }
+
+    let start = word_start.unwrap_or(input.len());
+    let (head, tail) = input.split_at(start);
+
+    let words = shell_parse_line_bytes(head)
+        .ok()?
+        .into_iter()
+        .map(|w| String::from_utf8_lossy(&w).into_owned())
+        .collect();
+
+    let mut fragment = tail.to_string();
+    if escaped {
+        fragment.pop();
+    }
+    match quote {
+        Quote::Single => fragment.push('\''),
+        Quote::Double => fragment.push('"'),
+        Quote::None => {}
+    }
+    let prefix = shell_parse_line_bytes(&fragment)
+        .ok()?
+        .into_iter()
+        .next()
+        .map(|w| String::from_utf8_lossy(&w).into_owned())
+        .unwrap_or_default();
+
+    Some(PartialLine {
+        words,
+        start,
+        prefix,
+        quote: tail.chars().next().filter(|c| matches!(c, '\'' | '"')),
+    })
+}
+
 /// Append the UTF-8 encoding of `c` to a byte buffer.
 #[inline]
 fn push_char(output: &mut Vec<u8>, c: char) {
@@ -661,6 +855,155 @@ mod tests {
         );
     }
 
+    // ---- shell_quote -------------------------------------------------------
+
+    fn round_trip(word: &str) {
+        let quoted = shell_quote(word);
+        assert_eq!(
+            shell_parse_line(&quoted).unwrap(),
+            vec![word],
+            "quoted as {quoted}"
+        );
+        for quote in [None, Some('\''), Some('"')] {
+            let quoted = shell_quote_as(word, quote, true);
+            assert_eq!(
+                shell_parse_line(&quoted).unwrap(),
+                vec![word],
+                "quoted as {quoted}"
+            );
+        }
+    }
+
+    #[test]
+    fn quote_plain_word_unchanged() {
+        assert_eq!(shell_quote("some/path-1.txt"), "some/path-1.txt");
+        assert_eq!(shell_quote("café"), "café");
+    }
+
+    #[test]
+    fn quote_empty_word() {
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_parse_line(&shell_quote("")).unwrap(), vec![""]);
+    }
+
+    #[test]
+    fn quote_round_trips() {
+        for word in [
+            "my file",
+            "it's",
+            r#"say "hi""#,
+            r"back\slash",
+            "#hash",
+            "tab\tand\nnewline",
+            "bell\u{7}",
+            "$dollar `tick`",
+            "mixed 'single' and \"double\"",
+        ] {
+            round_trip(word);
+        }
+    }
+
+    #[test]
+    fn quote_backslash_style() {
+        assert_eq!(shell_quote_as("my file", None, true), r"my\ file");
+        assert_eq!(shell_quote_as("a'b", None, true), r"a\'b");
+    }
+
+    #[test]
+    fn quote_unclosed() {
+        assert_eq!(shell_quote_as("my dir/", Some('"'), false), "\"my dir/");
+        assert_eq!(shell_quote_as("my dir/", Some('\''), false), "'my dir/");
+    }
+
+    // ---- shell_parse_partial -----------------------------------------------
+
+    fn partial(input: &str) -> PartialLine {
+        shell_parse_partial(input).unwrap()
+    }
+
+    #[test]
+    fn partial_empty_line() {
+        let p = partial("");
+        assert!(p.words.is_empty());
+        assert_eq!((p.start, p.prefix.as_str(), p.quote), (0, "", None));
+    }
+
+    #[test]
+    fn partial_after_space_starts_new_word() {
+        let p = partial("ls -l ");
+        assert_eq!(p.words, vec!["ls", "-l"]);
+        assert_eq!((p.start, p.prefix.as_str()), (6, ""));
+    }
+
+    #[test]
+    fn partial_plain_word() {
+        let p = partial("ls fo");
+        assert_eq!(p.words, vec!["ls"]);
+        assert_eq!((p.start, p.prefix.as_str(), p.quote), (3, "fo", None));
+    }
+
+    #[test]
+    fn partial_unterminated_double_quote() {
+        let p = partial(r#"cat "my fi"#);
+        assert_eq!(p.words, vec!["cat"]);
+        assert_eq!(
+            (p.start, p.prefix.as_str(), p.quote),
+            (4, "my fi", Some('"'))
+        );
+    }
+
+    #[test]
+    fn partial_unterminated_single_quote() {
+        let p = partial(r"cat 'a\b");
+        assert_eq!((p.prefix.as_str(), p.quote), (r"a\b", Some('\'')));
+    }
+
+    #[test]
+    fn partial_escaped_space() {
+        let p = partial(r"cat my\ fi");
+        assert_eq!(p.words, vec!["cat"]);
+        assert_eq!((p.start, p.prefix.as_str(), p.quote), (4, "my fi", None));
+    }
+
+    #[test]
+    fn partial_trailing_backslash() {
+        let p = partial(r"cat my\");
+        assert_eq!(p.prefix, "my");
+    }
+
+    #[test]
+    fn partial_quoted_earlier_words() {
+        let p = partial(r#"cp "a b" 'c d' e"#);
+        assert_eq!(p.words, vec!["cp", "a b", "c d"]);
+        assert_eq!(p.prefix, "e");
+    }
+
+    #[test]
+    fn partial_closed_quote_in_current_word() {
+        let p = partial(r#"cat "a b"c"#);
+        assert_eq!(
+            (p.start, p.prefix.as_str(), p.quote),
+            (4, "a bc", Some('"'))
+        );
+    }
+
+    #[test]
+    fn partial_inside_comment() {
+        assert_eq!(shell_parse_partial("ls # comm"), None);
+    }
+
+    #[test]
+    fn partial_invalid_escape() {
+        assert_eq!(shell_parse_partial(r"cat \x"), None);
+    }
+
+    #[test]
+    fn partial_multibyte_offsets() {
+        let p = partial("cat café b");
+        assert_eq!(p.words, vec!["cat", "café"]);
+        assert_eq!(p.start, "cat café ".len());
+    }
+
     // ---- comments ----------------------------------------------------------
 
     #[test]
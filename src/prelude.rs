@@ -11,9 +11,13 @@
 pub use std::process::ExitCode;
 pub use std::sync::Arc;
 
+#[cfg(any(feature = "tar", feature = "zip"))]
+pub use crate::ArchiveVfs;
 pub use crate::{
-    die, shell_config, Augmentor, Handler, HandlerResult, Shell, ShellConfig, ShellError, Vfs,
-    VfsLookup, HANDLER_SUCCESS,
+    die, shell_config, Augmentor, EditValidator, Handler, HandlerResult, HostAccess, HostPolicy,
+    HostVfs, MemVfs, MountTable, OverlayVfs, Shell, ShellConfig, ShellError, ValueCompleter, Vfs,
+    VfsDirEntry, VfsError, VfsFileType, VfsLookup, VfsMetadata, VfsOpener, VfsResult, VfsWriteMode,
+    HANDLER_SUCCESS,
 };
 pub use clap::{ArgMatches, Args, Command, CommandFactory, FromArgMatches, Parser, Subcommand};
 pub use tracing::{debug, error, info, trace, warn};
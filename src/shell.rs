@@ -1,17 +1,35 @@
-use std::path::Path;
-
-use clap::{
-    error::ErrorKind, ArgAction, ArgMatches, Args, Command, FromArgMatches, Parser, Subcommand,
-};
+mod complete;
+mod content_cmds;
+mod diff;
+mod edit_cmds;
+#[cfg(feature = "readline")]
+mod editor;
+mod file_cmds;
+mod interrupt;
+mod mount_cmds;
+mod open_cmds;
+mod overlay_cmds;
+mod repl;
+mod search_cmds;
+mod stat_cmds;
+mod sum_cmds;
+mod transfer_cmds;
+mod vfs_cmds;
+mod watch_cmds;
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use clap::{error::ErrorKind, ArgAction, ArgMatches, Args, Command, Parser, Subcommand};
 use thiserror::Error;
 
 use std::ffi::OsString;
 use std::process::ExitCode;
-use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError, Weak};
 
 use tracing::{info, warn};
 
-use crate::die;
+use crate::vfs::{normalize, MountTable, Vfs};
 
 /// Errors returned by shell operations.
 #[derive(Error, Debug)]
@@ -32,9 +50,25 @@ pub enum ShellError {
     #[error("Argument error: {0}")]
     ArgumentError(#[from] clap::Error),
 
+    /// Two registrations add a subcommand of the same name
+    #[error("Duplicate command: {0}")]
+    DuplicateCommand(String),
+
     /// Catch-all for standard IO issues
     #[error("IO Error: {0}")]
     Io(#[from] std::io::Error),
+
+    /// An input line could not be split into words
+    #[error("Parse error: {0}")]
+    Parse(#[from] crate::ShellParseError),
+
+    /// A VFS operation failed
+    #[error("VFS error: {0}")]
+    Vfs(#[from] crate::VfsError),
+
+    /// A command needs the VFS, but it was closed
+    #[error("No VFS open, use `open` or `reopen`")]
+    NoVfsOpen,
 }
 
 /// Core trait for running the shell.
@@ -56,6 +90,39 @@ pub trait Shell {
     /// Returns [`ShellError`] if tracing initialisation, VFS setup, or
     /// command dispatch fails.
     fn run_args(&self, args: &[OsString]) -> Result<ExitCode, ShellError>;
+
+    /// Run the interactive read-eval-print loop on stdin until `exit` or
+    /// end of input.
+    ///
+    /// Each line is split with [`shell_parse_line`](crate::shell_parse_line)
+    /// and dispatched to the interactive shell commands and handlers.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ShellError`] if reading input fails or a handler signals a
+    /// fatal error (see [`die!`](crate::die)). All other command errors are
+    /// reported on stderr and the loop continues.
+    fn repl(&self) -> Result<ExitCode, ShellError>;
+
+    /// Compute interactive completions for the word under the cursor at byte
+    /// offset `pos` of `line`.
+    ///
+    /// Completes subcommand names, flags, possible values, and values from
+    /// registered [`ValueCompleter`]s. Returns the byte offset where the word
+    /// under the cursor starts, and the replacements for that word, already
+    /// quoted so that they round-trip through
+    /// [`shell_parse_line`](crate::shell_parse_line).
+    fn complete(&self, line: &str, pos: usize) -> (usize, Vec<String>);
+
+    /// Drop the command trees built from the registered [`Augmentor`]s, so
+    /// the next command line rebuilds them.
+    ///
+    /// The trees are built once and reused for parsing, help and completion.
+    /// Call this when an augmentor adds something that depends on runtime
+    /// state, such as the possible values of an argument, and that state has
+    /// changed. Subcommands are dispatched by the names they had when the
+    /// shell was built.
+    fn invalidate_commands(&self);
 }
 
 type AugmentorFn = dyn Fn(Command) -> Command + Send + Sync;
@@ -74,26 +141,145 @@ pub const HANDLER_SUCCESS: HandlerResult = Ok(ExitCode::SUCCESS);
 ///
 /// Return `HANDLER_SUCCESS` on success, [`ShellError::CommandNotFound`] to pass
 /// control to the next handler, or another [`ShellError`] to abort.
+///
+/// Handlers for the subcommands of a single [`Subcommand`] type are easier to
+/// register with [`ShellConfig::command`], which parses the type for them and
+/// dispatches to them by name. Handlers registered on their own are tried in
+/// turn for the subcommands that no such registration owns.
 pub type Handler = Arc<HandlerFn>;
 
-/// Backend-agnostic VFS interface for the shell.
+type ValueCompleterFn = dyn Fn(&dyn Shell, &str) -> Vec<String> + Send + Sync;
+
+/// A shared closure that supplies interactive completion candidates for the
+/// values of an argument, given the (unquoted) prefix typed so far.
 ///
-/// Implement this trait to plug in any filesystem backend.
-pub trait Vfs: Send {
-    /// Return the current working directory of this filesystem.
-    fn cwd(&self) -> &Path;
-}
+/// Register it with [`ShellConfig::value_completer`]. Returned values that do
+/// not start with the prefix are filtered out.
+pub type ValueCompleter = Arc<ValueCompleterFn>;
 
 type VfsLookupFn = dyn Fn(&ArgMatches) -> Result<Box<dyn Vfs>, ShellError> + Send + Sync;
 
 /// A shared closure that creates a [`Vfs`] from the parsed command-line arguments.
 pub type VfsLookup = Arc<VfsLookupFn>;
 
+type VfsOpenerFn = dyn Fn(&str) -> Result<Box<dyn Vfs>, ShellError> + Send + Sync;
+
+/// A shared closure that creates a [`Vfs`] from a spec typed by the user,
+/// such as a path or URI, see [`ShellConfig::vfs_opener`].
+pub type VfsOpener = Arc<VfsOpenerFn>;
+
+/// What `get` and `put` are about to do with a host path, see
+/// [`HostPolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HostAccess {
+    /// Read a file or list a directory
+    Read,
+    /// Create or overwrite a file, or create a directory
+    Write,
+}
+
+type HostPolicyFn = dyn Fn(&Path, HostAccess) -> bool + Send + Sync;
+
+/// A shared closure that decides whether the `get` and `put` commands may
+/// access a host path, see [`ShellConfig::host_policy`].
+pub type HostPolicy = Arc<HostPolicyFn>;
+
+type EditValidatorFn = dyn Fn(&Path, &[u8]) -> Result<(), String> + Send + Sync;
+
+/// A shared closure that checks the new contents of a file edited with the
+/// `edit` command before they are saved, see [`ShellConfig::edit_validator`].
+pub type EditValidator = Arc<EditValidatorFn>;
+
 #[derive(Default, Clone)]
 struct CommandGroup {
     args: Vec<Augmentor>,
     cmds: Vec<Augmentor>,
     hnds: Vec<Handler>,
+    /// Subcommands registered together with their handler
+    routes: Vec<(Augmentor, Handler)>,
+    /// The handlers of `routes` by subcommand name, see [`Self::index`]
+    table: HashMap<String, Handler>,
+}
+
+impl CommandGroup {
+    /// Register the subcommands added by `cmds`, handled by `handler`.
+    fn route(&mut self, cmds: Augmentor, handler: Handler) {
+        self.cmds.push(Arc::clone(&cmds));
+        self.routes.push((cmds, handler));
+    }
+
+    /// Fill the dispatch table from the routes, and check that no two
+    /// registrations add a subcommand of the same name.
+    fn index(&mut self) -> Result<(), ShellError> {
+        let mut names = HashSet::new();
+        for name in self.cmds.iter().flat_map(|cmds| subcommand_names(&**cmds)) {
+            if !names.insert(name.clone()) {
+                return Err(ShellError::DuplicateCommand(name));
+            }
+        }
+        for (cmds, handler) in &self.routes {
+            for name in subcommand_names(&**cmds) {
+                self.table.insert(name, Arc::clone(handler));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The names of the subcommands that `augment` adds, with their visible and
+/// hidden aliases.
+fn subcommand_names(augment: &AugmentorFn) -> Vec<String> {
+    augment(Command::new(""))
+        .get_subcommands()
+        .flat_map(|cmd| std::iter::once(cmd.get_name()).chain(cmd.get_all_aliases()))
+        .map(str::to_owned)
+        .collect()
+}
+
+/// A command tree, built on first use and kept for later ones.
+///
+/// The command groups of a shell are fixed when it is built, so a cached
+/// tree stays valid until [`Shell::invalidate_commands`] clears it.
+#[derive(Default)]
+struct CommandCache(Mutex<Option<Command>>);
+
+impl CommandCache {
+    /// Run `f` on the cached tree, building it with `build` if there is none
+    /// yet. The cache is locked while `f` runs.
+    fn with<T>(
+        &self,
+        build: impl FnOnce() -> Command,
+        f: impl FnOnce(&mut Command) -> T,
+    ) -> Result<T, ShellError> {
+        let mut cached = self
+            .0
+            .lock()
+            .map_err(|e| ShellError::Internal(format!("command mutex poisoned: {e}")))?;
+        let cmd = cached.get_or_insert_with(|| {
+            let mut cmd = build();
+            cmd.build();
+            cmd
+        });
+        let result = f(cmd);
+        drop(cached);
+        Ok(result)
+    }
+
+    /// Drop the cached tree, so the next use builds it again.
+    fn clear(&self) {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+}
+
+/// The VFS of a shell and where it came from.
+#[derive(Default)]
+struct VfsState {
+    /// Opened on first use, see [`BasicShell::with_mounts`]
+    mounts: Option<MountTable>,
+    /// The spec of the last `open`; the configured lookups if `None`
+    spec: Option<String>,
+    /// Set by `close`, keeps the VFS from being opened on first use
+    closed: bool,
 }
 
 struct BasicShell {
@@ -101,11 +287,45 @@ struct BasicShell {
     pkg_name: String,
     version: String,
     cli_group: CommandGroup,
-    #[allow(dead_code)] // used by future REPL mode
     shell_group: CommandGroup,
-    vfs_lookup: Option<VfsLookup>,
-    vfs: Mutex<Option<Box<dyn Vfs>>>,
+    /// The trees of `build_cmd` and `build_shell_cmd`
+    cli_cmd: CommandCache,
+    shell_cmd: CommandCache,
+    completers: HashMap<String, ValueCompleter>,
+    vfs_mounts: Vec<(PathBuf, VfsLookup)>,
+    /// Subcommands that open the VFS before their handler runs
+    vfs_commands: HashSet<String>,
+    vfs_opener: Option<VfsOpener>,
+    host_policy: Option<HostPolicy>,
+    edit_validator: Option<EditValidator>,
+    /// Runs when the command line has no subcommand; `repl` if `None`
+    default_handler: Option<Handler>,
+    vfs: Mutex<VfsState>,
+    /// The parsed command line, kept for mounting configured backends later
+    cli_matches: Mutex<Option<ArgMatches>>,
+    exit_code: Mutex<Option<ExitCode>>,
     init_tracing: bool,
+    #[cfg_attr(not(feature = "readline"), allow(dead_code))]
+    history_file: Option<PathBuf>,
+}
+
+/// Parse the subcommand in `matches` as one of `T`'s.
+///
+/// Fails with [`ShellError::CommandNotFound`] if the subcommand is not one of
+/// `T`'s, so the next handler gets a chance, and with
+/// [`ShellError::ArgumentError`] if it is but cannot be parsed.
+fn parse_subcommand<T: Subcommand>(matches: &ArgMatches) -> Result<T, ShellError> {
+    match matches.subcommand_name() {
+        Some(name) if T::has_subcommand(name) => Ok(T::from_arg_matches(matches)?),
+        _ => Err(ShellError::CommandNotFound),
+    }
+}
+
+/// Wrap `handler` of the subcommands of `T` into a [`Handler`].
+fn subcommand_handler<T: Subcommand + 'static>(
+    handler: impl Fn(&dyn Shell, T) -> HandlerResult + Send + Sync + 'static,
+) -> Handler {
+    Arc::new(move |sh, matches| handler(sh, parse_subcommand(matches)?))
 }
 
 /// DSL for registering subcommands, arguments, and handlers
@@ -113,18 +333,18 @@ struct BasicShell {
 /// No locks are required — all registration happens before the groups are moved into the
 /// `BasicShell` struct.
 ///
-///   - `CMDS <Type> [groups..]` — registers `<Type>::augment_subcommands`
+///   - `CMDS <Type> => <fn> [groups..]` — registers `<Type>::augment_subcommands`
+///     with `<fn>` as the handler of its subcommands, wrapped in a `Handler`
+///     closure that captures a `Weak<BasicShell>` (must be called inside
+///     `Arc::new_cyclic`); `<fn>` takes the parsed `<Type>`
 ///   - `ARGS <Type> [groups..]` — registers `<Type>::augment_args`
-///   - `HNDS <fn>   [groups..]` — wraps `<fn>` in a `Handler` closure that
-///     captures a `Weak<BasicShell>` (must be called inside `Arc::new_cyclic`)
 ///
 /// # Example
 ///
 /// ```ignore
 /// add_sh!(weak => {
-///     CMDS BasicSharedCommands          [ shell_group, cli_group ],
-///     HNDS handle_basic_shared_command  [ shell_group, cli_group ],
-///     ARGS BasicCliArgs                 [              cli_group ],
+///     CMDS BasicSharedCommands => handle_basic_shared_command [ shell_group, cli_group ],
+///     ARGS BasicCliArgs                                       [              cli_group ],
 /// });
 /// ```
 macro_rules! add_sh {
@@ -132,16 +352,24 @@ macro_rules! add_sh {
 
     // Top-level entry: $weak is a &Weak<BasicShell> from Arc::new_cyclic
     ($weak:ident => {
-        $($method:ident $what:path [$($group:ident),* $(,)?] ),* $(,)?
+        $($method:ident $what:path $(=> $hnd:path)? [$($group:ident),* $(,)?] ),* $(,)?
     }) => {{
-        $( add_sh!(@add $weak, $method $what [ $( $group )* ] ); )*
+        $( add_sh!(@add $weak, $method $what $(=> $hnd)? [ $( $group )* ] ); )*
     }};
 
-    // CMDS — no Weak needed
-    (@add $weak:ident, CMDS $what:path [ $( $group:ident )* ] ) => {{
+    // CMDS — captures a Weak clone, upgrades when the handler is called
+    (@add $weak:ident, CMDS $what:path => $hnd:path [ $( $group:ident )* ] ) => {{
         type What = $what;
-        let aug = Arc::new(What::augment_subcommands);
-        $( $group.cmds.push(aug.clone()); )*
+        let aug: Augmentor = Arc::new(What::augment_subcommands);
+        let w = Weak::clone(&$weak);
+        let hnd: Handler = Arc::new(move |_, m| {
+            let cmd: What = parse_subcommand(m)?;
+            let sh = w.upgrade().ok_or(ShellError::Internal(
+                "shell dropped while handler active".into(),
+            ))?;
+            $hnd(&sh, cmd)
+        });
+        $( $group.route(aug.clone(), hnd.clone()); )*
     }};
 
     // ARGS — no Weak needed
@@ -151,30 +379,16 @@ macro_rules! add_sh {
         $( $group.args.push(aug.clone()); )*
     }};
 
-    // HNDS — captures a Weak clone, upgrades when called
-    (@add $weak:ident, HNDS $what:path [ $( $group:ident )* ] ) => {{
-        let w = Weak::clone(&$weak);
-        let hnd: Handler = Arc::new(move |_, m| {
-            let sh = w.upgrade().ok_or(ShellError::Internal(
-                "shell dropped while handler active".into(),
-            ))?;
-            $what(&sh, m)
-        });
-        $( $group.hnds.push(hnd.clone()); )*
-    }};
 }
 
-#[derive(Subcommand)]
+#[derive(Subcommand, Clone, Copy)]
 enum BasicCliCommands {
     Shell,
 }
 
-fn handle_basic_cli_command(_sh: &BasicShell, matches: &ArgMatches) -> HandlerResult {
-    match BasicCliCommands::from_arg_matches(matches) {
-        Ok(BasicCliCommands::Shell) => {
-            die!("command 'shell' not implemented");
-        }
-        Err(_) => Err(ShellError::CommandNotFound),
+fn handle_basic_cli_command(sh: &BasicShell, cmd: BasicCliCommands) -> HandlerResult {
+    match cmd {
+        BasicCliCommands::Shell => sh.repl(),
     }
 }
 
@@ -189,113 +403,177 @@ struct BasicCliArgs {
     verbose: u8,
 }
 
-#[derive(Subcommand)]
+#[derive(Subcommand, Clone, Copy)]
 enum BasicShellCommands {
-    Exit,
+    /// Leave the interactive shell
+    Exit {
+        /// Exit status to return from the shell
+        #[arg(default_value_t = 0)]
+        code: u8,
+    },
 }
 
-fn handle_basic_shell_command(_sh: &BasicShell, matches: &ArgMatches) -> HandlerResult {
-    match BasicShellCommands::from_arg_matches(matches) {
-        Ok(BasicShellCommands::Exit) => HANDLER_SUCCESS,
-        Err(_) => Err(ShellError::CommandNotFound),
+fn handle_basic_shell_command(sh: &BasicShell, cmd: BasicShellCommands) -> HandlerResult {
+    match cmd {
+        BasicShellCommands::Exit { code } => {
+            let code = ExitCode::from(code);
+            *sh.exit_code
+                .lock()
+                .map_err(|e| ShellError::Internal(format!("exit mutex poisoned: {e}")))? =
+                Some(code);
+            Ok(code)
+        }
     }
 }
 
-#[derive(Subcommand)]
+#[derive(Subcommand, Clone, Copy)]
 enum BasicSharedCommands {
     Version,
 }
 
-fn handle_basic_shared_command(sh: &BasicShell, matches: &ArgMatches) -> HandlerResult {
-    match BasicSharedCommands::from_arg_matches(matches) {
-        Ok(BasicSharedCommands::Version) => {
+fn handle_basic_shared_command(sh: &BasicShell, cmd: BasicSharedCommands) -> HandlerResult {
+    match cmd {
+        BasicSharedCommands::Version => {
             println!("{} {}", sh.pkg_name, sh.version);
             HANDLER_SUCCESS
         }
-        Err(_) => Err(ShellError::CommandNotFound),
     }
 }
 
-#[derive(Subcommand)]
-enum VfsSharedCommands {
-    Pwd,
-}
-
-fn handle_vfs_shared_command(sh: &BasicShell, matches: &ArgMatches) -> HandlerResult {
-    match VfsSharedCommands::from_arg_matches(matches) {
-        Ok(VfsSharedCommands::Pwd) => {
-            let vfs_guard = sh
-                .vfs
-                .lock()
-                .map_err(|e| ShellError::Internal(format!("vfs mutex poisoned: {e}")))?;
-            (*vfs_guard).as_ref().map_or_else(
-                || Err(ShellError::Internal("no current cwd".into())),
-                |fs| {
-                    println!("{}", fs.cwd().display());
-                    HANDLER_SUCCESS
-                },
-            )
-        }
-        Err(_) => Err(ShellError::CommandNotFound),
-    }
+/// The names of the built-in commands that need the VFS.
+fn builtin_vfs_commands() -> impl Iterator<Item = String> {
+    let builtins: [fn(Command) -> Command; 11] = [
+        vfs_cmds::VfsSharedCommands::augment_subcommands,
+        content_cmds::VfsContentCommands::augment_subcommands,
+        edit_cmds::VfsEditCommands::augment_subcommands,
+        file_cmds::VfsFileCommands::augment_subcommands,
+        search_cmds::VfsSearchCommands::augment_subcommands,
+        stat_cmds::VfsStatCommands::augment_subcommands,
+        sum_cmds::VfsSumCommands::augment_subcommands,
+        overlay_cmds::VfsOverlayCommands::augment_subcommands,
+        mount_cmds::VfsMountCommands::augment_subcommands,
+        watch_cmds::VfsWatchCommands::augment_subcommands,
+        transfer_cmds::VfsTransferCommands::augment_subcommands,
+    ];
+    builtins
+        .into_iter()
+        .flat_map(|augment| subcommand_names(&augment))
 }
 
 impl BasicShell {
-    fn new(
-        name: String,
-        pkg_name: String,
-        version: String,
-        shell_group: CommandGroup,
-        cli_group: CommandGroup,
-        vfs_lookup: Option<VfsLookup>,
-        init_tracing: bool,
-    ) -> Arc<Self> {
-        let has_vfs = vfs_lookup.is_some();
-        let mut shell_group = shell_group;
-        let mut cli_group = cli_group;
+    fn new(cfg: ShellConfig) -> Result<Arc<Self>, ShellError> {
+        let ShellConfig {
+            name,
+            pkg_name,
+            version,
+            mut cli_group,
+            mut shell_group,
+            completers,
+            vfs_mounts,
+            mut vfs_commands,
+            vfs_opener,
+            host_access,
+            host_policy,
+            edit_validator,
+            default_handler,
+            init_tracing,
+            history,
+            history_file,
+        } = cfg;
+        let has_vfs = !vfs_mounts.is_empty() || vfs_opener.is_some();
+        if has_vfs {
+            vfs_commands.extend(builtin_vfs_commands());
+        }
+        let history_file = if history {
+            history_file.or_else(|| crate::get_history_path(&name))
+        } else {
+            None
+        };
 
         // Build the Arc with new_cyclic so handler closures can capture a
         // Weak reference to the shell being constructed. The Weak is
         // guaranteed to upgrade successfully whenever a handler runs,
         // because the Arc owns the shell and handlers only run while it
         // is alive.
-        Arc::new_cyclic(|weak: &Weak<Self>| {
+        let mut indexed = Ok(());
+        let sh = Arc::new_cyclic(|weak: &Weak<Self>| {
             add_sh!(weak => {
-                CMDS BasicSharedCommands           [ shell_group, cli_group ],
-                HNDS handle_basic_shared_command   [ shell_group, cli_group ],
-
-                CMDS BasicShellCommands            [ shell_group            ],
-                HNDS handle_basic_shell_command    [ shell_group            ],
-
-                CMDS BasicCliCommands              [              cli_group ],
-                ARGS BasicCliArgs                  [              cli_group ],
-                HNDS handle_basic_cli_command      [              cli_group ],
+                CMDS BasicSharedCommands => handle_basic_shared_command [ shell_group, cli_group ],
+                CMDS BasicShellCommands  => handle_basic_shell_command  [ shell_group            ],
+                CMDS BasicCliCommands    => handle_basic_cli_command    [              cli_group ],
+                ARGS BasicCliArgs                                       [              cli_group ],
             });
 
             if has_vfs {
                 add_sh!(weak => {
-                    CMDS VfsSharedCommands         [ shell_group, cli_group ],
-                    HNDS handle_vfs_shared_command [ shell_group, cli_group ],
+                    CMDS vfs_cmds::VfsSharedCommands
+                        => vfs_cmds::handle_vfs_shared_command         [ shell_group, cli_group ],
+                    CMDS content_cmds::VfsContentCommands
+                        => content_cmds::handle_vfs_content_command    [ shell_group, cli_group ],
+                    CMDS edit_cmds::VfsEditCommands
+                        => edit_cmds::handle_vfs_edit_command          [ shell_group, cli_group ],
+                    CMDS file_cmds::VfsFileCommands
+                        => file_cmds::handle_vfs_file_command          [ shell_group, cli_group ],
+                    CMDS search_cmds::VfsSearchCommands
+                        => search_cmds::handle_vfs_search_command      [ shell_group, cli_group ],
+                    CMDS stat_cmds::VfsStatCommands
+                        => stat_cmds::handle_vfs_stat_command          [ shell_group, cli_group ],
+                    CMDS sum_cmds::VfsSumCommands
+                        => sum_cmds::handle_vfs_sum_command            [ shell_group, cli_group ],
+                    CMDS overlay_cmds::VfsOverlayCommands
+                        => overlay_cmds::handle_vfs_overlay_command    [ shell_group, cli_group ],
+                    CMDS mount_cmds::VfsMountCommands
+                        => mount_cmds::handle_vfs_mount_command        [ shell_group, cli_group ],
+                    CMDS watch_cmds::VfsWatchCommands
+                        => watch_cmds::handle_vfs_watch_command        [ shell_group, cli_group ],
+                });
+            }
+
+            if has_vfs && host_access {
+                add_sh!(weak => {
+                    CMDS transfer_cmds::VfsTransferCommands
+                        => transfer_cmds::handle_vfs_transfer_command  [ shell_group, cli_group ],
+                });
+            }
+
+            if vfs_opener.is_some() {
+                add_sh!(weak => {
+                    CMDS open_cmds::VfsOpenCommands
+                        => open_cmds::handle_vfs_open_command          [ shell_group            ],
                 });
             }
 
+            // Checked here, as the built-in commands only exist from here on
+            indexed = cli_group.index().and_then(|()| shell_group.index());
+
             Self {
                 name,
                 pkg_name,
                 version,
                 shell_group,
                 cli_group,
-                vfs_lookup,
-                vfs: Mutex::new(None),
+                cli_cmd: CommandCache::default(),
+                shell_cmd: CommandCache::default(),
+                completers,
+                vfs_mounts,
+                vfs_commands,
+                vfs_opener,
+                host_policy,
+                edit_validator,
+                default_handler,
+                vfs: Mutex::new(VfsState::default()),
+                cli_matches: Mutex::new(None),
+                exit_code: Mutex::new(None),
                 init_tracing,
+                history_file,
             }
-        })
+        });
+        indexed.map(|()| sh)
     }
 
     fn build_cmd(&self) -> Command {
-        let mut cmd = Command::new(self.name.clone())
-            .subcommand_required(true)
-            .arg_required_else_help(true);
+        // Without a subcommand, the default handler runs
+        let mut cmd = Command::new(self.name.clone());
 
         for args in &self.cli_group.args {
             cmd = (args)(cmd);
@@ -307,6 +585,132 @@ impl BasicShell {
 
         cmd
     }
+
+    fn build_shell_cmd(&self) -> Command {
+        let mut cmd = Command::new(self.name.clone())
+            .no_binary_name(true)
+            .subcommand_required(true)
+            .disable_version_flag(true);
+
+        for args in &self.shell_group.args {
+            cmd = (args)(cmd);
+        }
+
+        for cmds in &self.shell_group.cmds {
+            cmd = (cmds)(cmd);
+        }
+
+        cmd
+    }
+
+    /// Run `f` on the CLI command tree, see [`CommandCache`].
+    fn with_cli_cmd<T>(&self, f: impl FnOnce(&mut Command) -> T) -> Result<T, ShellError> {
+        self.cli_cmd.with(|| self.build_cmd(), f)
+    }
+
+    /// Run `f` on the interactive command tree, see [`CommandCache`].
+    fn with_shell_cmd<T>(&self, f: impl FnOnce(&mut Command) -> T) -> Result<T, ShellError> {
+        self.shell_cmd.with(|| self.build_shell_cmd(), f)
+    }
+
+    fn dispatch(&self, group: &CommandGroup, matches: &ArgMatches) -> HandlerResult {
+        if matches
+            .subcommand_name()
+            .is_some_and(|name| self.vfs_commands.contains(name))
+        {
+            self.with_mounts(|_| Ok(()))?;
+        }
+
+        if let Some(handler) = matches
+            .subcommand_name()
+            .and_then(|name| group.table.get(name))
+        {
+            return handler(self, matches);
+        }
+
+        // Handlers registered on their own get a chance in turn
+        for handler in &group.hnds {
+            match (handler)(self, matches) {
+                Ok(code) => return Ok(code),
+                Err(ShellError::CommandNotFound) => {} // Continue and give next handler a chance
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(ShellError::Internal(
+            "no handler matched the command".into(),
+        ))
+    }
+
+    /// Run `f` on the VFS, holding its lock for the duration of the call.
+    fn with_vfs<T>(
+        &self,
+        f: impl FnOnce(&mut (dyn Vfs + 'static)) -> Result<T, ShellError>,
+    ) -> Result<T, ShellError> {
+        self.with_mounts(|mounts| f(mounts))
+    }
+
+    /// Run `f` on the mount table behind the VFS, see
+    /// [`with_vfs`](Self::with_vfs).
+    ///
+    /// The VFS is opened on first use, unless it was closed. If opening
+    /// fails, the error is returned and the next call tries again.
+    fn with_mounts<T>(
+        &self,
+        f: impl FnOnce(&mut MountTable) -> Result<T, ShellError>,
+    ) -> Result<T, ShellError> {
+        let mut state = self.lock_vfs()?;
+        if state.mounts.is_none() && !state.closed {
+            state.mounts = Some(self.open_vfs(state.spec.as_deref())?);
+        }
+        state.mounts.as_mut().map_or(Err(ShellError::NoVfsOpen), f)
+    }
+
+    fn lock_vfs(&self) -> Result<std::sync::MutexGuard<'_, VfsState>, ShellError> {
+        self.vfs
+            .lock()
+            .map_err(|e| ShellError::Internal(format!("vfs mutex poisoned: {e}")))
+    }
+
+    /// Open the VFS from `spec` with the [`VfsOpener`], or mount all
+    /// configured backends (see [`ShellConfig::vfs_mount`]) without one.
+    fn open_vfs(&self, spec: Option<&str>) -> Result<MountTable, ShellError> {
+        if let Some(spec) = spec {
+            let opener = self
+                .vfs_opener
+                .as_ref()
+                .ok_or_else(|| ShellError::Internal("no VFS opener configured".into()))?;
+            return Ok(MountTable::from((opener)(spec)?));
+        }
+        if self.vfs_mounts.is_empty() {
+            return Err(ShellError::NoVfsOpen);
+        }
+        let matches = self.cli_matches()?;
+        let mut mounts = MountTable::new();
+        for (point, lookup) in &self.vfs_mounts {
+            mounts.mount(point, (lookup)(&matches)?)?;
+        }
+        Ok(mounts)
+    }
+
+    /// Create the backend configured for the mount point `point`, see
+    /// [`ShellConfig::vfs_mount`].
+    fn lookup_vfs(&self, point: &Path) -> Result<Option<Box<dyn Vfs>>, ShellError> {
+        let Some((_, lookup)) = self.vfs_mounts.iter().find(|(p, _)| p == point) else {
+            return Ok(None);
+        };
+        (lookup)(&self.cli_matches()?).map(Some)
+    }
+
+    /// The parsed command line, or empty matches before [`Shell::run_args`].
+    fn cli_matches(&self) -> Result<ArgMatches, ShellError> {
+        Ok(self
+            .cli_matches
+            .lock()
+            .map_err(|e| ShellError::Internal(format!("matches mutex poisoned: {e}")))?
+            .clone()
+            .unwrap_or_default())
+    }
 }
 
 static INIT_LOGGING: OnceLock<Result<(), String>> = OnceLock::new();
@@ -328,7 +732,7 @@ impl Shell for BasicShell {
         // First, evaluate the actual command line using external argv.
         // Then we determine if we need to go into interactive mode or
         // directly execute a command from argv.
-        let matches = match self.build_cmd().try_get_matches_from(args) {
+        let matches = match self.with_cli_cmd(|cmd| cmd.try_get_matches_from_mut(args))? {
             Ok(m) => m,
             Err(e) => match e.kind() {
                 ErrorKind::DisplayHelp | ErrorKind::DisplayVersion => {
@@ -365,25 +769,50 @@ impl Shell for BasicShell {
             }
         }
 
-        if let Some(vfs_lookup) = &self.vfs_lookup {
-            let vfs = (vfs_lookup)(&matches)?;
+        if !self.vfs_mounts.is_empty() {
+            // Mounted again from these matches when a command needs it
+            *self.lock_vfs()? = VfsState::default();
             *self
-                .vfs
+                .cli_matches
                 .lock()
-                .map_err(|e| ShellError::Internal(format!("vfs mutex poisoned: {e}")))? = Some(vfs);
+                .map_err(|e| ShellError::Internal(format!("matches mutex poisoned: {e}")))? =
+                Some(matches.clone());
         }
 
-        for handler in &self.cli_group.hnds {
-            match (handler)(self, &matches) {
-                Ok(code) => return Ok(code),
-                Err(ShellError::CommandNotFound) => {} // Continue and give next handler a chance
-                Err(e) => return Err(e),
-            }
+        if matches.subcommand_name().is_none() {
+            return self
+                .default_handler
+                .as_ref()
+                .map_or_else(|| self.repl(), |handler| handler(self, &matches));
         }
+        self.dispatch(&self.cli_group, &matches)
+    }
 
-        Err(ShellError::Internal(
-            "no handler matched the command".into(),
-        ))
+    fn repl(&self) -> Result<ExitCode, ShellError> {
+        #[cfg(feature = "readline")]
+        if std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+            let helper = editor::ShellHelper::new(self);
+            let mut lines = editor::EditorLines::new(self.history_file.clone(), Some(helper))?;
+            return self.run_repl(&mut lines);
+        }
+        self.run_repl(&mut repl::StdinLines::new())
+    }
+
+    fn complete(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        // A copy, so completers may use the shell
+        let Ok(cmd) = self.with_shell_cmd(|cmd| cmd.clone()) else {
+            return (pos, Vec::new());
+        };
+        let (start, candidates) = self.complete_line(&cmd, line, pos);
+        (
+            start,
+            candidates.into_iter().map(|c| c.replacement).collect(),
+        )
+    }
+
+    fn invalidate_commands(&self) {
+        self.cli_cmd.clear();
+        self.shell_cmd.clear();
     }
 }
 
@@ -398,8 +827,17 @@ pub struct ShellConfig {
     version: String,
     cli_group: CommandGroup,
     shell_group: CommandGroup,
-    vfs_lookup: Option<VfsLookup>,
+    completers: HashMap<String, ValueCompleter>,
+    vfs_mounts: Vec<(PathBuf, VfsLookup)>,
+    vfs_commands: HashSet<String>,
+    vfs_opener: Option<VfsOpener>,
+    host_access: bool,
+    host_policy: Option<HostPolicy>,
+    edit_validator: Option<EditValidator>,
+    default_handler: Option<Handler>,
     init_tracing: bool,
+    history: bool,
+    history_file: Option<PathBuf>,
 }
 
 /// Create a [`ShellConfig`] with Cargo metadata filled in automatically.
@@ -433,8 +871,17 @@ impl ShellConfig {
             version: version.into(),
             cli_group: CommandGroup::default(),
             shell_group: CommandGroup::default(),
-            vfs_lookup: None,
+            completers: HashMap::new(),
+            vfs_mounts: Vec::new(),
+            vfs_commands: HashSet::new(),
+            vfs_opener: None,
+            host_access: true,
+            host_policy: None,
+            edit_validator: None,
+            default_handler: None,
             init_tracing: true,
+            history: true,
+            history_file: None,
         }
     }
 
@@ -462,6 +909,53 @@ impl ShellConfig {
         self
     }
 
+    /// Register the subcommands of `T` and their `handler`, in the CLI as
+    /// well as in the interactive shell.
+    ///
+    /// The handler gets the parsed subcommand and only runs for `T`'s
+    /// subcommands; one that cannot be parsed fails with
+    /// [`ShellError::ArgumentError`].
+    ///
+    /// ```no_run
+    /// # use esh::prelude::*;
+    /// # #[derive(Subcommand)]
+    /// # enum GreetCommands {
+    /// #     Hello { name: String },
+    /// # }
+    /// fn greet(_sh: &dyn Shell, cmd: GreetCommands) -> HandlerResult {
+    ///     match cmd {
+    ///         GreetCommands::Hello { name } => println!("Hello, {name}!"),
+    ///     }
+    ///     HANDLER_SUCCESS
+    /// }
+    ///
+    /// let cfg = shell_config!("greeter").command(greet);
+    /// ```
+    pub fn command<T: Subcommand + 'static>(
+        mut self,
+        handler: impl Fn(&dyn Shell, T) -> HandlerResult + Send + Sync + 'static,
+    ) -> Self {
+        let augmentor: Augmentor = Arc::new(T::augment_subcommands);
+        let handler = subcommand_handler(handler);
+        self.cli_group
+            .route(Arc::clone(&augmentor), Arc::clone(&handler));
+        self.shell_group.route(augmentor, handler);
+        self
+    }
+
+    /// Register the subcommands of `T` and their `handler` in the CLI only,
+    /// see [`command`](Self::command).
+    pub fn cli_command<T: Subcommand + 'static>(
+        mut self,
+        handler: impl Fn(&dyn Shell, T) -> HandlerResult + Send + Sync + 'static,
+    ) -> Self {
+        self.cli_group.route(
+            Arc::new(T::augment_subcommands),
+            subcommand_handler(handler),
+        );
+        self
+    }
+
     /// Register an [`Augmentor`] that adds arguments to interactive shell commands.
     pub fn shell_args(mut self, args: Augmentor) -> Self {
         self.shell_group.args.push(args);
@@ -480,9 +974,125 @@ impl ShellConfig {
         self
     }
 
+    /// Register the subcommands of `T` and their `handler` in the interactive
+    /// shell only, see [`command`](Self::command).
+    pub fn shell_command<T: Subcommand + 'static>(
+        mut self,
+        handler: impl Fn(&dyn Shell, T) -> HandlerResult + Send + Sync + 'static,
+    ) -> Self {
+        self.shell_group.route(
+            Arc::new(T::augment_subcommands),
+            subcommand_handler(handler),
+        );
+        self
+    }
+
+    /// Register a [`ValueCompleter`] for interactive tab completion of the
+    /// argument with id `arg_id` (the field name for derived arguments), in
+    /// every command that has such an argument.
+    ///
+    /// Takes precedence over the argument's possible values.
+    pub fn value_completer(mut self, arg_id: impl Into<String>, completer: ValueCompleter) -> Self {
+        self.completers.insert(arg_id.into(), completer);
+        self
+    }
+
     /// Set the [`VfsLookup`] closure that creates a VFS from parsed arguments.
-    pub fn vfs_lookup(mut self, lookup: VfsLookup) -> Self {
-        self.vfs_lookup = Some(lookup);
+    ///
+    /// This is the backend mounted at `/`, see [`vfs_mount`](Self::vfs_mount).
+    pub fn vfs_lookup(self, lookup: VfsLookup) -> Self {
+        self.vfs_mount("/", lookup)
+    }
+
+    /// Mount the VFS created by `lookup` at `point`, next to the other
+    /// backends in the shell's [`MountTable`](crate::MountTable). A later
+    /// lookup for the same mount point replaces the earlier one.
+    ///
+    /// The lookups run when a command first uses the VFS, not when the
+    /// shell starts, so commands like `version` work even if a backend
+    /// cannot be created. The interactive `umount` command detaches a
+    /// backend, `mount` brings it back by running its lookup again.
+    pub fn vfs_mount(mut self, point: impl AsRef<Path>, lookup: VfsLookup) -> Self {
+        let point = normalize(Path::new("/"), point.as_ref());
+        self.vfs_mounts.retain(|(p, _)| *p != point);
+        self.vfs_mounts.push((point, lookup));
+        self
+    }
+
+    /// Declare that the subcommand `name` needs the VFS, in the CLI as well
+    /// as in the interactive shell.
+    ///
+    /// The VFS is then opened before the command's handler runs, and the
+    /// command fails with the error of opening it instead of running. The
+    /// built-in VFS commands are declared this way.
+    pub fn vfs_command(mut self, name: impl Into<String>) -> Self {
+        self.vfs_commands.insert(name.into());
+        self
+    }
+
+    /// Set the [`VfsOpener`] that creates a VFS from a spec typed by the
+    /// user.
+    ///
+    /// This adds the interactive commands `open SPEC`, which replaces the
+    /// whole VFS with the one created from `SPEC`, `close`, and `reopen`,
+    /// which opens the last spec again (or runs the configured lookups if
+    /// there was none). The prompt shows the spec while it is open.
+    pub fn vfs_opener(mut self, opener: VfsOpener) -> Self {
+        self.vfs_opener = Some(opener);
+        self
+    }
+
+    /// Let `policy` decide which host paths the `get` and `put` commands
+    /// may read and write. Without a policy, they may access every host path
+    /// the process can.
+    ///
+    /// The policy sees each path before it is accessed, with symlinks
+    /// resolved: a file that does not exist yet is checked as its canonical
+    /// directory joined with its name. The commands then access that
+    /// resolved path, and refuse to write through a symlink that appears in
+    /// its place. Denied paths fail with
+    /// [`VfsError::PermissionDenied`](crate::VfsError).
+    pub fn host_policy(mut self, policy: HostPolicy) -> Self {
+        self.host_policy = Some(policy);
+        self
+    }
+
+    /// Leave out the `get` and `put` commands, so the shell never touches
+    /// the host filesystem on behalf of its users.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn no_host_access(mut self) -> Self {
+        self.host_access = false;
+        self
+    }
+
+    /// Let `validator` check the contents of files edited with the `edit`
+    /// command before they are written back to the VFS.
+    ///
+    /// The validator gets the VFS path and the new contents. If it returns
+    /// an error, the message is shown and the editor opens again on the
+    /// rejected contents; closing it without changes drops the edit.
+    pub fn edit_validator(mut self, validator: EditValidator) -> Self {
+        self.edit_validator = Some(validator);
+        self
+    }
+
+    /// Set the [`Handler`] that runs when the command line has global
+    /// arguments at most, but no subcommand, such as a plain `myshell -v`.
+    ///
+    /// It gets the parsed command line. Without one, the shell runs
+    /// [`Shell::repl`]: the interactive shell when stdin is a terminal, and
+    /// the commands read from stdin otherwise.
+    ///
+    /// ```no_run
+    /// # use esh::prelude::*;
+    /// # fn show_dashboard() {}
+    /// let cfg = shell_config!("dashboard").default_handler(Arc::new(|_sh, _matches| {
+    ///     show_dashboard();
+    ///     HANDLER_SUCCESS
+    /// }));
+    /// ```
+    pub fn default_handler(mut self, handler: Handler) -> Self {
+        self.default_handler = Some(handler);
         self
     }
 
@@ -497,18 +1107,31 @@ impl ShellConfig {
         self
     }
 
+    /// Store the interactive history in `path` instead of the default
+    /// location returned by [`get_history_path`](crate::get_history_path).
+    ///
+    /// Only used when the `readline` feature is enabled.
+    pub fn history_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.history_file = Some(path.into());
+        self
+    }
+
+    /// Do not load or save interactive history.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn no_history(mut self) -> Self {
+        self.history = false;
+        self
+    }
+
     /// Build the configured shell and return it as an `Arc<dyn Shell>`.
-    #[must_use]
-    pub fn build(self) -> Arc<dyn Shell + 'static> {
-        BasicShell::new(
-            self.name,
-            self.pkg_name,
-            self.version,
-            self.shell_group,
-            self.cli_group,
-            self.vfs_lookup,
-            self.init_tracing,
-        )
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ShellError::DuplicateCommand`] if two registrations add a
+    /// subcommand of the same name to the CLI or to the interactive shell,
+    /// built-in commands included.
+    pub fn build(self) -> Result<Arc<dyn Shell + 'static>, ShellError> {
+        Ok(BasicShell::new(self)?)
     }
 }
 
@@ -516,7 +1139,9 @@ impl ShellConfig {
 #[allow(clippy::expect_used, clippy::panic)]
 mod tests {
     use super::*;
-    use std::path::PathBuf;
+    use crate::die;
+    use clap::FromArgMatches;
+    use std::path::Path;
     use std::sync::atomic::{AtomicUsize, Ordering};
 
     fn config(name: &str) -> ShellConfig {
@@ -527,6 +1152,20 @@ mod tests {
         OsString::from(s)
     }
 
+    /// Parse `line` as one of the subcommands in `T`.
+    pub(super) fn try_parse_cmd<T: Subcommand + FromArgMatches>(
+        line: &str,
+    ) -> Result<T, clap::Error> {
+        let words = crate::shell_parse_line(line).expect("parse");
+        let cmd = T::augment_subcommands(Command::new("test").no_binary_name(true));
+        T::from_arg_matches(&cmd.try_get_matches_from(words)?)
+    }
+
+    /// Parse `line` as one of the subcommands in `T`, which must succeed.
+    pub(super) fn parse_cmd<T: Subcommand + FromArgMatches>(line: &str) -> T {
+        try_parse_cmd(line).expect("command")
+    }
+
     // -- ShellError --------------------------------------------------------
 
     #[test]
@@ -548,11 +1187,17 @@ mod tests {
         assert!(e.to_string().contains("gone"));
     }
 
+    #[test]
+    fn shell_error_from_vfs_error() {
+        let e: ShellError = crate::VfsError::NotFound("/nope".into()).into();
+        assert_eq!(e.to_string(), "VFS error: /nope: no such file or directory");
+    }
+
     // -- ShellConfig builder -----------------------------------------------
 
     #[test]
     fn config_sets_name() {
-        let sh = config("mysh").build();
+        let sh = config("mysh").build().expect("build");
         // Verify it built without panic — the name is internal, so just
         // confirm the returned Arc is usable as a trait object.
         let _: &dyn Shell = &*sh;
@@ -560,7 +1205,7 @@ mod tests {
 
     #[test]
     fn config_name_override() {
-        let sh = config("original").name("override").build();
+        let sh = config("original").name("override").build().expect("build");
         let _: &dyn Shell = &*sh;
     }
 
@@ -576,7 +1221,8 @@ mod tests {
             .shell_args(noop_aug.clone())
             .shell_cmds(noop_aug.clone())
             .shell_handler(noop_hnd.clone())
-            .build();
+            .build()
+            .expect("build");
         let _: &dyn Shell = &*sh;
     }
 
@@ -590,7 +1236,7 @@ mod tests {
         }
 
         let lookup: VfsLookup = Arc::new(|_| Ok(Box::new(TestFs)));
-        let sh = config("vfssh").vfs_lookup(lookup).build();
+        let sh = config("vfssh").vfs_lookup(lookup).build().expect("build");
         let _: &dyn Shell = &*sh;
     }
 
@@ -598,23 +1244,11 @@ mod tests {
 
     #[test]
     fn builtin_version_succeeds() {
-        let sh = config("test-version").build();
+        let sh = config("test-version").build().expect("build");
         let result = sh.run_args(&[os("test-version"), os("version")]);
         assert!(result.is_ok());
     }
 
-    #[test]
-    fn builtin_shell_returns_not_implemented() {
-        let sh = config("test-shell").build();
-        let result = sh.run_args(&[os("test-shell"), os("shell")]);
-        match result {
-            Err(ShellError::Fatal(msg)) => {
-                assert!(msg.contains("not implemented"), "unexpected: {msg}");
-            }
-            other => panic!("expected Fatal error, got: {other:?}"),
-        }
-    }
-
     #[test]
     fn builtin_pwd_with_vfs_succeeds() {
         struct TestFs(PathBuf);
@@ -625,11 +1259,33 @@ mod tests {
         }
 
         let lookup: VfsLookup = Arc::new(|_| Ok(Box::new(TestFs(PathBuf::from("/test/dir")))));
-        let sh = config("test-pwd").vfs_lookup(lookup).build();
+        let sh = config("test-pwd")
+            .vfs_lookup(lookup)
+            .build()
+            .expect("build");
         let result = sh.run_args(&[os("test-pwd"), os("pwd")]);
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn builtin_command_trees_are_consistent() {
+        // Catches clashes between built-in flags and the global CLI flags
+        let lookup: VfsLookup = Arc::new(|_| Err(ShellError::Internal("unused".into())));
+        let sh = build_basic_shell(config("test-trees").vfs_lookup(lookup));
+        sh.build_cmd().debug_assert();
+        sh.build_shell_cmd().debug_assert();
+    }
+
+    #[test]
+    fn complete_through_shell_trait() {
+        let sh = config("test-complete")
+            .no_init_tracing()
+            .build()
+            .expect("build");
+        assert_eq!(sh.complete("ver", 3), (0, vec!["version ".to_string()]));
+        assert_eq!(sh.complete("version ", 8), (8, Vec::new()));
+    }
+
     // -- Custom augmentors and handlers ------------------------------------
 
     #[derive(Subcommand)]
@@ -641,41 +1297,83 @@ mod tests {
     fn custom_handler_is_invoked() {
         static CALL_COUNT: AtomicUsize = AtomicUsize::new(0);
 
-        let cmds: Augmentor = Arc::new(CustomCmds::augment_subcommands);
-        let handler: Handler = Arc::new(|_, m| match CustomCmds::from_arg_matches(m) {
-            Ok(CustomCmds::Greet) => {
+        let sh = config("custom")
+            .cli_command(|_, CustomCmds::Greet| {
                 CALL_COUNT.fetch_add(1, Ordering::SeqCst);
                 HANDLER_SUCCESS
-            }
-            Err(_) => Err(ShellError::CommandNotFound),
-        });
-
-        let sh = config("custom").cli_cmds(cmds).cli_handler(handler).build();
+            })
+            .build()
+            .expect("build");
         let result = sh.run_args(&[os("custom"), os("greet")]);
         assert!(result.is_ok());
         assert!(CALL_COUNT.load(Ordering::SeqCst) >= 1);
     }
 
+    #[derive(Subcommand)]
+    enum NamedCmds {
+        Hello { name: String },
+    }
+
+    #[test]
+    fn command_registers_for_cli_and_shell() {
+        static GREETED: AtomicUsize = AtomicUsize::new(0);
+
+        let sh = build_basic_shell(config("typed").no_init_tracing().command(
+            |_, NamedCmds::Hello { name }| {
+                assert_eq!(name, "esh");
+                GREETED.fetch_add(1, Ordering::SeqCst);
+                HANDLER_SUCCESS
+            },
+        ));
+        let before = GREETED.load(Ordering::SeqCst);
+        sh.run_args(&[os("typed"), os("hello"), os("esh")])
+            .expect("cli");
+        sh.run_line("hello esh").expect("shell");
+        // Other subcommands still reach their own handlers
+        sh.run_line("version").expect("version");
+        assert_eq!(GREETED.load(Ordering::SeqCst), before + 2);
+    }
+
+    #[test]
+    fn parse_subcommand_only_takes_its_own_subcommands() {
+        let cmd = Command::new("t")
+            .subcommand(Command::new("hello").arg(clap::Arg::new("name")))
+            .subcommand(Command::new("other"));
+        let matches = cmd.clone().get_matches_from(["t", "other"]);
+        assert!(matches!(
+            parse_subcommand::<NamedCmds>(&matches),
+            Err(ShellError::CommandNotFound)
+        ));
+
+        // Ours, but without the argument `NamedCmds` needs
+        let matches = cmd.get_matches_from(["t", "hello"]);
+        assert!(matches!(
+            parse_subcommand::<NamedCmds>(&matches),
+            Err(ShellError::ArgumentError(_))
+        ));
+    }
+
     #[test]
     fn handler_chain_falls_through_command_not_found() {
         static SECOND_CALLED: AtomicUsize = AtomicUsize::new(0);
 
         let first_handler: Handler = Arc::new(|_, _| Err(ShellError::CommandNotFound));
-        let second_handler: Handler =
-            Arc::new(|_, m| match BasicSharedCommands::from_arg_matches(m) {
-                Ok(BasicSharedCommands::Version) => {
-                    SECOND_CALLED.fetch_add(1, Ordering::SeqCst);
-                    HANDLER_SUCCESS
-                }
-                Err(_) => Err(ShellError::CommandNotFound),
-            });
+        let second_handler: Handler = Arc::new(|_, m| match OrphanCmd::from_arg_matches(m) {
+            Ok(OrphanCmd::Orphan) => {
+                SECOND_CALLED.fetch_add(1, Ordering::SeqCst);
+                HANDLER_SUCCESS
+            }
+            Err(_) => Err(ShellError::CommandNotFound),
+        });
 
         let sh = config("chain")
+            .cli_cmds(Arc::new(OrphanCmd::augment_subcommands))
             .cli_handler(first_handler)
             .cli_handler(second_handler)
-            .build();
+            .build()
+            .expect("build");
 
-        let result = sh.run_args(&[os("chain"), os("version")]);
+        let result = sh.run_args(&[os("chain"), os("orphan")]);
         assert!(result.is_ok());
         assert!(SECOND_CALLED.load(Ordering::SeqCst) >= 1);
     }
@@ -691,11 +1389,13 @@ mod tests {
         });
 
         let sh = config("chain-err")
+            .cli_cmds(Arc::new(OrphanCmd::augment_subcommands))
             .cli_handler(failing_handler)
             .cli_handler(second_handler)
-            .build();
+            .build()
+            .expect("build");
 
-        let result = sh.run_args(&[os("chain-err"), os("version")]);
+        let result = sh.run_args(&[os("chain-err"), os("orphan")]);
         match result {
             Err(ShellError::Internal(msg)) => assert_eq!(msg, "fatal"),
             other => panic!("expected Internal error, got: {other:?}"),
@@ -703,6 +1403,34 @@ mod tests {
         assert_eq!(SECOND_CALLED.load(Ordering::SeqCst), 0);
     }
 
+    #[test]
+    fn invalidate_commands_rebuilds_the_tree() {
+        let colors = Arc::new(Mutex::new(vec!["red"]));
+        let current = Arc::clone(&colors);
+        let sh = config("invalidate")
+            .cli_cmds(Arc::new(move |cmd| {
+                let values = current.lock().map(|v| v.clone()).unwrap_or_default();
+                cmd.subcommand(
+                    Command::new("paint").arg(
+                        clap::Arg::new("color")
+                            .value_parser(clap::builder::PossibleValuesParser::new(values)),
+                    ),
+                )
+            }))
+            .cli_handler(Arc::new(|_, _| HANDLER_SUCCESS))
+            .build()
+            .expect("build");
+
+        let paint = |color: &str| sh.run_args(&[os("invalidate"), os("paint"), os(color)]);
+        assert_eq!(paint("red").ok(), Some(ExitCode::SUCCESS));
+        colors.lock().expect("colors").push("blue");
+        // Still the cached tree
+        assert_eq!(paint("blue").ok(), Some(ExitCode::from(2)));
+
+        sh.invalidate_commands();
+        assert_eq!(paint("blue").ok(), Some(ExitCode::SUCCESS));
+    }
+
     #[test]
     fn handler_chain_first_match_wins() {
         static FIRST_CALLED: AtomicUsize = AtomicUsize::new(0);
@@ -718,14 +1446,16 @@ mod tests {
         });
 
         let sh = config("first-wins")
+            .cli_cmds(Arc::new(OrphanCmd::augment_subcommands))
             .cli_handler(first_handler)
             .cli_handler(second_handler)
-            .build();
+            .build()
+            .expect("build");
 
         let before_first = FIRST_CALLED.load(Ordering::SeqCst);
         let before_second = SECOND_CALLED.load(Ordering::SeqCst);
 
-        let result = sh.run_args(&[os("first-wins"), os("version")]);
+        let result = sh.run_args(&[os("first-wins"), os("orphan")]);
         assert!(result.is_ok());
         assert_eq!(FIRST_CALLED.load(Ordering::SeqCst), before_first + 1);
         assert_eq!(SECOND_CALLED.load(Ordering::SeqCst), before_second);
@@ -744,7 +1474,8 @@ mod tests {
         let sh = config("nomatch")
             .cli_cmds(cmds)
             .cli_handler(never_handler)
-            .build();
+            .build()
+            .expect("build");
 
         let result = sh.run_args(&[os("nomatch"), os("orphan")]);
         match result {
@@ -755,6 +1486,82 @@ mod tests {
         }
     }
 
+    #[test]
+    fn owned_subcommands_bypass_the_handler_chain() {
+        static CHAINED: AtomicUsize = AtomicUsize::new(0);
+
+        let greedy: Handler = Arc::new(|_, _| {
+            CHAINED.fetch_add(1, Ordering::SeqCst);
+            HANDLER_SUCCESS
+        });
+        let sh = config("owned")
+            .cli_handler(greedy)
+            .cli_command(|_, CustomCmds::Greet| Err(ShellError::Fatal("greeted".into())))
+            .build()
+            .expect("build");
+
+        let before = CHAINED.load(Ordering::SeqCst);
+        let result = sh.run_args(&[os("owned"), os("greet")]);
+        assert!(matches!(result, Err(ShellError::Fatal(msg)) if msg == "greeted"));
+        assert!(sh.run_args(&[os("owned"), os("version")]).is_ok());
+        assert_eq!(CHAINED.load(Ordering::SeqCst), before);
+    }
+
+    #[test]
+    fn duplicate_subcommands_fail_the_build() {
+        let greet = |_: &dyn Shell, CustomCmds::Greet| HANDLER_SUCCESS;
+        let result = config("dup-cmd")
+            .command(greet)
+            .shell_command(greet)
+            .build();
+        assert!(matches!(result, Err(ShellError::DuplicateCommand(name)) if name == "greet"));
+
+        // Also against the built-in commands, and untyped registrations
+        let result = config("dup-builtin")
+            .cli_cmds(Arc::new(BasicSharedCommands::augment_subcommands))
+            .build();
+        assert!(matches!(result, Err(ShellError::DuplicateCommand(name)) if name == "version"));
+
+        // Aliases clash as well
+        let result = config("dup-alias")
+            .cli_cmds(Arc::new(|cmd: Command| {
+                cmd.subcommand(Command::new("about").alias("version"))
+            }))
+            .build();
+        assert!(matches!(result, Err(ShellError::DuplicateCommand(name)) if name == "version"));
+
+        // The same name in the CLI and in the shell is fine
+        let result = config("dup-groups")
+            .cli_command(greet)
+            .shell_command(greet)
+            .build();
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn default_handler_runs_without_a_subcommand() {
+        static DEFAULTED: AtomicUsize = AtomicUsize::new(0);
+
+        let handler: Handler = Arc::new(|_, m| {
+            assert_eq!(m.get_count("verbose"), 2);
+            DEFAULTED.fetch_add(1, Ordering::SeqCst);
+            Ok(ExitCode::from(3))
+        });
+        let sh = config("defaulted")
+            .no_init_tracing()
+            .default_handler(handler)
+            .build()
+            .expect("build");
+
+        let before = DEFAULTED.load(Ordering::SeqCst);
+        let result = sh.run_args(&[os("defaulted"), os("-vv")]);
+        assert_eq!(result.ok(), Some(ExitCode::from(3)));
+        assert!(sh
+            .run_args(&[os("defaulted"), os("-vv"), os("version")])
+            .is_ok());
+        assert_eq!(DEFAULTED.load(Ordering::SeqCst), before + 1);
+    }
+
     // -- Custom augmentor adds arguments -----------------------------------
 
     #[derive(Parser, Debug)]
@@ -777,10 +1584,12 @@ mod tests {
 
         let sh = config("augargs")
             .cli_args(args_aug)
+            .cli_cmds(Arc::new(OrphanCmd::augment_subcommands))
             .cli_handler(handler)
-            .build();
+            .build()
+            .expect("build");
 
-        let result = sh.run_args(&[os("augargs"), os("--dry-run"), os("version")]);
+        let result = sh.run_args(&[os("augargs"), os("--dry-run"), os("orphan")]);
         assert!(result.is_ok());
         assert!(DRY_RUN_SEEN.load(Ordering::SeqCst) >= 1);
     }
@@ -790,8 +1599,8 @@ mod tests {
     #[test]
     fn vfs_lookup_error_propagates() {
         let lookup: VfsLookup = Arc::new(|_| Err(ShellError::Internal("vfs init failed".into())));
-        let sh = config("vfsfail").vfs_lookup(lookup).build();
-        let result = sh.run_args(&[os("vfsfail"), os("version")]);
+        let sh = config("vfsfail").vfs_lookup(lookup).build().expect("build");
+        let result = sh.run_args(&[os("vfsfail"), os("pwd")]);
         match result {
             Err(ShellError::Internal(msg)) => {
                 assert!(msg.contains("vfs init failed"), "unexpected: {msg}");
@@ -800,6 +1609,45 @@ mod tests {
         }
     }
 
+    #[test]
+    fn vfs_is_opened_on_first_use() {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&lookups);
+        let lookup: VfsLookup = Arc::new(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(Box::new(crate::MemVfs::new()))
+        });
+        let sh = build_basic_shell(config("vfslazy").no_init_tracing().vfs_lookup(lookup));
+
+        sh.run_args(&[os("vfslazy"), os("version")])
+            .expect("version");
+        assert_eq!(lookups.load(Ordering::SeqCst), 0);
+        sh.run_line("pwd").expect("pwd");
+        sh.run_line("ls").expect("ls");
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn declared_vfs_commands_fail_before_their_handler() {
+        static GREETED: AtomicUsize = AtomicUsize::new(0);
+
+        let lookup: VfsLookup = Arc::new(|_| Err(ShellError::Internal("vfs init failed".into())));
+        let sh = config("vfsdeclared")
+            .no_init_tracing()
+            .vfs_lookup(lookup)
+            .cli_command(|_, CustomCmds::Greet| {
+                GREETED.fetch_add(1, Ordering::SeqCst);
+                HANDLER_SUCCESS
+            })
+            .vfs_command("greet")
+            .build()
+            .expect("build");
+
+        let result = sh.run_args(&[os("vfsdeclared"), os("greet")]);
+        assert!(matches!(result, Err(ShellError::Internal(msg)) if msg == "vfs init failed"));
+        assert_eq!(GREETED.load(Ordering::SeqCst), 0);
+    }
+
     #[test]
     fn vfs_cwd_is_accessible_from_handler() {
         static CWD_MATCHED: AtomicUsize = AtomicUsize::new(0);
@@ -812,7 +1660,7 @@ mod tests {
         }
 
         let lookup: VfsLookup = Arc::new(|_| Ok(Box::new(TestFs)));
-        let sh = config("vfscwd").vfs_lookup(lookup).build();
+        let sh = config("vfscwd").vfs_lookup(lookup).build().expect("build");
 
         let result = sh.run_args(&[os("vfscwd"), os("pwd")]);
         assert!(result.is_ok());
@@ -826,32 +1674,81 @@ mod tests {
         });
         let sh2 = config("vfscwd2")
             .vfs_lookup(lookup2)
+            .cli_cmds(Arc::new(OrphanCmd::augment_subcommands))
             .cli_handler(handler)
-            .build();
-        let result2 = sh2.run_args(&[os("vfscwd2"), os("version")]);
+            .build()
+            .expect("build");
+        let result2 = sh2.run_args(&[os("vfscwd2"), os("orphan")]);
         assert!(result2.is_ok());
         assert!(CWD_MATCHED.load(Ordering::SeqCst) >= 1);
     }
 
+    #[test]
+    fn vfs_mounts_share_one_namespace() {
+        let root: VfsLookup = Arc::new(|_| Ok(Box::new(crate::MemVfs::new().with_dir("/etc"))));
+        let data: VfsLookup =
+            Arc::new(|_| Ok(Box::new(crate::MemVfs::from_files([("/a.csv", "")]))));
+        let sh = build_basic_shell(
+            config("vfsmounts")
+                .no_init_tracing()
+                .vfs_lookup(root)
+                .vfs_mount("data/", data),
+        );
+        let result = sh.run_args(&[os("vfsmounts"), os("pwd")]);
+        assert!(result.is_ok());
+
+        let exists = |path: &str| sh.with_vfs(|vfs| Ok(vfs.exists(Path::new(path))?));
+        assert!(exists("/etc").expect("exists"));
+        assert!(exists("/data/a.csv").expect("exists"));
+
+        // umount and mount run the lookup again
+        sh.run_line("umount /data").expect("umount");
+        assert!(!exists("/data/a.csv").expect("exists"));
+        sh.run_line("mount /data").expect("mount");
+        assert!(exists("/data/a.csv").expect("exists"));
+    }
+
+    #[test]
+    fn no_host_access_leaves_out_transfers() {
+        let lookup: VfsLookup = Arc::new(|_| Ok(Box::new(crate::MemVfs::new())));
+        let open = build_basic_shell(
+            config("hostsh")
+                .no_init_tracing()
+                .vfs_lookup(lookup.clone()),
+        );
+        assert!(open.build_shell_cmd().find_subcommand("get").is_some());
+        assert!(open.build_shell_cmd().find_subcommand("put").is_some());
+
+        let closed = build_basic_shell(
+            config("hostsh")
+                .no_init_tracing()
+                .vfs_lookup(lookup)
+                .no_host_access(),
+        );
+        assert!(closed.build_shell_cmd().find_subcommand("get").is_none());
+        assert!(closed.build_shell_cmd().find_subcommand("put").is_none());
+        assert!(closed.build_shell_cmd().find_subcommand("ls").is_some());
+    }
+
     // -- Verbose / quiet flags ---------------------------------------------
 
     #[test]
     fn verbose_flag_accepted() {
-        let sh = config("test-verbose").build();
+        let sh = config("test-verbose").build().expect("build");
         let result = sh.run_args(&[os("test-verbose"), os("-v"), os("version")]);
         assert!(result.is_ok());
     }
 
     #[test]
     fn quiet_flag_accepted() {
-        let sh = config("test-quiet").build();
+        let sh = config("test-quiet").build().expect("build");
         let result = sh.run_args(&[os("test-quiet"), os("-q"), os("version")]);
         assert!(result.is_ok());
     }
 
     #[test]
     fn multiple_verbose_flags_accepted() {
-        let sh = config("test-vvv").build();
+        let sh = config("test-vvv").build().expect("build");
         let result = sh.run_args(&[os("test-vvv"), os("-vvv"), os("version")]);
         assert!(result.is_ok());
     }
@@ -860,7 +1757,7 @@ mod tests {
 
     #[test]
     fn build_returns_arc_dyn_shell() {
-        let sh: Arc<dyn Shell> = config("dyn").build();
+        let sh: Arc<dyn Shell> = config("dyn").build().expect("build");
         // Confirm it can be cloned and shared
         let sh2 = Arc::clone(&sh);
         drop(sh2);
@@ -868,8 +1765,8 @@ mod tests {
 
     #[test]
     fn multiple_shells_coexist() {
-        let sh1 = config("shell-a").build();
-        let sh2 = config("shell-b").build();
+        let sh1 = config("shell-a").build().expect("build");
+        let sh2 = config("shell-b").build().expect("build");
         let r1 = sh1.run_args(&[os("shell-a"), os("version")]);
         let r2 = sh2.run_args(&[os("shell-b"), os("version")]);
         assert!(r1.is_ok());
@@ -878,16 +1775,37 @@ mod tests {
 
     // -- Concurrent VFS access ---------------------------------------------
 
-    fn build_basic_shell(cfg: ShellConfig) -> Arc<BasicShell> {
-        BasicShell::new(
-            cfg.name,
-            cfg.pkg_name,
-            cfg.version,
-            cfg.shell_group,
-            cfg.cli_group,
-            cfg.vfs_lookup,
-            cfg.init_tracing,
-        )
+    pub(super) fn build_basic_shell(cfg: ShellConfig) -> Arc<BasicShell> {
+        BasicShell::new(cfg).expect("build")
+    }
+
+    /// A `HostVfs` whose watchers poll every few milliseconds, for commands
+    /// that wait for changes.
+    pub(super) struct QuickPoll(pub(super) crate::vfs::HostVfs);
+
+    impl Vfs for QuickPoll {
+        fn cwd(&self) -> &Path {
+            self.0.cwd()
+        }
+
+        fn read_dir(&self, path: &Path) -> crate::VfsResult<Vec<crate::VfsDirEntry>> {
+            self.0.read_dir(path)
+        }
+
+        fn metadata(&self, path: &Path) -> crate::VfsResult<crate::VfsMetadata> {
+            self.0.metadata(path)
+        }
+
+        fn open_read(&self, path: &Path) -> crate::VfsResult<Box<dyn std::io::Read + Send>> {
+            self.0.open_read(path)
+        }
+
+        fn watch(&self, path: &Path) -> crate::VfsResult<Box<dyn crate::VfsWatcher>> {
+            let watcher = crate::PollWatcher::new(self, path)?;
+            Ok(Box::new(
+                watcher.interval(std::time::Duration::from_millis(5)),
+            ))
+        }
     }
 
     #[test]
@@ -977,7 +1895,7 @@ mod tests {
     #[test]
     fn shell_config_macro_with_name() {
         let cfg = shell_config!("my-test-shell");
-        let sh = cfg.build();
+        let sh = cfg.build().expect("build");
         let result = sh.run_args(&[os("my-test-shell"), os("version")]);
         assert!(result.is_ok());
     }
@@ -985,7 +1903,7 @@ mod tests {
     #[test]
     fn shell_config_macro_with_custom_name() {
         let cfg = shell_config!("another-shell");
-        let sh = cfg.no_init_tracing().build();
+        let sh = cfg.no_init_tracing().build().expect("build");
         let result = sh.run_args(&[os("another-shell"), os("version")]);
         assert!(result.is_ok());
     }
@@ -994,7 +1912,7 @@ mod tests {
 
     #[test]
     fn no_init_tracing_skips_subscriber_setup() {
-        let sh = config("no-trace").no_init_tracing().build();
+        let sh = config("no-trace").no_init_tracing().build().expect("build");
         // Should succeed without touching the global subscriber
         let result = sh.run_args(&[os("no-trace"), os("version")]);
         assert!(result.is_ok());
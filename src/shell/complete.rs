@@ -0,0 +1,626 @@
+use std::path::Path;
+
+use clap::{Arg, ArgAction, Command, ValueHint};
+
+use crate::parse::{shell_parse_partial, shell_quote_as};
+
+use super::BasicShell;
+use crate::vfs::VfsFileType;
+
+/// A single completion candidate for the word under the cursor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Candidate {
+    /// What to show in the list of candidates.
+    pub(super) display: String,
+    /// What to replace the word under the cursor with, quoted as needed.
+    pub(super) replacement: String,
+}
+
+/// An unquoted completion value.
+struct Value {
+    text: String,
+    /// `true` if nothing can follow the value in the same word, i.e. the
+    /// word is finished and a separating space should be appended.
+    complete: bool,
+}
+
+impl Value {
+    fn done(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            complete: true,
+        }
+    }
+}
+
+/// Where in the command tree the word under the cursor belongs.
+struct Position<'a> {
+    cmd: &'a Command,
+    /// An option that is still waiting for its value.
+    pending: Option<&'a Arg>,
+    /// Number of positional values already given to `cmd`.
+    positionals: usize,
+    /// `--` was seen, everything that follows is positional.
+    escaped: bool,
+}
+
+impl<'a> Position<'a> {
+    /// Walk the words typed so far through the (built) command tree.
+    fn walk(root: &'a Command, words: &[String]) -> Self {
+        let mut pos = Self {
+            cmd: root,
+            pending: None,
+            positionals: 0,
+            escaped: false,
+        };
+
+        for word in words {
+            if pos.pending.take().is_some() {
+                continue;
+            }
+            if pos.escaped {
+                pos.positionals += 1;
+            } else if word == "--" {
+                pos.escaped = true;
+            } else if let Some(long) = word.strip_prefix("--") {
+                if !long.contains('=') {
+                    pos.pending = find_long(pos.cmd, long).filter(|a| takes_value(a));
+                }
+            } else if let Some(shorts) = word.strip_prefix('-').filter(|s| !s.is_empty()) {
+                for (i, c) in shorts.char_indices() {
+                    if let Some(arg) = find_short(pos.cmd, c).filter(|a| takes_value(a)) {
+                        // The value is either the rest of the cluster or the next word
+                        if shorts.len() == i + c.len_utf8() {
+                            pos.pending = Some(arg);
+                        }
+                        break;
+                    }
+                }
+            } else if let Some(sub) = pos.cmd.find_subcommand(word) {
+                pos.cmd = sub;
+                pos.positionals = 0;
+            } else {
+                pos.positionals += 1;
+            }
+        }
+
+        pos
+    }
+
+    /// The positional argument the next plain word is assigned to.
+    fn next_positional(&self) -> Option<&'a Arg> {
+        let mut remaining = self.positionals;
+        for arg in self.cmd.get_positionals() {
+            let multiple = matches!(arg.get_action(), ArgAction::Append)
+                || arg.get_num_args().is_some_and(|r| r.max_values() > 1);
+            if multiple || remaining == 0 {
+                return Some(arg);
+            }
+            remaining -= 1;
+        }
+        None
+    }
+}
+
+fn takes_value(arg: &Arg) -> bool {
+    arg.get_action().takes_values()
+}
+
+fn find_long<'a>(cmd: &'a Command, name: &str) -> Option<&'a Arg> {
+    cmd.get_arguments().find(|a| {
+        a.get_long() == Some(name)
+            || a.get_all_aliases()
+                .is_some_and(|aliases| aliases.contains(&name))
+    })
+}
+
+fn find_short(cmd: &Command, c: char) -> Option<&Arg> {
+    cmd.get_arguments().find(|a| {
+        a.get_short() == Some(c)
+            || a.get_all_short_aliases()
+                .is_some_and(|aliases| aliases.contains(&c))
+    })
+}
+
+impl BasicShell {
+    /// Complete the word under the cursor at byte offset `pos` of `line`,
+    /// using the interactive command tree `cmd`.
+    ///
+    /// `cmd` must have been prepared with [`Command::build`]. Returns the
+    /// byte offset where the word under the cursor starts (the part of the
+    /// line each candidate replaces) and the sorted candidates.
+    pub(super) fn complete_line(
+        &self,
+        cmd: &Command,
+        line: &str,
+        pos: usize,
+    ) -> (usize, Vec<Candidate>) {
+        let Some(partial) = line.get(..pos).and_then(shell_parse_partial) else {
+            return (pos, Vec::new());
+        };
+
+        let position = Position::walk(cmd, &partial.words);
+        let prefix = partial.prefix.as_str();
+
+        let (lead, values) = self.word_values(&position, prefix);
+
+        let mut candidates: Vec<Candidate> = values
+            .into_iter()
+            .filter(|v| v.text.starts_with(prefix.get(lead.len()..).unwrap_or("")))
+            .map(|v| {
+                let word = format!("{lead}{}", v.text);
+                let mut replacement = shell_quote_as(&word, partial.quote, v.complete);
+                if v.complete {
+                    replacement.push(' ');
+                }
+                Candidate {
+                    display: v.text,
+                    replacement,
+                }
+            })
+            .collect();
+        candidates.sort_by(|a, b| a.display.cmp(&b.display));
+        candidates.dedup();
+
+        (partial.start, candidates)
+    }
+
+    /// Candidate values for the word `prefix` at `position`, along with the
+    /// leading part of the word that is kept as is (the `--option=` of an
+    /// `--option=value` word).
+    fn word_values<'p>(&self, position: &Position, prefix: &'p str) -> (&'p str, Vec<Value>) {
+        if let Some(arg) = position.pending {
+            return ("", self.arg_values(arg, prefix));
+        }
+        if position.escaped {
+            return ("", self.positional_values(position, prefix));
+        }
+        if let Some((long, value)) = prefix
+            .strip_prefix("--")
+            .and_then(|rest| rest.split_once('='))
+        {
+            let lead = prefix.get(..prefix.len() - value.len()).unwrap_or_default();
+            let values = find_long(position.cmd, long)
+                .map(|arg| self.arg_values(arg, value))
+                .unwrap_or_default();
+            return (lead, values);
+        }
+        if prefix.starts_with('-') {
+            return ("", flag_values(position.cmd, prefix));
+        }
+
+        let mut values = Vec::new();
+        if position.positionals == 0 {
+            values.extend(subcommand_values(position.cmd, prefix));
+        }
+        values.extend(self.positional_values(position, prefix));
+        ("", values)
+    }
+
+    fn positional_values(&self, position: &Position, prefix: &str) -> Vec<Value> {
+        position
+            .next_positional()
+            .map(|arg| self.arg_values(arg, prefix))
+            .unwrap_or_default()
+    }
+
+    /// Values for `arg`: from a registered value completer if there is one,
+    /// from the VFS for path arguments, otherwise from the argument's
+    /// possible values.
+    fn arg_values(&self, arg: &Arg, prefix: &str) -> Vec<Value> {
+        if let Some(completer) = self.completers.get(arg.get_id().as_str()) {
+            return completer(self, prefix)
+                .into_iter()
+                .map(Value::done)
+                .collect();
+        }
+
+        match arg.get_value_hint() {
+            ValueHint::AnyPath | ValueHint::FilePath | ValueHint::ExecutablePath => {
+                return self.path_values(prefix, false);
+            }
+            ValueHint::DirPath => return self.path_values(prefix, true),
+            _ => {}
+        }
+
+        arg.get_possible_values()
+            .iter()
+            .filter(|v| !v.is_hide_set())
+            .map(|v| Value::done(v.get_name()))
+            .collect()
+    }
+}
+
+impl BasicShell {
+    /// Entries of the active VFS matching the partial path `prefix`, relative
+    /// to the VFS cwd. Directories get a trailing `/` and leave the word open
+    /// so the user can keep descending.
+    fn path_values(&self, prefix: &str, dirs_only: bool) -> Vec<Value> {
+        let (dir, name) = prefix
+            .rfind('/')
+            .map_or(("", prefix), |i| prefix.split_at(i + 1));
+
+        let Ok(entries) = self.with_vfs(|vfs| Ok(vfs.read_dir(&vfs.resolve(Path::new(dir)))?))
+        else {
+            return Vec::new();
+        };
+
+        entries
+            .into_iter()
+            .filter_map(|entry| {
+                let entry_name = entry.name.to_str()?;
+                // Dot files only show up when asked for
+                if !entry_name.starts_with(name)
+                    || (entry_name.starts_with('.') && !name.starts_with('.'))
+                {
+                    return None;
+                }
+                let is_dir = entry.file_type == VfsFileType::Dir;
+                if dirs_only && !is_dir {
+                    return None;
+                }
+                Some(Value {
+                    text: format!("{dir}{entry_name}{}", if is_dir { "/" } else { "" }),
+                    complete: !is_dir,
+                })
+            })
+            .collect()
+    }
+}
+
+fn subcommand_values(cmd: &Command, prefix: &str) -> Vec<Value> {
+    cmd.get_subcommands()
+        .filter(|s| !s.is_hide_set())
+        .map(Command::get_name)
+        .filter(|name| name.starts_with(prefix))
+        .map(Value::done)
+        .collect()
+}
+
+fn flag_values(cmd: &Command, prefix: &str) -> Vec<Value> {
+    let mut values = Vec::new();
+    for arg in cmd.get_arguments().filter(|a| !a.is_hide_set()) {
+        if let Some(long) = arg.get_long() {
+            let flag = format!("--{long}");
+            values.push(Value {
+                complete: !takes_value(arg),
+                text: if takes_value(arg) {
+                    format!("{flag}=")
+                } else {
+                    flag
+                },
+            });
+        }
+        if prefix == "-" {
+            if let Some(short) = arg.get_short() {
+                values.push(Value::done(format!("-{short}")));
+            }
+        }
+    }
+    values
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::shell::tests::build_basic_shell;
+    use crate::shell::{ShellConfig, ValueCompleter};
+    use crate::vfs::{MountTable, Vfs, VfsDirEntry, VfsError, VfsResult};
+    use clap::{Args, Subcommand, ValueEnum};
+    use std::collections::BTreeMap;
+    use std::ffi::OsString;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    #[derive(Clone, ValueEnum)]
+    enum Color {
+        Red,
+        Green,
+        Blue,
+    }
+
+    #[derive(Args)]
+    struct PaintArgs {
+        #[arg(short, long, value_enum)]
+        color: Option<Color>,
+
+        #[arg(short, long)]
+        force: bool,
+
+        #[arg(long)]
+        host: Option<String>,
+
+        target: Option<Color>,
+    }
+
+    #[derive(Args)]
+    struct OpenArgs {
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        into: Option<String>,
+
+        file: Option<PathBuf>,
+    }
+
+    #[derive(Subcommand)]
+    enum TestCmds {
+        Paint(PaintArgs),
+        Open(OpenArgs),
+        Ping {
+            host: String,
+        },
+        #[command(hide = true)]
+        Secret,
+    }
+
+    /// A fixed directory tree, cwd is `/home`.
+    struct TreeFs(BTreeMap<PathBuf, Vec<VfsDirEntry>>);
+
+    impl TreeFs {
+        fn new() -> Self {
+            let entry = |name: &str, file_type| VfsDirEntry {
+                name: OsString::from(name),
+                file_type,
+            };
+            let mut tree = BTreeMap::new();
+            tree.insert(
+                PathBuf::from("/"),
+                vec![
+                    entry("home", VfsFileType::Dir),
+                    entry("etc", VfsFileType::Dir),
+                ],
+            );
+            tree.insert(
+                PathBuf::from("/home"),
+                vec![
+                    entry("notes.txt", VfsFileType::File),
+                    entry("my docs", VfsFileType::Dir),
+                    entry("it's.md", VfsFileType::File),
+                    entry(".hidden", VfsFileType::File),
+                    entry("link", VfsFileType::Symlink),
+                ],
+            );
+            tree.insert(
+                PathBuf::from("/home/my docs"),
+                vec![entry("a.txt", VfsFileType::File)],
+            );
+            tree.insert(
+                PathBuf::from("/etc"),
+                vec![entry("hosts", VfsFileType::File)],
+            );
+            Self(tree)
+        }
+    }
+
+    impl Vfs for TreeFs {
+        fn cwd(&self) -> &Path {
+            Path::new("/home")
+        }
+
+        fn read_dir(&self, path: &Path) -> VfsResult<Vec<VfsDirEntry>> {
+            self.0
+                .get(path)
+                .cloned()
+                .ok_or_else(|| VfsError::NotFound(path.into()))
+        }
+    }
+
+    fn shell() -> Arc<BasicShell> {
+        let hosts: ValueCompleter =
+            Arc::new(|_, _| vec!["alpha".into(), "beta".into(), "my host".into()]);
+        let sh = build_basic_shell(
+            ShellConfig::new("complete-test", "test-pkg", "0.0.1")
+                .no_init_tracing()
+                .shell_cmds(Arc::new(TestCmds::augment_subcommands))
+                .value_completer("host", hosts),
+        );
+        sh.vfs.lock().expect("vfs lock").mounts =
+            Some(MountTable::from(Box::new(TreeFs::new()) as Box<dyn Vfs>));
+        sh
+    }
+
+    fn complete(line: &str) -> (usize, Vec<String>) {
+        let sh = shell();
+        let mut cmd = sh.build_shell_cmd();
+        cmd.build();
+        let (start, candidates) = sh.complete_line(&cmd, line, line.len());
+        (
+            start,
+            candidates.into_iter().map(|c| c.replacement).collect(),
+        )
+    }
+
+    #[test]
+    fn completes_subcommand_names() {
+        let (start, c) = complete("pa");
+        assert_eq!(start, 0);
+        assert_eq!(c, vec!["paint "]);
+    }
+
+    #[test]
+    fn empty_line_lists_visible_subcommands() {
+        let (_, c) = complete("");
+        assert!(c.contains(&"paint ".to_string()));
+        assert!(c.contains(&"ping ".to_string()));
+        assert!(c.contains(&"exit ".to_string()));
+        assert!(c.contains(&"version ".to_string()));
+        assert!(!c.iter().any(|s| s.starts_with("secret")));
+    }
+
+    #[test]
+    fn completes_long_flags() {
+        let (start, c) = complete("paint --f");
+        assert_eq!(start, 6);
+        assert_eq!(c, vec!["--force "]);
+    }
+
+    #[test]
+    fn long_flag_with_value_gets_equals_sign() {
+        let (_, c) = complete("paint --co");
+        assert_eq!(c, vec!["--color="]);
+    }
+
+    #[test]
+    fn single_dash_lists_short_and_long_flags() {
+        let (_, c) = complete("paint -");
+        for flag in ["-c ", "-f ", "-h ", "--color=", "--force ", "--help "] {
+            assert!(c.contains(&flag.to_string()), "missing {flag} in {c:?}");
+        }
+    }
+
+    #[test]
+    fn completes_possible_values_after_option() {
+        let (start, c) = complete("paint --color ");
+        assert_eq!(start, 14);
+        assert_eq!(c, vec!["blue ", "green ", "red "]);
+
+        let (_, c) = complete("paint -c g");
+        assert_eq!(c, vec!["green "]);
+    }
+
+    #[test]
+    fn completes_possible_values_after_equals() {
+        let (start, c) = complete("paint --color=b");
+        assert_eq!(start, 6);
+        assert_eq!(c, vec!["--color=blue "]);
+    }
+
+    #[test]
+    fn completes_positional_possible_values() {
+        let (_, c) = complete("paint --force r");
+        assert_eq!(c, vec!["red "]);
+    }
+
+    #[test]
+    fn option_value_is_not_counted_as_positional() {
+        let (_, c) = complete("paint --color red ");
+        assert_eq!(c, vec!["blue ", "green ", "red "]);
+        let (_, c) = complete("paint red ");
+        assert!(c.is_empty(), "unexpected {c:?}");
+    }
+
+    #[test]
+    fn custom_completer_for_option_and_positional() {
+        let (_, c) = complete("paint --host ");
+        assert_eq!(c, vec!["alpha ", "beta ", r"my\ host "]);
+        let (_, c) = complete("ping b");
+        assert_eq!(c, vec!["beta "]);
+    }
+
+    #[test]
+    fn quoted_prefix_keeps_quote_style() {
+        let (start, c) = complete("ping \"my");
+        assert_eq!(start, 5);
+        assert_eq!(c, vec!["\"my host\" "]);
+
+        let (_, c) = complete("ping 'my h");
+        assert_eq!(c, vec!["'my host' "]);
+    }
+
+    #[test]
+    fn escaped_prefix_is_matched_unescaped() {
+        let (_, c) = complete(r"ping my\ ");
+        assert_eq!(c, vec![r"my\ host "]);
+    }
+
+    #[test]
+    fn unknown_word_yields_nothing() {
+        let (_, c) = complete("nosuchcmd ");
+        assert!(c.is_empty());
+    }
+
+    #[test]
+    fn comment_yields_nothing() {
+        let (_, c) = complete("paint # co");
+        assert!(c.is_empty());
+    }
+
+    // -- VFS paths ---------------------------------------------------------
+
+    #[test]
+    fn path_completion_lists_cwd_entries() {
+        let (start, c) = complete("open ");
+        assert_eq!(start, 5);
+        assert_eq!(c, vec![r"it\'s.md ", "link ", r"my\ docs/", "notes.txt "]);
+    }
+
+    #[test]
+    fn path_completion_filters_by_prefix() {
+        let (_, c) = complete("open no");
+        assert_eq!(c, vec!["notes.txt "]);
+    }
+
+    #[test]
+    fn path_completion_shows_dot_files_on_request() {
+        let (_, c) = complete("open .");
+        assert_eq!(c, vec![".hidden "]);
+    }
+
+    #[test]
+    fn path_completion_descends_into_directories() {
+        let (_, c) = complete(r"open my\ docs/");
+        assert_eq!(c, vec![r"my\ docs/a.txt "]);
+
+        let (_, c) = complete("open \"my docs/");
+        assert_eq!(c, vec!["\"my docs/a.txt\" "]);
+    }
+
+    #[test]
+    fn path_completion_quotes_directory_without_closing() {
+        let (_, c) = complete("open \"my");
+        assert_eq!(c, vec!["\"my docs/"]);
+    }
+
+    #[test]
+    fn path_completion_absolute_and_parent_paths() {
+        let (_, c) = complete("open /e");
+        assert_eq!(c, vec!["/etc/"]);
+        let (_, c) = complete("open /etc/");
+        assert_eq!(c, vec!["/etc/hosts "]);
+        let (_, c) = complete("open ../etc/h");
+        assert_eq!(c, vec!["../etc/hosts "]);
+    }
+
+    #[test]
+    fn path_completion_round_trips_through_parser() {
+        for c in complete("open ").1 {
+            let words = crate::shell_parse_line(&c).expect("candidate must parse");
+            assert_eq!(words.len(), 1, "candidate {c} should be a single word");
+        }
+    }
+
+    #[test]
+    fn dir_hint_only_lists_directories() {
+        let (_, c) = complete("open --into ");
+        assert_eq!(c, vec![r"my\ docs/"]);
+    }
+
+    #[test]
+    fn path_completion_without_vfs_yields_nothing() {
+        let sh = build_basic_shell(
+            ShellConfig::new("complete-novfs", "test-pkg", "0.0.1")
+                .no_init_tracing()
+                .shell_cmds(Arc::new(TestCmds::augment_subcommands)),
+        );
+        let mut cmd = sh.build_shell_cmd();
+        cmd.build();
+        let (_, c) = sh.complete_line(&cmd, "open ", 5);
+        assert!(c.is_empty());
+    }
+
+    #[test]
+    fn path_completion_with_unlisted_directory_yields_nothing() {
+        let (_, c) = complete("open nope/");
+        assert!(c.is_empty());
+    }
+
+    #[test]
+    fn cursor_in_the_middle_of_the_line() {
+        let sh = shell();
+        let mut cmd = sh.build_shell_cmd();
+        cmd.build();
+        let (start, c) = sh.complete_line(&cmd, "pa --force", 2);
+        assert_eq!(start, 0);
+        assert_eq!(c.first().map(|c| c.display.as_str()), Some("paint"));
+    }
+}
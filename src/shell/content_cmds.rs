@@ -0,0 +1,598 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::{Args, Subcommand, ValueHint};
+
+use super::interrupt::Interrupt;
+use super::watch_cmds::{SharedVfs, WAIT_TICK};
+use super::{BasicShell, HandlerResult, ShellError};
+use crate::vfs::{Vfs, VfsChangeKind, VfsError, VfsWatcher};
+
+/// Bytes per `hexdump -C` line.
+const HEXDUMP_WIDTH: usize = 16;
+
+#[derive(Subcommand)]
+pub enum VfsContentCommands {
+    /// Print the contents of files
+    Cat {
+        /// Files to print
+        #[arg(required = true, value_hint = ValueHint::FilePath)]
+        paths: Vec<PathBuf>,
+    },
+
+    /// Print the first lines of files
+    Head(LinesArgs),
+
+    /// Print the last lines of files
+    Tail(TailArgs),
+
+    /// Print files in hexadecimal and ASCII
+    Hexdump(HexdumpArgs),
+}
+
+#[derive(Args)]
+pub struct LinesArgs {
+    /// Number of lines to print
+    #[arg(short = 'n', long = "lines", value_name = "NUM", default_value_t = 10)]
+    lines: usize,
+
+    /// Files to print
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    paths: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct TailArgs {
+    /// Keep printing what is appended to the files, until they are removed
+    #[arg(short = 'f', long = "follow")]
+    follow: bool,
+
+    #[command(flatten)]
+    lines: LinesArgs,
+}
+
+#[derive(Args)]
+pub struct HexdumpArgs {
+    /// Canonical hex+ASCII display (the only supported format)
+    #[arg(short = 'C')]
+    canonical: bool,
+
+    /// Skip OFFSET bytes from the beginning of the input
+    #[arg(short = 's', value_name = "OFFSET")]
+    skip: Option<u64>,
+
+    /// Interpret only LENGTH bytes of input
+    #[arg(short = 'n', value_name = "LENGTH")]
+    length: Option<u64>,
+
+    /// Print identical lines instead of replacing them with `*`
+    // Not -v, that is the global verbose flag on the command line
+    #[arg(long = "no-squeezing")]
+    no_squeezing: bool,
+
+    /// Files to dump, concatenated
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    paths: Vec<PathBuf>,
+}
+
+pub fn handle_vfs_content_command(sh: &BasicShell, cmd: VfsContentCommands) -> HandlerResult {
+    run(sh, cmd, &mut std::io::stdout().lock())
+}
+
+fn run(vfs: &impl SharedVfs, cmd: VfsContentCommands, out: &mut dyn Write) -> HandlerResult {
+    match cmd {
+        VfsContentCommands::Cat { paths } => vfs.step(|vfs| {
+            for_each_file(vfs, "cat", &paths, out, |_, reader, out| {
+                std::io::copy(reader, out).map(|_| ())
+            })
+        }),
+        VfsContentCommands::Head(args) => {
+            let mut header = Header::new(&args.paths);
+            vfs.step(|vfs| {
+                for_each_file(vfs, "head", &args.paths, out, |path, reader, out| {
+                    header.write(out, path)?;
+                    head(&mut BufReader::new(reader), out, args.lines)
+                })
+            })
+        }
+        VfsContentCommands::Tail(TailArgs { follow, lines }) => {
+            let mut header = Header::new(&lines.paths);
+            let mut printed = Vec::new();
+            let status = vfs.step(|vfs| {
+                for_each_file(vfs, "tail", &lines.paths, out, |path, reader, out| {
+                    header.write(out, path)?;
+                    let read = tail(&mut BufReader::new(reader), out, lines.lines)?;
+                    printed.push((path.to_path_buf(), read));
+                    Ok(())
+                })
+            })?;
+            if follow {
+                let interrupt = Interrupt::catch();
+                follow_files(vfs, printed, &mut header, &|| interrupt.raised(), out)?;
+            }
+            Ok(status)
+        }
+        VfsContentCommands::Hexdump(args) => vfs.step(|vfs| hexdump(vfs, &args, out)),
+    }
+}
+
+/// Open each of `paths` and hand it to `f`. Files that cannot be opened are
+/// reported and skipped, the status then is a failure.
+fn for_each_file(
+    vfs: &dyn Vfs,
+    cmd: &str,
+    paths: &[PathBuf],
+    out: &mut dyn Write,
+    mut f: impl FnMut(&Path, &mut dyn Read, &mut dyn Write) -> std::io::Result<()>,
+) -> HandlerResult {
+    let mut status = ExitCode::SUCCESS;
+    for path in paths {
+        match vfs.open_read(path) {
+            Ok(mut reader) => f(path, &mut reader, out)?,
+            Err(e) => {
+                eprintln!("{cmd}: {e}");
+                status = ExitCode::FAILURE;
+            }
+        }
+    }
+    Ok(status)
+}
+
+/// The `==> path <==` lines separating the output of several files in head
+/// and tail.
+struct Header {
+    enabled: bool,
+    first: bool,
+}
+
+impl Header {
+    const fn new(paths: &[PathBuf]) -> Self {
+        Self {
+            enabled: paths.len() > 1,
+            first: true,
+        }
+    }
+
+    fn write(&mut self, out: &mut dyn Write, path: &Path) -> std::io::Result<()> {
+        if self.enabled {
+            if !self.first {
+                writeln!(out)?;
+            }
+            writeln!(out, "==> {} <==", path.display())?;
+            self.first = false;
+        }
+        Ok(())
+    }
+}
+
+fn head(reader: &mut dyn BufRead, out: &mut dyn Write, lines: usize) -> std::io::Result<()> {
+    let mut line = Vec::new();
+    for _ in 0..lines {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        out.write_all(&line)?;
+    }
+    Ok(())
+}
+
+/// Print the last `lines` lines of `reader`, returning how many bytes it had.
+fn tail(reader: &mut dyn BufRead, out: &mut dyn Write, lines: usize) -> std::io::Result<u64> {
+    if lines == 0 {
+        return std::io::copy(reader, &mut std::io::sink());
+    }
+    // Only the last `lines` lines are kept, however long the file is
+    let mut last: VecDeque<Vec<u8>> = VecDeque::with_capacity(lines.min(1024));
+    let mut line = Vec::new();
+    let mut read = 0;
+    loop {
+        let n = reader.read_until(b'\n', &mut line)?;
+        if n == 0 {
+            break;
+        }
+        read += n as u64;
+        // Recycle the buffer of the line that drops out
+        let spare = if last.len() == lines {
+            last.pop_front().unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        last.push_back(std::mem::replace(&mut line, spare));
+        line.clear();
+    }
+    for line in last {
+        out.write_all(&line)?;
+    }
+    Ok(read)
+}
+
+/// A file `tail -f` follows.
+struct Followed {
+    path: PathBuf,
+    /// How many bytes of it were printed
+    offset: u64,
+    watcher: Box<dyn VfsWatcher>,
+}
+
+/// Print what is appended to `files` after the bytes already printed of
+/// each, until all of them are removed or `stop` returns true. A header is
+/// printed whenever the output switches to another file. The VFS is only
+/// locked while looking for changes.
+fn follow_files(
+    vfs: &impl SharedVfs,
+    files: Vec<(PathBuf, u64)>,
+    header: &mut Header,
+    stop: &dyn Fn() -> bool,
+    out: &mut dyn Write,
+) -> Result<(), ShellError> {
+    let mut current = files.last().map(|(path, _)| path.clone());
+    let mut followed = vfs.step(|vfs| {
+        let mut followed = Vec::with_capacity(files.len());
+        for (path, offset) in files {
+            let watcher = vfs.watch(&path)?;
+            followed.push(Followed {
+                path,
+                offset,
+                watcher,
+            });
+        }
+        Ok(followed)
+    })?;
+    while !followed.is_empty() && !stop() {
+        vfs.step(|vfs| {
+            let mut i = 0;
+            while let Some(file) = followed.get_mut(i) {
+                let changes = file.watcher.wait(vfs, Duration::ZERO)?;
+                let removed = changes.iter().any(|c| c.kind == VfsChangeKind::Removed);
+                if removed
+                    || (!changes.is_empty() && !append(vfs, file, &mut current, header, out)?)
+                {
+                    followed.remove(i);
+                } else {
+                    i += 1;
+                }
+            }
+            Ok(())
+        })?;
+        std::thread::sleep(WAIT_TICK);
+    }
+    Ok(())
+}
+
+/// Print what was appended to `file` since last time, starting over if it
+/// got shorter. Returns whether the file is still there.
+fn append(
+    vfs: &dyn Vfs,
+    file: &mut Followed,
+    current: &mut Option<PathBuf>,
+    header: &mut Header,
+    out: &mut dyn Write,
+) -> Result<bool, ShellError> {
+    let len = match vfs.metadata(&file.path) {
+        Ok(meta) => meta.len,
+        Err(VfsError::NotFound(_)) => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    if len < file.offset {
+        eprintln!("tail: {}: file truncated", file.path.display());
+        file.offset = 0;
+    }
+    if len == file.offset {
+        return Ok(true);
+    }
+    if current.as_ref() != Some(&file.path) {
+        header.write(out, &file.path)?;
+        *current = Some(file.path.clone());
+    }
+    let mut reader = vfs.open_read(&file.path)?;
+    std::io::copy(&mut (&mut reader).take(file.offset), &mut std::io::sink())?;
+    file.offset += std::io::copy(&mut reader, out)?;
+    out.flush()?;
+    Ok(true)
+}
+
+fn hexdump(vfs: &dyn Vfs, args: &HexdumpArgs, out: &mut dyn Write) -> HandlerResult {
+    // Like hexdump, all files form a single stream
+    let mut readers = Vec::with_capacity(args.paths.len());
+    for path in &args.paths {
+        readers.push(vfs.open_read(path)?);
+    }
+    let mut input: Box<dyn Read> = Box::new(std::io::empty());
+    for reader in readers {
+        input = Box::new(input.chain(reader));
+    }
+
+    // Offsets start after the bytes actually skipped, fewer past the end
+    let skip = args.skip.unwrap_or(0);
+    let mut offset = std::io::copy(&mut (&mut input).take(skip), &mut std::io::sink())?;
+    let mut input = input.take(args.length.unwrap_or(u64::MAX));
+
+    let mut previous: Option<[u8; HEXDUMP_WIDTH]> = None;
+    let mut squeezing = false;
+    let mut chunk = [0; HEXDUMP_WIDTH];
+    loop {
+        let n = read_full(&mut input, &mut chunk)?;
+        if n == 0 {
+            break;
+        }
+        let bytes = chunk.get(..n).unwrap_or_default();
+        if !args.no_squeezing && n == HEXDUMP_WIDTH && previous == Some(chunk) {
+            if !squeezing {
+                writeln!(out, "*")?;
+                squeezing = true;
+            }
+        } else {
+            write_hexdump_line(out, offset, bytes)?;
+            squeezing = false;
+        }
+        previous = (n == HEXDUMP_WIDTH).then_some(chunk);
+        offset += n as u64;
+    }
+    if offset > 0 {
+        writeln!(out, "{offset:08x}")?;
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Read until `buf` is full or the input ends, returning the bytes read.
+fn read_full(reader: &mut dyn Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while let Some(rest) = buf.get_mut(filled..).filter(|rest| !rest.is_empty()) {
+        match reader.read(rest) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Write one line in the `hexdump -C` format:
+/// `00000010  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 0a           |Hello, world.|`
+fn write_hexdump_line(out: &mut dyn Write, offset: u64, bytes: &[u8]) -> std::io::Result<()> {
+    write!(out, "{offset:08x} ")?;
+    for i in 0..HEXDUMP_WIDTH {
+        if i % 8 == 0 {
+            write!(out, " ")?;
+        }
+        match bytes.get(i) {
+            Some(b) => write!(out, "{b:02x} ")?,
+            None => write!(out, "   ")?,
+        }
+    }
+    let ascii: String = bytes
+        .iter()
+        .map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                char::from(b)
+            } else {
+                '.'
+            }
+        })
+        .collect();
+    writeln!(out, " |{ascii}|")
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::shell::tests::{parse_cmd, QuickPoll};
+    use crate::vfs::{HostVfs, MemVfs};
+
+    fn test_fs() -> MemVfs {
+        let numbers: Vec<String> = (1..=15).map(|i| format!("line {i}\n")).collect();
+        MemVfs::new()
+            .with_file("/data/numbers.txt", numbers.concat())
+            .with_file("/data/short.txt", "one\ntwo")
+            .with_file(
+                "/data/hello.bin",
+                b"Hello, world.\n\x00\x01\x7f\xff".to_vec(),
+            )
+            .with_file("/data/zeros.bin", vec![0; 64])
+    }
+
+    fn exec(fs: &impl SharedVfs, line: &str) -> (HandlerResult, Vec<u8>) {
+        let mut out = Vec::new();
+        let result = run(fs, parse_cmd(line), &mut out);
+        (result, out)
+    }
+
+    fn output(fs: &MemVfs, line: &str) -> String {
+        let (result, out) = exec(fs, line);
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS), "{line} failed");
+        String::from_utf8(out).expect("utf-8")
+    }
+
+    // -- cat ---------------------------------------------------------------
+
+    #[test]
+    fn cat_concatenates_files() {
+        let fs = test_fs();
+        assert_eq!(
+            output(&fs, "cat data/short.txt /data/short.txt"),
+            "one\ntwoone\ntwo"
+        );
+    }
+
+    #[test]
+    fn cat_is_binary_safe() {
+        let fs = test_fs();
+        let (_, out) = exec(&fs, "cat data/hello.bin");
+        assert_eq!(out, b"Hello, world.\n\x00\x01\x7f\xff");
+    }
+
+    #[test]
+    fn cat_reports_missing_files_and_continues() {
+        let fs = test_fs();
+        let (result, out) = exec(&fs, "cat nope data/short.txt");
+        assert_eq!(result.ok(), Some(ExitCode::FAILURE));
+        assert_eq!(out, b"one\ntwo");
+    }
+
+    #[test]
+    fn cat_rejects_directories() {
+        let fs = test_fs();
+        let (result, out) = exec(&fs, "cat data");
+        assert_eq!(result.ok(), Some(ExitCode::FAILURE));
+        assert!(out.is_empty());
+    }
+
+    // -- head / tail -------------------------------------------------------
+
+    #[test]
+    fn head_prints_first_lines() {
+        let fs = test_fs();
+        assert_eq!(
+            output(&fs, "head -n 2 data/numbers.txt"),
+            "line 1\nline 2\n"
+        );
+        assert_eq!(output(&fs, "head data/numbers.txt").lines().count(), 10);
+        assert_eq!(output(&fs, "head -n 5 data/short.txt"), "one\ntwo");
+        assert_eq!(output(&fs, "head -n 0 data/short.txt"), "");
+    }
+
+    #[test]
+    fn tail_prints_last_lines() {
+        let fs = test_fs();
+        assert_eq!(
+            output(&fs, "tail -n 2 data/numbers.txt"),
+            "line 14\nline 15\n"
+        );
+        assert_eq!(output(&fs, "tail data/numbers.txt").lines().count(), 10);
+        assert_eq!(output(&fs, "tail -n 1 data/short.txt"), "two");
+        assert_eq!(output(&fs, "tail -n 9 data/short.txt"), "one\ntwo");
+        assert_eq!(output(&fs, "tail -n 0 data/short.txt"), "");
+    }
+
+    #[test]
+    fn head_and_tail_label_multiple_files() {
+        let fs = test_fs();
+        assert_eq!(
+            output(&fs, "head -n 1 data/short.txt data/numbers.txt"),
+            "==> data/short.txt <==\none\n\n==> data/numbers.txt <==\nline 1\n"
+        );
+        assert_eq!(
+            output(&fs, "tail -n 1 data/numbers.txt data/short.txt"),
+            "==> data/numbers.txt <==\nline 15\n\n==> data/short.txt <==\ntwo"
+        );
+    }
+
+    #[test]
+    fn tail_follows_appended_data() {
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        let log = dir.path().join("app.log");
+        std::fs::write(&log, "a\nb\n").expect("write");
+        let vfs = QuickPoll(HostVfs::new(dir.path()).expect("host vfs"));
+        let (result, out) = std::thread::scope(|s| {
+            s.spawn(|| {
+                let pause = || std::thread::sleep(Duration::from_millis(50));
+                pause();
+                std::fs::OpenOptions::new()
+                    .append(true)
+                    .open(&log)
+                    .and_then(|mut file| file.write_all(b"c\n"))
+                    .expect("append");
+                pause();
+                std::fs::write(&log, "new\n").expect("truncate");
+                pause();
+                std::fs::remove_file(&log).expect("rm");
+            });
+            exec(&vfs, "tail -n 1 -f app.log")
+        });
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+        assert_eq!(String::from_utf8(out).expect("utf-8"), "b\nc\nnew\n");
+    }
+
+    #[test]
+    fn tail_follows_several_files_with_headers() {
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        std::fs::write(dir.path().join("a"), "a\n").expect("write");
+        std::fs::write(dir.path().join("b"), "b\n").expect("write");
+        let vfs = QuickPoll(HostVfs::new(dir.path()).expect("host vfs"));
+        let (result, out) = std::thread::scope(|s| {
+            s.spawn(|| {
+                std::thread::sleep(Duration::from_millis(50));
+                std::fs::OpenOptions::new()
+                    .append(true)
+                    .open(dir.path().join("a"))
+                    .and_then(|mut file| file.write_all(b"more a\n"))
+                    .expect("append");
+                std::thread::sleep(Duration::from_millis(300));
+                std::fs::remove_file(dir.path().join("a")).expect("rm");
+                std::fs::remove_file(dir.path().join("b")).expect("rm");
+            });
+            exec(&vfs, "tail -f a b nope")
+        });
+        assert_eq!(result.ok(), Some(ExitCode::FAILURE));
+        assert_eq!(
+            String::from_utf8(out).expect("utf-8"),
+            "==> a <==\na\n\n==> b <==\nb\n\n==> a <==\nmore a\n"
+        );
+    }
+
+    // -- hexdump -----------------------------------------------------------
+
+    #[test]
+    fn hexdump_canonical_format() {
+        let fs = test_fs();
+        assert_eq!(
+            output(&fs, "hexdump -C data/hello.bin"),
+            "00000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 2e 0a 00 01  |Hello, world....|\n\
+             00000010  7f ff                                             |..|\n\
+             00000012\n"
+        );
+    }
+
+    #[test]
+    fn hexdump_squeezes_repeated_lines() {
+        let fs = test_fs();
+        assert_eq!(
+            output(&fs, "hexdump -C data/zeros.bin"),
+            "00000000  00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00  |................|\n\
+             *\n\
+             00000040\n"
+        );
+        assert_eq!(
+            output(&fs, "hexdump -C --no-squeezing data/zeros.bin")
+                .lines()
+                .count(),
+            5
+        );
+    }
+
+    #[test]
+    fn hexdump_skip_and_length() {
+        let fs = test_fs();
+        assert_eq!(
+            output(&fs, "hexdump -C -s 7 -n 5 data/hello.bin"),
+            "00000007  77 6f 72 6c 64                                    |world|\n\
+             0000000c\n"
+        );
+        // Past the end of the input, only the end offset is left
+        assert_eq!(
+            output(&fs, "hexdump -C -s 100 data/hello.bin"),
+            "00000012\n"
+        );
+        let line = format!("hexdump -C -s {} data/hello.bin", u64::MAX);
+        assert_eq!(output(&fs, &line), "00000012\n");
+    }
+
+    #[test]
+    fn hexdump_of_empty_input() {
+        let fs = MemVfs::new().with_file("/empty", "");
+        assert_eq!(output(&fs, "hexdump -C empty"), "");
+    }
+
+    #[test]
+    fn hexdump_missing_file_fails() {
+        let fs = test_fs();
+        let (result, _) = exec(&fs, "hexdump -C nope");
+        assert!(matches!(result, Err(ShellError::Vfs(_))));
+    }
+}
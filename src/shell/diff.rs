@@ -0,0 +1,800 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Args, ValueHint};
+
+use super::{HandlerResult, ShellError};
+use crate::vfs::{Vfs, VfsError, VfsFileType, VfsResult};
+
+/// Lines of context around the changes of a unified diff.
+const CONTEXT: usize = 3;
+
+/// Differing byte ranges listed for binary files.
+const MAX_RANGES: usize = 8;
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// Print the differences of text files in unified format
+    #[arg(short = 'u')]
+    unified: bool,
+
+    /// Compare subdirectories of directories too
+    #[arg(short = 'r')]
+    recursive: bool,
+
+    /// First file or directory; without A and B, show the changes of an
+    /// overlay
+    #[arg(requires = "b", value_hint = ValueHint::AnyPath)]
+    a: Option<PathBuf>,
+
+    /// Second file or directory
+    #[arg(value_hint = ValueHint::AnyPath)]
+    b: Option<PathBuf>,
+}
+
+impl DiffArgs {
+    /// The paths to compare, if both were given.
+    pub(super) fn paths(&self) -> Option<(&Path, &Path)> {
+        self.a.as_deref().zip(self.b.as_deref())
+    }
+}
+
+/// Compare the files or directories `a` and `b` of `vfs`, which may be on
+/// different mounts. Fails if they differ, like `diff(1)`.
+///
+/// Files are compared as text in the normal or unified format, or by the
+/// byte ranges that differ if either holds a NUL byte. Directories are
+/// listed with `A`, `D` and `M` per entry, relative to `a` and `b`, and
+/// with `-u` the differences of changed files follow.
+pub(super) fn compare(
+    vfs: &dyn Vfs,
+    a: &Path,
+    b: &Path,
+    args: &DiffArgs,
+    out: &mut dyn Write,
+) -> HandlerResult {
+    let (mut a, mut b) = (vfs.resolve(a), vfs.resolve(b));
+    let (a_dir, b_dir) = (vfs.metadata(&a)?.is_dir(), vfs.metadata(&b)?.is_dir());
+    // A file compares with the file of the same name in a directory
+    if a_dir && !b_dir {
+        a = a.join(b.file_name().unwrap_or_default());
+    } else if b_dir && !a_dir {
+        b = b.join(a.file_name().unwrap_or_default());
+    }
+    let mut diff = Diff {
+        vfs,
+        args,
+        out,
+        status: ExitCode::SUCCESS,
+    };
+    if a_dir && b_dir {
+        diff.dirs(&a, &b, Path::new(""))?;
+    } else {
+        diff.files(&a, &b)?;
+    }
+    Ok(diff.status)
+}
+
+/// A comparison in progress: differences and errors of single entries turn
+/// the status into a failure, but the other entries are still compared.
+struct Diff<'a> {
+    vfs: &'a dyn Vfs,
+    args: &'a DiffArgs,
+    out: &'a mut dyn Write,
+    status: ExitCode,
+}
+
+impl Diff<'_> {
+    /// Compare the files `a` and `b`.
+    fn files(&mut self, a: &Path, b: &Path) -> Result<(), ShellError> {
+        let (a_data, b_data) = (read(self.vfs, a)?, read(self.vfs, b)?);
+        if a_data != b_data {
+            self.status = ExitCode::FAILURE;
+            self.contents(a, b, &a_data, &b_data)?;
+        }
+        Ok(())
+    }
+
+    /// Print the differences of `a` and `b`, with contents `a_data` and
+    /// `b_data`.
+    fn contents(
+        &mut self,
+        a: &Path,
+        b: &Path,
+        a_data: &[u8],
+        b_data: &[u8],
+    ) -> std::io::Result<()> {
+        if a_data.contains(&0) || b_data.contains(&0) {
+            return binary(self.out, a, b, a_data, b_data);
+        }
+        let (a_lines, b_lines) = (lines(a_data), lines(b_data));
+        let ops = edit_script(&a_lines, &b_lines);
+        if self.args.unified {
+            writeln!(self.out, "--- {}", a.display())?;
+            writeln!(self.out, "+++ {}", b.display())?;
+            unified(self.out, &a_lines, &b_lines, &ops)
+        } else {
+            normal(self.out, &a_lines, &b_lines, &ops)
+        }
+    }
+
+    /// Compare the directories `a` and `b`, which are `rel` below the
+    /// directories compared first.
+    ///
+    /// Symlinks compare as what they point to, dangling ones as symlinks,
+    /// but `-r` does not descend into symlinked directories: they could
+    /// lead anywhere, even into a loop.
+    fn dirs(&mut self, a: &Path, b: &Path, rel: &Path) -> Result<(), ShellError> {
+        let vfs = self.vfs;
+        let entries = |dir: &Path| -> VfsResult<BTreeMap<OsString, (VfsFileType, bool)>> {
+            Ok(vfs
+                .read_dir(dir)?
+                .into_iter()
+                .map(|e| {
+                    let link = e.file_type == VfsFileType::Symlink;
+                    let file_type = if link {
+                        vfs.metadata(&dir.join(&e.name))
+                            .map_or(e.file_type, |meta| meta.file_type)
+                    } else {
+                        e.file_type
+                    };
+                    (e.name, (file_type, link))
+                })
+                .collect())
+        };
+        let (a_entries, b_entries) = (entries(a)?, entries(b)?);
+        let names: BTreeSet<&OsString> = a_entries.keys().chain(b_entries.keys()).collect();
+        for name in names {
+            let path = rel.join(name);
+            let (a_child, b_child) = (a.join(name), b.join(name));
+            let result = match (a_entries.get(name), b_entries.get(name)) {
+                (Some(&(file_type, _)), None) => self.entry('D', &path, file_type),
+                (None, Some(&(file_type, _))) => self.entry('A', &path, file_type),
+                // Only descend into real directories
+                (Some((VfsFileType::Dir, false)), Some((VfsFileType::Dir, false)))
+                    if self.args.recursive =>
+                {
+                    self.dirs(&a_child, &b_child, &path)
+                }
+                // Other directories are alike here; dangling symlinks cannot be read
+                (Some((VfsFileType::Dir, _)), Some((VfsFileType::Dir, _)))
+                | (Some((VfsFileType::Symlink, _)), Some((VfsFileType::Symlink, _)))
+                | (None, None) => Ok(()),
+                (Some(&(a_type, _)), Some(&(b_type, _)))
+                    if (a_type == VfsFileType::Dir) != (b_type == VfsFileType::Dir)
+                        || (a_type == VfsFileType::Symlink) != (b_type == VfsFileType::Symlink) =>
+                {
+                    self.entry('M', &path, b_type)
+                }
+                (Some(_), Some(&(file_type, _))) => {
+                    self.changed(&a_child, &b_child, &path, file_type)
+                }
+            };
+            match result {
+                Err(ShellError::Vfs(e)) => {
+                    eprintln!("diff: {e}");
+                    self.status = ExitCode::FAILURE;
+                }
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+
+    /// List the file `path` as changed if `a` and `b` differ.
+    fn changed(
+        &mut self,
+        a: &Path,
+        b: &Path,
+        path: &Path,
+        file_type: VfsFileType,
+    ) -> Result<(), ShellError> {
+        let (a_data, b_data) = (read(self.vfs, a)?, read(self.vfs, b)?);
+        if a_data != b_data {
+            self.entry('M', path, file_type)?;
+            if self.args.unified {
+                self.contents(a, b, &a_data, &b_data)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// List the directory entry `path`, with a `/` after directories.
+    fn entry(&mut self, kind: char, path: &Path, file_type: VfsFileType) -> Result<(), ShellError> {
+        let slash = if file_type == VfsFileType::Dir {
+            "/"
+        } else {
+            ""
+        };
+        writeln!(self.out, "{kind} {}{slash}", path.display())?;
+        self.status = ExitCode::FAILURE;
+        Ok(())
+    }
+}
+
+fn read(vfs: &dyn Vfs, path: &Path) -> VfsResult<Vec<u8>> {
+    let mut data = Vec::new();
+    vfs.open_read(path)?
+        .read_to_end(&mut data)
+        .map_err(|e| VfsError::from_io(path, e))?;
+    Ok(data)
+}
+
+/// The lines of `data`, each with its newline if it has one.
+fn lines(data: &[u8]) -> Vec<&[u8]> {
+    data.split_inclusive(|&byte| byte == b'\n').collect()
+}
+
+/// Write `line` after `prefix`, marking a missing newline like `diff(1)`.
+fn write_line(out: &mut dyn Write, prefix: &str, line: &[u8]) -> std::io::Result<()> {
+    out.write_all(prefix.as_bytes())?;
+    if let Some(text) = line.strip_suffix(b"\n") {
+        out.write_all(text)?;
+        out.write_all(b"\n")
+    } else {
+        out.write_all(line)?;
+        out.write_all(b"\n\\ No newline at end of file\n")
+    }
+}
+
+/// Summarize where the binary contents `a_data` and `b_data` differ: the
+/// first byte ranges by offset, and the sizes.
+fn binary(
+    out: &mut dyn Write,
+    a: &Path,
+    b: &Path,
+    a_data: &[u8],
+    b_data: &[u8],
+) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "Binary files {} and {} differ",
+        a.display(),
+        b.display()
+    )?;
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for (offset, _) in a_data
+        .iter()
+        .zip(b_data)
+        .enumerate()
+        .filter(|(_, (x, y))| x != y)
+    {
+        match ranges.last_mut() {
+            Some((_, end)) if *end == offset => *end += 1,
+            _ => ranges.push((offset, offset + 1)),
+        }
+    }
+    for &(start, end) in ranges.iter().take(MAX_RANGES) {
+        let len = end - start;
+        if len == 1 {
+            writeln!(out, "  offset {start}: 1 byte differs")?;
+        } else {
+            writeln!(out, "  offsets {start}-{}: {len} bytes differ", end - 1)?;
+        }
+    }
+    let more = ranges.len().saturating_sub(MAX_RANGES);
+    if more > 0 {
+        writeln!(
+            out,
+            "  and {more} more {}",
+            crate::pluralize!("range", more)
+        )?;
+    }
+    if a_data.len() != b_data.len() {
+        writeln!(
+            out,
+            "  sizes differ: {} and {} bytes",
+            a_data.len(),
+            b_data.len()
+        )?;
+    }
+    Ok(())
+}
+
+/// One step of an edit script, see [`edit_script`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    /// The next lines of both sides are the same
+    Equal,
+    /// The next line of the first side is left out
+    Delete,
+    /// The next line of the second side is put in
+    Insert,
+}
+
+/// The steps of `ops` with the lines of both sides before each.
+fn positions(ops: &[Op]) -> Vec<(Op, usize, usize)> {
+    let (mut a, mut b) = (0, 0);
+    ops.iter()
+        .map(|&op| {
+            let step = (op, a, b);
+            if op != Op::Insert {
+                a += 1;
+            }
+            if op != Op::Delete {
+                b += 1;
+            }
+            step
+        })
+        .collect()
+}
+
+/// Write the changes of `ops` in the normal format of `diff(1)`.
+fn normal(out: &mut dyn Write, a: &[&[u8]], b: &[&[u8]], ops: &[Op]) -> std::io::Result<()> {
+    let range = |start: usize, len: usize| {
+        if len == 1 {
+            format!("{}", start + 1)
+        } else {
+            format!("{},{}", start + 1, start + len)
+        }
+    };
+    let steps = positions(ops);
+    for run in steps
+        .split(|&(op, _, _)| op == Op::Equal)
+        .filter(|run| !run.is_empty())
+    {
+        let Some(&(_, a_start, b_start)) = run.first() else {
+            continue;
+        };
+        let deleted: Vec<&[u8]> = run
+            .iter()
+            .filter(|(op, _, _)| *op == Op::Delete)
+            .filter_map(|&(_, i, _)| a.get(i).copied())
+            .collect();
+        let inserted: Vec<&[u8]> = run
+            .iter()
+            .filter(|(op, _, _)| *op == Op::Insert)
+            .filter_map(|&(_, _, i)| b.get(i).copied())
+            .collect();
+        let (a_range, b_range) = (
+            range(a_start, deleted.len()),
+            range(b_start, inserted.len()),
+        );
+        match (deleted.is_empty(), inserted.is_empty()) {
+            (false, false) => writeln!(out, "{a_range}c{b_range}")?,
+            (false, true) => writeln!(out, "{a_range}d{b_start}")?,
+            (true, _) => writeln!(out, "{a_start}a{b_range}")?,
+        }
+        for line in &deleted {
+            write_line(out, "< ", line)?;
+        }
+        if !deleted.is_empty() && !inserted.is_empty() {
+            writeln!(out, "---")?;
+        }
+        for line in &inserted {
+            write_line(out, "> ", line)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the changes of `ops` as the hunks of a unified diff, with
+/// [`CONTEXT`] lines around them.
+fn unified(out: &mut dyn Write, a: &[&[u8]], b: &[&[u8]], ops: &[Op]) -> std::io::Result<()> {
+    let range = |start: usize, len: usize| match len {
+        0 => format!("{start},0"),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{len}", start + 1),
+    };
+    let steps = positions(ops);
+    let changes: Vec<usize> = steps
+        .iter()
+        .enumerate()
+        .filter(|(_, (op, _, _))| *op != Op::Equal)
+        .map(|(i, _)| i)
+        .collect();
+
+    // Changes close enough to share their context make up one hunk
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &i in &changes {
+        match hunks.last_mut() {
+            Some((_, last)) if i - *last <= 2 * CONTEXT => *last = i,
+            _ => hunks.push((i, i)),
+        }
+    }
+    for (first, last) in hunks {
+        let start = first.saturating_sub(CONTEXT);
+        let end = (last + CONTEXT + 1).min(steps.len());
+        let hunk = steps.get(start..end).unwrap_or_default();
+        let Some(&(_, a_start, b_start)) = hunk.first() else {
+            continue;
+        };
+        let a_len = hunk.iter().filter(|(op, _, _)| *op != Op::Insert).count();
+        let b_len = hunk.iter().filter(|(op, _, _)| *op != Op::Delete).count();
+        writeln!(
+            out,
+            "@@ -{} +{} @@",
+            range(a_start, a_len),
+            range(b_start, b_len)
+        )?;
+        for &(op, i, j) in hunk {
+            let (prefix, line) = match op {
+                Op::Equal => (" ", a.get(i)),
+                Op::Delete => ("-", a.get(i)),
+                Op::Insert => ("+", b.get(j)),
+            };
+            if let Some(line) = line {
+                write_line(out, prefix, line)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A shortest edit script that turns `a` into `b`.
+fn edit_script<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Op> {
+    let mut ops = Vec::with_capacity(a.len() + b.len());
+    push_edit_script(a, b, &mut ops);
+    ops
+}
+
+/// Append a shortest edit script that turns `a` into `b` to `ops`, splitting
+/// both at their [`middle_snake`] and recursing on each side of it.
+fn push_edit_script<T: PartialEq>(a: &[T], b: &[T], ops: &mut Vec<Op>) {
+    // The common ends are cheap to take off first
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let (a, b) = (
+        a.get(prefix..).unwrap_or_default(),
+        b.get(prefix..).unwrap_or_default(),
+    );
+    let suffix = a
+        .iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a, b) = (
+        a.get(..a.len() - suffix).unwrap_or_default(),
+        b.get(..b.len() - suffix).unwrap_or_default(),
+    );
+    ops.extend(std::iter::repeat_n(Op::Equal, prefix));
+    let split = if a.is_empty() || b.is_empty() {
+        None
+    } else {
+        middle_snake(a, b)
+    };
+    if let Some((x, y)) = split {
+        push_edit_script(
+            a.get(..x).unwrap_or_default(),
+            b.get(..y).unwrap_or_default(),
+            ops,
+        );
+        push_edit_script(
+            a.get(x..).unwrap_or_default(),
+            b.get(y..).unwrap_or_default(),
+            ops,
+        );
+    } else {
+        ops.extend(std::iter::repeat_n(Op::Delete, a.len()));
+        ops.extend(std::iter::repeat_n(Op::Insert, b.len()));
+    }
+    ops.extend(std::iter::repeat_n(Op::Equal, suffix));
+}
+
+/// How far each diagonal `k = x - y` got in one direction of
+/// [`middle_snake`], for `k` in `-max..=max`.
+struct Frontier {
+    max: isize,
+    xs: Vec<isize>,
+}
+
+impl Frontier {
+    fn new(max: isize) -> Self {
+        Self {
+            max,
+            xs: vec![0; usize::try_from(2 * max + 1).unwrap_or_default()],
+        }
+    }
+
+    fn index(&self, k: isize) -> Option<usize> {
+        usize::try_from(k + self.max).ok()
+    }
+
+    fn get(&self, k: isize) -> isize {
+        self.index(k)
+            .and_then(|i| self.xs.get(i))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn set(&mut self, k: isize, x: isize) {
+        if let Some(slot) = self.index(k).and_then(|i| self.xs.get_mut(i)) {
+            *slot = x;
+        }
+    }
+
+    /// The x at which a path reaching diagonal `k` in step `d` starts its
+    /// snake, coming from the further of the neighbouring diagonals.
+    fn start(&self, d: isize, k: isize) -> isize {
+        if k == -d || (k != d && self.get(k - 1) < self.get(k + 1)) {
+            self.get(k + 1)
+        } else {
+            self.get(k - 1) + 1
+        }
+    }
+}
+
+/// Where a shortest edit path from `old` to `new` can be split in two, with
+/// Myers' O(ND) difference algorithm in linear space.
+///
+/// Searches forward from the start and backward from the end at the same
+/// time, keeping only the current frontier of each, until they overlap on
+/// the middle snake. Both sides are non-empty and differ at both ends, see
+/// [`push_edit_script`].
+fn middle_snake<T: PartialEq>(old: &[T], new: &[T]) -> Option<(usize, usize)> {
+    let len = |side: &[T]| isize::try_from(side.len()).unwrap_or(isize::MAX);
+    let (n, m) = (len(old), len(new));
+    let same =
+        |x: isize, y: isize| matches!((item(old, x), item(new, y)), (Some(p), Some(q)) if p == q);
+    let split = |x: isize, y: isize| Some((usize::try_from(x).ok()?, usize::try_from(y).ok()?));
+    // Forward diagonal k is backward diagonal delta - k
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    let max = (n + m + 1) / 2 + 1;
+    let (mut forward, mut backward) = (Frontier::new(max), Frontier::new(max));
+
+    for d in 0..max {
+        for k in (-d..=d).step_by(2) {
+            let start = forward.start(d, k);
+            let mut x = start;
+            while x < n && x - k < m && same(x, x - k) {
+                x += 1;
+            }
+            forward.set(k, x);
+            // The backward search is one step behind
+            if odd && (k - delta).abs() < d && x + backward.get(delta - k) >= n {
+                return split(start, start - k);
+            }
+        }
+        for k in (-d..=d).step_by(2) {
+            let mut x = backward.start(d, k);
+            while x < n && x - k < m && same(n - x - 1, m - (x - k) - 1) {
+                x += 1;
+            }
+            backward.set(k, x);
+            if !odd && (k - delta).abs() <= d && x + forward.get(delta - k) >= n {
+                return split(n - x, m - (x - k));
+            }
+        }
+    }
+    None
+}
+
+fn item<T>(side: &[T], i: isize) -> Option<&T> {
+    usize::try_from(i).ok().and_then(|i| side.get(i))
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::shell::overlay_cmds::VfsOverlayCommands;
+    use crate::shell::tests::parse_cmd;
+    use crate::vfs::{MemVfs, MountTable};
+
+    fn script(a: &str, b: &str) -> Vec<Op> {
+        let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+        edit_script(&a, &b)
+    }
+
+    /// Apply `ops` to `a`, taking inserted items from `b`.
+    fn apply(a: &str, b: &str, ops: &[Op]) -> String {
+        let (mut a, mut b) = (a.chars(), b.chars());
+        let mut result = String::new();
+        for op in ops {
+            match op {
+                Op::Equal => {
+                    result.extend(a.next());
+                    b.next();
+                }
+                Op::Delete => {
+                    a.next();
+                }
+                Op::Insert => result.extend(b.next()),
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn edit_scripts_are_shortest() {
+        for (a, b, edits) in [
+            ("", "", 0),
+            ("abc", "abc", 0),
+            ("", "abc", 3),
+            ("abc", "", 3),
+            ("abcabba", "cbabac", 5),
+            ("xaby", "xcby", 2),
+            ("abcdef", "azcdqf", 4),
+        ] {
+            let ops = script(a, b);
+            assert_eq!(apply(a, b, &ops), b, "{a} -> {b}");
+            let count = ops.iter().filter(|op| **op != Op::Equal).count();
+            assert_eq!(count, edits, "{a} -> {b}");
+        }
+    }
+
+    #[test]
+    fn edit_scripts_of_large_different_inputs() {
+        // Keeping every frontier would take D² = 16M positions here
+        let a: Vec<u32> = (0..2_000).collect();
+        let b: Vec<u32> = (2_000..4_000).collect();
+        let ops = edit_script(&a, &b);
+        assert_eq!(ops.len(), 4_000);
+        assert!(!ops.contains(&Op::Equal));
+
+        let mut c = a.clone();
+        c.insert(1_000, 0);
+        let ops = edit_script(&a, &c);
+        assert_eq!(ops.iter().filter(|op| **op == Op::Insert).count(), 1);
+        assert!(!ops.contains(&Op::Delete));
+    }
+
+    fn test_fs() -> MemVfs {
+        MemVfs::new()
+            .with_file(
+                "/a/conf.txt",
+                "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\n",
+            )
+            .with_file(
+                "/b/conf.txt",
+                "one\n2\nthree\nfour\nfive\nsix\nseven\neight\nnine\n",
+            )
+            .with_file("/a/same.txt", "same\n")
+            .with_file("/b/same.txt", "same\n")
+            .with_file("/a/old.txt", "old\n")
+            .with_file("/b/new.txt", "new")
+            .with_file("/a/sub/x.bin", b"\0\x01\x02\x03\x04\x05".to_vec())
+            .with_file("/b/sub/x.bin", b"\0\x01\xff\xff\x04\x05\x06".to_vec())
+            .with_dir("/a/kind")
+            .with_file("/b/kind", "")
+    }
+
+    fn exec(vfs: &dyn Vfs, line: &str) -> (HandlerResult, String) {
+        let VfsOverlayCommands::Diff(args) = parse_cmd(&format!("diff {line}")) else {
+            panic!("not a diff command");
+        };
+        let (a, b) = args.paths().expect("paths");
+        let mut out = Vec::new();
+        let result = compare(vfs, a, b, &args, &mut out);
+        (result, String::from_utf8(out).expect("utf-8"))
+    }
+
+    #[test]
+    fn identical_files_succeed_quietly() {
+        let vfs = test_fs();
+        let (result, out) = exec(&vfs, "/a/same.txt /b/same.txt");
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+        assert_eq!(out, "");
+    }
+
+    #[test]
+    fn text_files_in_normal_format() {
+        let vfs = test_fs();
+        let (result, out) = exec(&vfs, "/a/conf.txt /b/conf.txt");
+        assert_eq!(result.ok(), Some(ExitCode::FAILURE));
+        assert_eq!(out, "2c2\n< two\n---\n> 2\n8a9\n> nine\n");
+
+        let (_, out) = exec(&vfs, "/a/old.txt /b/new.txt");
+        assert_eq!(
+            out,
+            "1c1\n< old\n---\n> new\n\\ No newline at end of file\n"
+        );
+    }
+
+    #[test]
+    fn text_files_in_unified_format() {
+        let vfs = test_fs();
+        let (result, out) = exec(&vfs, "-u /a/conf.txt /b/conf.txt");
+        assert_eq!(result.ok(), Some(ExitCode::FAILURE));
+        assert_eq!(
+            out,
+            "--- /a/conf.txt\n+++ /b/conf.txt\n\
+             @@ -1,5 +1,5 @@\n one\n-two\n+2\n three\n four\n five\n\
+             @@ -6,3 +6,4 @@\n six\n seven\n eight\n+nine\n"
+        );
+
+        let (_, out) = exec(&vfs, "-u /a/old.txt /a/sub/../same.txt");
+        assert_eq!(
+            out,
+            "--- /a/old.txt\n+++ /a/same.txt\n@@ -1 +1 @@\n-old\n+same\n"
+        );
+    }
+
+    #[test]
+    fn binary_files_by_offset() {
+        let vfs = test_fs();
+        let (result, out) = exec(&vfs, "/a/sub/x.bin /b/sub/x.bin");
+        assert_eq!(result.ok(), Some(ExitCode::FAILURE));
+        assert_eq!(
+            out,
+            "Binary files /a/sub/x.bin and /b/sub/x.bin differ\n\
+             \x20 offsets 2-3: 2 bytes differ\n\
+             \x20 sizes differ: 6 and 7 bytes\n"
+        );
+    }
+
+    #[test]
+    fn directories_list_their_differences() {
+        let vfs = test_fs();
+        let (result, out) = exec(&vfs, "/a /b");
+        assert_eq!(result.ok(), Some(ExitCode::FAILURE));
+        assert_eq!(out, "M conf.txt\nM kind\nA new.txt\nD old.txt\n");
+
+        let (_, out) = exec(&vfs, "-r /a /b");
+        assert_eq!(
+            out,
+            "M conf.txt\nM kind\nA new.txt\nD old.txt\nM sub/x.bin\n"
+        );
+
+        let (_, out) = exec(&vfs, "-r -u /a/sub /b/sub");
+        assert_eq!(
+            out,
+            "M x.bin\nBinary files /a/sub/x.bin and /b/sub/x.bin differ\n\
+             \x20 offsets 2-3: 2 bytes differ\n\
+             \x20 sizes differ: 6 and 7 bytes\n"
+        );
+    }
+
+    #[test]
+    fn files_compare_with_the_same_name_in_directories() {
+        let vfs = test_fs();
+        let (result, out) = exec(&vfs, "/a/same.txt /b");
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+        assert_eq!(out, "");
+        let (result, _) = exec(&vfs, "/a /b/new.txt");
+        assert!(matches!(
+            result,
+            Err(ShellError::Vfs(VfsError::NotFound(path))) if path == Path::new("/a/new.txt")
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_compare_as_their_targets() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        for tree in ["d", "e"] {
+            let root = dir.path().join(tree);
+            std::fs::create_dir_all(root.join("sub")).expect("mkdir");
+            std::fs::write(root.join("sub/f"), "f\n").expect("write");
+            symlink(".", root.join("loop")).expect("symlink");
+            symlink("sub/f", root.join("g")).expect("symlink");
+            symlink("nowhere", root.join("dangling")).expect("symlink");
+        }
+        symlink("sub", dir.path().join("d/sl")).expect("symlink");
+        std::fs::write(dir.path().join("e/sl"), "sl\n").expect("write");
+        let vfs = crate::vfs::HostVfs::new(dir.path()).expect("host vfs");
+
+        let (result, out) = exec(&vfs, "-r /d /d");
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+        assert_eq!(out, "");
+        let (result, out) = exec(&vfs, "-r /d /e");
+        assert_eq!(result.ok(), Some(ExitCode::FAILURE));
+        assert_eq!(out, "M sl\n");
+    }
+
+    #[test]
+    fn trees_compare_across_mounts() {
+        let mut vfs = MountTable::new();
+        vfs.mount("/", Box::new(MemVfs::new())).expect("mount");
+        let staging = MemVfs::new()
+            .with_file("/app/conf.txt", "debug = true\n")
+            .with_file("/app/extra.txt", "");
+        let live = MemVfs::new().with_file("/app/conf.txt", "debug = false\n");
+        vfs.mount("/staging", Box::new(staging)).expect("mount");
+        vfs.mount("/live", Box::new(live)).expect("mount");
+
+        let (result, out) = exec(&vfs, "-r -u /live /staging");
+        assert_eq!(result.ok(), Some(ExitCode::FAILURE));
+        assert_eq!(
+            out,
+            "M app/conf.txt\n--- /live/app/conf.txt\n+++ /staging/app/conf.txt\n\
+             @@ -1 +1 @@\n-debug = false\n+debug = true\nA app/extra.txt\n"
+        );
+    }
+}
@@ -0,0 +1,337 @@
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use clap::{Subcommand, ValueHint};
+
+use super::{BasicShell, EditValidatorFn, HandlerResult, ShellError, HANDLER_SUCCESS};
+use crate::vfs::{Vfs, VfsError, VfsWriteMode};
+
+/// The editor when neither `$VISUAL` nor `$EDITOR` is set.
+const DEFAULT_EDITOR: &str = "vi";
+
+#[derive(Subcommand)]
+pub enum VfsEditCommands {
+    /// Edit a file with $VISUAL or $EDITOR, creating it if it is missing
+    Edit {
+        /// File to edit
+        #[arg(value_hint = ValueHint::FilePath)]
+        path: PathBuf,
+    },
+}
+
+pub fn handle_vfs_edit_command(sh: &BasicShell, cmd: VfsEditCommands) -> HandlerResult {
+    let VfsEditCommands::Edit { path } = cmd;
+    let validator = sh.edit_validator.clone();
+    sh.with_vfs(|vfs| edit(vfs, &path, &editor(), validator.as_deref()))
+}
+
+/// The editor command line from `$VISUAL` or `$EDITOR`.
+fn editor() -> String {
+    ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_EDITOR.into())
+}
+
+/// Edit `path` with the command line `editor` in a host copy, and write the
+/// copy back once it changed and `validator` accepts it.
+///
+/// A rejected copy is reported and opened again, until it is accepted or
+/// left as it was rejected.
+fn edit(
+    vfs: &mut dyn Vfs,
+    path: &Path,
+    editor: &str,
+    validator: Option<&EditValidatorFn>,
+) -> HandlerResult {
+    let path = vfs.resolve(path);
+    let original = match read(vfs, &path) {
+        Ok(data) => data,
+        Err(VfsError::NotFound(_)) => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let copy = EditCopy::new(&path, &original)?;
+    let mut rejected = None;
+    loop {
+        if !run_editor(editor, copy.path())? {
+            return Ok(ExitCode::FAILURE);
+        }
+        let edited = std::fs::read(copy.path())?;
+        if edited == original {
+            eprintln!("edit: no changes to '{}'", path.display());
+            return HANDLER_SUCCESS;
+        }
+        if rejected.as_ref() == Some(&edited) {
+            eprintln!("edit: '{}' not saved", path.display());
+            return Ok(ExitCode::FAILURE);
+        }
+        if let Some(Err(reason)) = validator.map(|validate| validate(&path, &edited)) {
+            eprintln!("edit: {}: {reason}", path.display());
+            rejected = Some(edited);
+            continue;
+        }
+        let mut writer = vfs.open_write(&path, VfsWriteMode::Truncate)?;
+        writer
+            .write_all(&edited)
+            .and_then(|()| writer.flush())
+            .map_err(|e| VfsError::from_io(&path, e))?;
+        return HANDLER_SUCCESS;
+    }
+}
+
+fn read(vfs: &dyn Vfs, path: &Path) -> Result<Vec<u8>, VfsError> {
+    let mut data = Vec::new();
+    vfs.open_read(path)?
+        .read_to_end(&mut data)
+        .map_err(|e| VfsError::from_io(path, e))?;
+    Ok(data)
+}
+
+/// Run the command line `editor` on `file` and wait for it; returns whether
+/// it succeeded.
+fn run_editor(editor: &str, file: &Path) -> Result<bool, ShellError> {
+    let words = crate::shell_parse_line(editor)?;
+    let Some((program, args)) = words.split_first() else {
+        return Err(ShellError::Internal("empty editor command".into()));
+    };
+    let status = std::process::Command::new(program)
+        .args(args)
+        .arg(file)
+        .status()
+        .map_err(|e| std::io::Error::new(e.kind(), format!("cannot run editor '{editor}': {e}")))?;
+    if !status.success() {
+        eprintln!("edit: editor '{editor}' failed ({status}), nothing saved");
+    }
+    Ok(status.success())
+}
+
+/// A host copy of a VFS file, in a directory of its own that only the
+/// current user can read and that is removed on drop. The copy keeps the
+/// file name, so editors can tell what kind of file it is.
+struct EditCopy {
+    dir: PathBuf,
+    path: PathBuf,
+}
+
+impl EditCopy {
+    fn new(path: &Path, data: &[u8]) -> std::io::Result<Self> {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        let name = path.file_name().unwrap_or_else(|| "file".as_ref());
+        loop {
+            let dir = std::env::temp_dir().join(format!(
+                "esh-edit-{}-{}",
+                std::process::id(),
+                NEXT.fetch_add(1, Ordering::Relaxed)
+            ));
+            let mut builder = std::fs::DirBuilder::new();
+            #[cfg(unix)]
+            std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+            match builder.create(&dir) {
+                // Left behind by a process with the same id
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e),
+                Ok(()) => {
+                    let copy = Self {
+                        path: dir.join(name),
+                        dir,
+                    };
+                    std::fs::write(&copy.path, data)?;
+                    return Ok(copy);
+                }
+            }
+        }
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for EditCopy {
+    fn drop(&mut self) {
+        // Editors may leave swap or backup files next to the copy
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(all(test, unix))]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemVfs;
+
+    /// A fake editor: a shell script run with `sh`, so it never needs to be
+    /// executable. Its runs are counted in `runs` next to it.
+    struct FakeEditor {
+        dir: tempfile::TempDir,
+    }
+
+    impl FakeEditor {
+        fn new(script: &str) -> Self {
+            let dir = tempfile::tempdir().expect("failed to create tempdir");
+            let runs = dir.path().join("runs");
+            let script = format!("echo \"$1\" >> '{}'\n{script}", runs.display());
+            std::fs::write(dir.path().join("editor.sh"), script).expect("write");
+            Self { dir }
+        }
+
+        fn command(&self) -> String {
+            format!("sh '{}'", self.dir.path().join("editor.sh").display())
+        }
+
+        /// The files the editor was started on.
+        fn runs(&self) -> Vec<PathBuf> {
+            std::fs::read_to_string(self.dir.path().join("runs"))
+                .unwrap_or_default()
+                .lines()
+                .map(PathBuf::from)
+                .collect()
+        }
+    }
+
+    fn test_fs() -> MemVfs {
+        MemVfs::new().with_file("/etc/app.conf", "debug = false\n")
+    }
+
+    fn content(vfs: &dyn Vfs, path: &str) -> String {
+        String::from_utf8(read(vfs, Path::new(path)).expect("read")).expect("utf-8")
+    }
+
+    #[test]
+    fn changes_are_written_back() {
+        let editor =
+            FakeEditor::new("sed -e 's/false/true/' \"$1\" > \"$1.new\" && mv \"$1.new\" \"$1\"");
+        let mut vfs = test_fs();
+        vfs.cd(Path::new("/etc")).expect("cd");
+        let result = edit(&mut vfs, Path::new("app.conf"), &editor.command(), None);
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+        assert_eq!(content(&vfs, "/etc/app.conf"), "debug = true\n");
+
+        // The copy has the file's name and is gone afterwards
+        let runs = editor.runs();
+        assert_eq!(runs.len(), 1);
+        assert!(runs
+            .iter()
+            .all(|copy| copy.ends_with("app.conf") && !copy.exists()));
+    }
+
+    #[test]
+    fn unchanged_files_are_left_alone() {
+        let editor = FakeEditor::new("true");
+        let mut vfs = test_fs();
+        let result = edit(
+            &mut vfs,
+            Path::new("/etc/app.conf"),
+            &editor.command(),
+            None,
+        );
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+        assert_eq!(content(&vfs, "/etc/app.conf"), "debug = false\n");
+
+        // A missing file left empty is not created
+        let result = edit(
+            &mut vfs,
+            Path::new("/etc/new.conf"),
+            &editor.command(),
+            None,
+        );
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+        assert!(!vfs.exists(Path::new("/etc/new.conf")).expect("exists"));
+    }
+
+    #[test]
+    fn missing_files_are_created() {
+        let editor = FakeEditor::new("echo 'port = 80' > \"$1\"");
+        let mut vfs = test_fs();
+        let result = edit(
+            &mut vfs,
+            Path::new("/etc/new.conf"),
+            &editor.command(),
+            None,
+        );
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+        assert_eq!(content(&vfs, "/etc/new.conf"), "port = 80\n");
+    }
+
+    #[test]
+    fn failing_editors_save_nothing() {
+        let editor = FakeEditor::new("echo broken > \"$1\"; exit 3");
+        let mut vfs = test_fs();
+        let result = edit(
+            &mut vfs,
+            Path::new("/etc/app.conf"),
+            &editor.command(),
+            None,
+        );
+        assert_eq!(result.ok(), Some(ExitCode::FAILURE));
+        assert_eq!(content(&vfs, "/etc/app.conf"), "debug = false\n");
+
+        let result = edit(&mut vfs, Path::new("/etc/app.conf"), "", None);
+        assert!(matches!(result, Err(ShellError::Internal(_))));
+        let result = edit(
+            &mut vfs,
+            Path::new("/etc/app.conf"),
+            "/nonexistent/editor",
+            None,
+        );
+        assert!(matches!(result, Err(ShellError::Io(e)) if e.kind() == ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn rejected_edits_reopen_the_editor() {
+        // Breaks the file the first time, fixes it the second
+        let editor = FakeEditor::new(
+            "if grep -q broken \"$1\"; then echo 'debug = true' > \"$1\"; \
+             else echo broken > \"$1\"; fi",
+        );
+        let validator = |path: &Path, data: &[u8]| {
+            assert_eq!(path, Path::new("/etc/app.conf"));
+            if data.starts_with(b"debug = ") {
+                Ok(())
+            } else {
+                Err("expected 'debug = BOOL'".to_string())
+            }
+        };
+        let mut vfs = test_fs();
+        let result = edit(
+            &mut vfs,
+            Path::new("/etc/app.conf"),
+            &editor.command(),
+            Some(&validator),
+        );
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+        assert_eq!(content(&vfs, "/etc/app.conf"), "debug = true\n");
+        assert_eq!(editor.runs().len(), 2);
+    }
+
+    #[test]
+    fn rejected_edits_left_as_they_are_are_dropped() {
+        let editor = FakeEditor::new("echo broken > \"$1\"");
+        let validator = |_: &Path, _: &[u8]| Err("never valid".to_string());
+        let mut vfs = test_fs();
+        let result = edit(
+            &mut vfs,
+            Path::new("/etc/app.conf"),
+            &editor.command(),
+            Some(&validator),
+        );
+        assert_eq!(result.ok(), Some(ExitCode::FAILURE));
+        assert_eq!(content(&vfs, "/etc/app.conf"), "debug = false\n");
+        assert_eq!(editor.runs().len(), 2);
+    }
+
+    #[test]
+    fn directories_cannot_be_edited() {
+        let editor = FakeEditor::new("true");
+        let mut vfs = test_fs();
+        let result = edit(&mut vfs, Path::new("/etc"), &editor.command(), None);
+        assert!(matches!(
+            result,
+            Err(ShellError::Vfs(VfsError::IsADirectory(_)))
+        ));
+        assert!(editor.runs().is_empty());
+    }
+}
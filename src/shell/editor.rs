@@ -0,0 +1,210 @@
+use std::path::PathBuf;
+
+use rustyline::completion::{Completer, Pair};
+use rustyline::config::{CompletionType, Config};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+
+use tracing::{debug, warn};
+
+use super::repl::LineSource;
+use super::BasicShell;
+
+const MAX_HISTORY_SIZE: usize = 1000;
+
+/// Tab completion for the line editor, driven by the interactive command
+/// tree of the shell.
+pub(super) struct ShellHelper<'a> {
+    shell: &'a BasicShell,
+}
+
+impl<'a> ShellHelper<'a> {
+    pub(super) const fn new(shell: &'a BasicShell) -> Self {
+        Self { shell }
+    }
+}
+
+impl Completer for ShellHelper<'_> {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        // A copy, so completers may use the shell
+        let Ok(cmd) = self.shell.with_shell_cmd(|cmd| cmd.clone()) else {
+            return Ok((pos, Vec::new()));
+        };
+        let (start, candidates) = self.shell.complete_line(&cmd, line, pos);
+        let pairs = candidates
+            .into_iter()
+            .map(|c| Pair {
+                display: c.display,
+                replacement: c.replacement,
+            })
+            .collect();
+        Ok((start, pairs))
+    }
+}
+
+impl Hinter for ShellHelper<'_> {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper<'_> {}
+
+impl Validator for ShellHelper<'_> {}
+
+impl Helper for ShellHelper<'_> {}
+
+/// Reads lines through a `rustyline` editor, with emacs/vi key bindings,
+/// kill/yank, reverse-i-search, tab completion and a persistent history file.
+pub(super) struct EditorLines<'a> {
+    editor: Editor<ShellHelper<'a>, DefaultHistory>,
+    history_file: Option<PathBuf>,
+}
+
+impl<'a> EditorLines<'a> {
+    pub(super) fn new(
+        history_file: Option<PathBuf>,
+        helper: Option<ShellHelper<'a>>,
+    ) -> std::io::Result<Self> {
+        let config = Config::builder()
+            .completion_type(CompletionType::List)
+            .auto_add_history(false)
+            .history_ignore_space(true)
+            .history_ignore_dups(true)
+            .and_then(|b| b.max_history_size(MAX_HISTORY_SIZE))
+            .map_err(readline_to_io)?
+            .build();
+
+        let mut editor = Editor::with_config(config).map_err(readline_to_io)?;
+        editor.set_helper(helper);
+
+        if let Some(path) = &history_file {
+            match editor.load_history(path) {
+                Ok(()) => debug!("loaded history from {}", path.display()),
+                Err(ReadlineError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => warn!("cannot load history from {}: {e}", path.display()),
+            }
+        }
+
+        Ok(Self {
+            editor,
+            history_file,
+        })
+    }
+
+    fn remember(&mut self, line: &str) {
+        if line.trim().is_empty() {
+            return;
+        }
+        match self.editor.add_history_entry(line) {
+            Ok(true) => {}
+            Ok(false) => return, // ignored as duplicate or space-prefixed
+            Err(e) => {
+                warn!("cannot add history entry: {e}");
+                return;
+            }
+        }
+
+        // Append right away so that history survives crashes and concurrent
+        // sessions of the same shell interleave instead of overwriting.
+        if let Some(path) = &self.history_file {
+            if let Some(dir) = path.parent() {
+                if let Err(e) = std::fs::create_dir_all(dir) {
+                    warn!("cannot create history directory {}: {e}", dir.display());
+                    return;
+                }
+            }
+            if let Err(e) = self.editor.append_history(path) {
+                warn!("cannot save history to {}: {e}", path.display());
+            }
+        }
+    }
+
+    #[cfg(test)]
+    fn history(&self) -> Vec<String> {
+        self.editor.history().iter().cloned().collect()
+    }
+}
+
+impl LineSource for EditorLines<'_> {
+    fn read_line(&mut self, prompt: &str) -> std::io::Result<Option<String>> {
+        match self.editor.readline(prompt) {
+            Ok(line) => {
+                self.remember(&line);
+                Ok(Some(line))
+            }
+            // Ctrl-C abandons the current line, like in any other shell
+            Err(ReadlineError::Interrupted) => Ok(Some(String::new())),
+            Err(ReadlineError::Eof) => Ok(None),
+            Err(e) => Err(readline_to_io(e)),
+        }
+    }
+}
+
+fn readline_to_io(e: ReadlineError) -> std::io::Error {
+    match e {
+        ReadlineError::Io(e) => e,
+        e => std::io::Error::other(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_is_appended_and_reloaded() {
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        let path = dir.path().join("nested").join("test_history");
+
+        let mut first = EditorLines::new(Some(path.clone()), None).expect("editor");
+        first.remember("version");
+        first.remember("   ");
+        first.remember("pwd");
+        assert!(path.exists(), "history file should be created");
+
+        let second = EditorLines::new(Some(path), None).expect("editor");
+        assert_eq!(second.history(), vec!["version", "pwd"]);
+    }
+
+    #[test]
+    fn missing_history_file_is_not_an_error() {
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        let lines = EditorLines::new(Some(dir.path().join("absent")), None).expect("editor");
+        assert!(lines.history().is_empty());
+    }
+
+    #[test]
+    fn helper_completes_through_the_shell() {
+        let sh = crate::shell::tests::build_basic_shell(
+            crate::ShellConfig::new("helper-test", "test-pkg", "0.0.1").no_init_tracing(),
+        );
+        let helper = ShellHelper::new(&sh);
+        let history = DefaultHistory::new();
+        let ctx = Context::new(&history);
+        let (start, pairs) = helper.complete("ver", 3, &ctx).expect("complete");
+        assert_eq!(start, 0);
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(
+            pairs.first().map(|p| p.replacement.as_str()),
+            Some("version ")
+        );
+    }
+
+    #[test]
+    fn no_history_file_keeps_history_in_memory() {
+        let mut lines = EditorLines::new(None, None).expect("editor");
+        lines.remember("exit");
+        assert_eq!(lines.history(), vec!["exit"]);
+    }
+}
@@ -0,0 +1,745 @@
+use std::fmt::Display;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::SystemTime;
+
+use clap::{Args, Subcommand, ValueHint};
+
+use super::{BasicShell, HandlerResult};
+use crate::vfs::{Vfs, VfsError, VfsFileType, VfsResult, VfsWriteMode};
+
+#[derive(Subcommand)]
+pub enum VfsFileCommands {
+    /// Create directories
+    Mkdir {
+        /// Create parent directories as needed, no error if existing
+        #[arg(short = 'p', long = "parents")]
+        parents: bool,
+
+        /// Directories to create
+        #[arg(required = true, value_hint = ValueHint::DirPath)]
+        paths: Vec<PathBuf>,
+    },
+
+    /// Remove files or directories
+    Rm(RmArgs),
+
+    /// Move or rename files and directories
+    Mv(MvArgs),
+
+    /// Copy files and directories
+    Cp(CpArgs),
+
+    /// Create empty files or update their modification time
+    Touch {
+        /// Do not create missing files
+        #[arg(short = 'c', long = "no-create")]
+        no_create: bool,
+
+        /// Files to touch
+        #[arg(required = true, value_hint = ValueHint::FilePath)]
+        paths: Vec<PathBuf>,
+    },
+}
+
+#[derive(Args)]
+pub struct RmArgs {
+    /// Remove directories and their contents recursively
+    #[arg(short = 'r', visible_short_alias = 'R', long = "recursive")]
+    recursive: bool,
+
+    /// Ignore nonexistent files, never prompt
+    #[arg(short = 'f', long = "force", overrides_with = "interactive")]
+    force: bool,
+
+    /// Prompt before every removal
+    #[arg(short = 'i', overrides_with = "force")]
+    interactive: bool,
+
+    /// Files and directories to remove
+    #[arg(required_unless_present = "force", value_hint = ValueHint::AnyPath)]
+    paths: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct MvArgs {
+    /// Do not prompt before overwriting
+    #[arg(short = 'f', long = "force", overrides_with = "interactive")]
+    force: bool,
+
+    /// Prompt before overwriting
+    #[arg(short = 'i', long = "interactive", overrides_with = "force")]
+    interactive: bool,
+
+    /// Sources followed by the destination
+    #[arg(required = true, num_args = 2.., value_name = "PATH", value_hint = ValueHint::AnyPath)]
+    paths: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct CpArgs {
+    /// Copy directories recursively
+    #[arg(short = 'r', visible_short_alias = 'R', long = "recursive")]
+    recursive: bool,
+
+    /// Replace destination files that cannot be opened
+    #[arg(short = 'f', long = "force", overrides_with = "interactive")]
+    force: bool,
+
+    /// Prompt before overwriting
+    #[arg(short = 'i', long = "interactive", overrides_with = "force")]
+    interactive: bool,
+
+    /// Sources followed by the destination
+    #[arg(required = true, num_args = 2.., value_name = "PATH", value_hint = ValueHint::AnyPath)]
+    paths: Vec<PathBuf>,
+}
+
+pub fn handle_vfs_file_command(sh: &BasicShell, cmd: VfsFileCommands) -> HandlerResult {
+    sh.with_vfs(|vfs| run(vfs, cmd, &mut confirm_on_stdin))
+}
+
+/// Ask `question` on stderr, only an answer starting with `y` is a yes.
+fn confirm_on_stdin(question: &str) -> bool {
+    eprint!("{question} ");
+    let _ = std::io::stderr().flush();
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    answer.trim_start().starts_with(['y', 'Y'])
+}
+
+fn run(
+    vfs: &mut dyn Vfs,
+    cmd: VfsFileCommands,
+    confirm: &mut dyn FnMut(&str) -> bool,
+) -> HandlerResult {
+    match cmd {
+        VfsFileCommands::Mkdir { parents, paths } => {
+            let mut op = Op::new("mkdir", confirm);
+            for path in &paths {
+                let result = if parents {
+                    vfs.create_dir_all(path)
+                } else {
+                    vfs.create_dir(path)
+                };
+                op.check(result);
+            }
+            Ok(op.status)
+        }
+        VfsFileCommands::Rm(args) => {
+            let mut op = Op::new("rm", confirm);
+            rm(vfs, &args, &mut op);
+            Ok(op.status)
+        }
+        VfsFileCommands::Mv(args) => {
+            let mut op = Op::new("mv", confirm);
+            mv(vfs, &args, &mut op)?;
+            Ok(op.status)
+        }
+        VfsFileCommands::Cp(args) => {
+            let mut op = Op::new("cp", confirm);
+            cp(vfs, &args, &mut op)?;
+            Ok(op.status)
+        }
+        VfsFileCommands::Touch { no_create, paths } => {
+            let mut op = Op::new("touch", confirm);
+            for path in &paths {
+                let result = match vfs.exists(path) {
+                    Ok(true) => vfs.set_modified(path, SystemTime::now()),
+                    Ok(false) if no_create => continue,
+                    Ok(false) => vfs.open_write(path, VfsWriteMode::CreateNew).map(drop),
+                    Err(e) => Err(e),
+                };
+                op.check(result);
+            }
+            Ok(op.status)
+        }
+    }
+}
+
+/// A command working through its operands: failures are reported and turn
+/// the status into a failure, but do not stop the command.
+struct Op<'a> {
+    cmd: &'static str,
+    confirm: &'a mut dyn FnMut(&str) -> bool,
+    status: ExitCode,
+}
+
+impl<'a> Op<'a> {
+    fn new(cmd: &'static str, confirm: &'a mut dyn FnMut(&str) -> bool) -> Self {
+        Self {
+            cmd,
+            confirm,
+            status: ExitCode::SUCCESS,
+        }
+    }
+
+    fn fail(&mut self, e: impl Display) {
+        eprintln!("{}: {e}", self.cmd);
+        self.status = ExitCode::FAILURE;
+    }
+
+    fn check<T>(&mut self, result: VfsResult<T>) -> Option<T> {
+        result.map_err(|e| self.fail(e)).ok()
+    }
+
+    fn ask(&mut self, question: impl Display) -> bool {
+        (self.confirm)(&format!("{}: {question}?", self.cmd))
+    }
+}
+
+// -- rm ----------------------------------------------------------------------
+
+fn rm(vfs: &mut dyn Vfs, args: &RmArgs, op: &mut Op<'_>) {
+    for path in &args.paths {
+        // `.`, `..` and the root have no file name
+        if path.file_name().is_none() || vfs.resolve(path).parent().is_none() {
+            op.fail(format_args!("refusing to remove '{}'", path.display()));
+            continue;
+        }
+        // Symlinks are removed themselves, whatever they point to
+        let link = is_symlink(vfs, path);
+        let dir = match vfs.metadata(path) {
+            Ok(meta) => meta.is_dir() && !link,
+            Err(_) if link => false,
+            Err(VfsError::NotFound(_)) if args.force => continue,
+            Err(e) => {
+                op.fail(e);
+                continue;
+            }
+        };
+        if dir && !args.recursive {
+            op.fail(VfsError::IsADirectory(vfs.resolve(path)));
+            continue;
+        }
+        if args.interactive {
+            let what = if dir {
+                "directory and its contents"
+            } else if link {
+                "symbolic link"
+            } else {
+                "file"
+            };
+            if !op.ask(format_args!("remove {what} '{}'", path.display())) {
+                continue;
+            }
+        }
+        let result = if dir {
+            vfs.remove_dir_all(path)
+        } else {
+            vfs.remove_file(path)
+        };
+        op.check(result);
+    }
+}
+
+/// Whether `path` is a symlink, looked up in its parent directory because
+/// [`Vfs::metadata`] follows symlinks.
+fn is_symlink(vfs: &dyn Vfs, path: &Path) -> bool {
+    let path = vfs.resolve(path);
+    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+        return false;
+    };
+    vfs.read_dir(parent).is_ok_and(|entries| {
+        entries
+            .iter()
+            .any(|e| e.name == name && e.file_type == VfsFileType::Symlink)
+    })
+}
+
+// -- mv / cp -----------------------------------------------------------------
+
+/// Resolve the sources and their targets: inside `dest` if that is a
+/// directory, `dest` itself otherwise, which then takes a single source.
+fn targets(vfs: &dyn Vfs, sources: &[PathBuf], dest: &Path) -> VfsResult<Vec<(PathBuf, PathBuf)>> {
+    let dest = vfs.resolve(dest);
+    let into_dir = vfs.metadata(&dest).is_ok_and(|meta| meta.is_dir());
+    if !into_dir && sources.len() > 1 {
+        return Err(VfsError::NotADirectory(dest));
+    }
+    Ok(sources
+        .iter()
+        .map(|source| {
+            let from = vfs.resolve(source);
+            let to = if into_dir {
+                dest.join(from.file_name().unwrap_or_default())
+            } else {
+                dest.clone()
+            };
+            (from, to)
+        })
+        .collect())
+}
+
+fn mv(vfs: &mut dyn Vfs, args: &MvArgs, op: &mut Op<'_>) -> VfsResult<()> {
+    let Some((dest, sources)) = args.paths.split_last() else {
+        return Ok(());
+    };
+    for (from, to) in targets(vfs, sources, dest)? {
+        if from == to {
+            op.fail(format_args!(
+                "'{}' and '{}' are the same file",
+                from.display(),
+                to.display()
+            ));
+            continue;
+        }
+        if to.starts_with(&from) {
+            op.fail(format_args!(
+                "cannot move '{}' to a subdirectory of itself",
+                from.display()
+            ));
+            continue;
+        }
+        if args.interactive
+            && vfs.exists(&to).unwrap_or(true)
+            && !op.ask(format_args!("overwrite '{}'", to.display()))
+        {
+            continue;
+        }
+        match vfs.rename(&from, &to) {
+            Err(VfsError::CrossesDevices(_)) => op.check(move_across(vfs, &from, &to)),
+            result => op.check(result),
+        };
+    }
+    Ok(())
+}
+
+/// Move by copying and removing, for renames between mount points.
+fn move_across(vfs: &mut dyn Vfs, from: &Path, to: &Path) -> VfsResult<()> {
+    if !vfs.metadata(from)?.is_dir() {
+        vfs.copy(from, to)?;
+        return vfs.remove_file(from);
+    }
+    match vfs.create_dir(to) {
+        // Like rename, replace an empty directory
+        Err(VfsError::AlreadyExists(_)) if vfs.read_dir(to)?.is_empty() => {}
+        result => result?,
+    }
+    for entry in vfs.read_dir(from)? {
+        move_across(vfs, &from.join(&entry.name), &to.join(&entry.name))?;
+    }
+    vfs.remove_dir(from)
+}
+
+fn cp(vfs: &mut dyn Vfs, args: &CpArgs, op: &mut Op<'_>) -> VfsResult<()> {
+    let Some((dest, sources)) = args.paths.split_last() else {
+        return Ok(());
+    };
+    for (from, to) in targets(vfs, sources, dest)? {
+        let Some(meta) = op.check(vfs.metadata(&from)) else {
+            continue;
+        };
+        if !meta.is_dir() {
+            copy_file(vfs, args, op, &from, &to);
+        } else if !args.recursive {
+            op.fail(format_args!(
+                "-r not specified; omitting directory '{}'",
+                from.display()
+            ));
+        } else if to.starts_with(&from) {
+            op.fail(format_args!(
+                "cannot copy '{}' into itself, '{}'",
+                from.display(),
+                to.display()
+            ));
+        } else {
+            copy_tree(vfs, args, op, &from, &to);
+        }
+    }
+    Ok(())
+}
+
+fn copy_file(vfs: &mut dyn Vfs, args: &CpArgs, op: &mut Op<'_>, from: &Path, to: &Path) {
+    if from == to {
+        op.fail(format_args!(
+            "'{}' and '{}' are the same file",
+            from.display(),
+            to.display()
+        ));
+        return;
+    }
+    if args.interactive
+        && vfs.exists(to).unwrap_or(true)
+        && !op.ask(format_args!("overwrite '{}'", to.display()))
+    {
+        return;
+    }
+    let mut result = vfs.copy(from, to);
+    // Like cp -f, replace a destination that cannot be written to
+    if args.force && matches!(&result, Err(VfsError::PermissionDenied(path)) if path == to) {
+        result = vfs.remove_file(to).and_then(|()| vfs.copy(from, to));
+    }
+    op.check(result);
+}
+
+fn copy_tree(vfs: &mut dyn Vfs, args: &CpArgs, op: &mut Op<'_>, from: &Path, to: &Path) {
+    let created = match vfs.metadata(to) {
+        Ok(meta) if meta.is_dir() => Ok(()),
+        Ok(_) => Err(VfsError::NotADirectory(to.into())),
+        Err(VfsError::NotFound(_)) => vfs.create_dir(to),
+        Err(e) => Err(e),
+    };
+    if op.check(created).is_none() {
+        return;
+    }
+    let Some(entries) = op.check(vfs.read_dir(from)) else {
+        return;
+    };
+    for entry in entries {
+        let (from, to) = (from.join(&entry.name), to.join(&entry.name));
+        match entry.file_type {
+            VfsFileType::Dir => copy_tree(vfs, args, op, &from, &to),
+            VfsFileType::File => copy_file(vfs, args, op, &from, &to),
+            // Links to files are copied as files, links to directories
+            // could lead anywhere, even into a loop
+            VfsFileType::Symlink if vfs.metadata(&from).is_ok_and(|meta| meta.is_file()) => {
+                copy_file(vfs, args, op, &from, &to);
+            }
+            VfsFileType::Symlink | VfsFileType::Other => op.fail(format_args!(
+                "omitting '{}': not a regular file or directory",
+                from.display()
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::shell::tests::{parse_cmd, try_parse_cmd};
+    use crate::vfs::{MemVfs, MountTable};
+    use crate::ShellError;
+    use std::time::UNIX_EPOCH;
+
+    fn test_fs() -> MemVfs {
+        MemVfs::from_files([
+            ("/docs/a.txt", "alpha"),
+            ("/docs/b.txt", "beta"),
+            ("/docs/deep/c.txt", "gamma"),
+            ("/top.txt", "top"),
+        ])
+        .with_dir("/empty")
+    }
+
+    /// Run `line` answering every prompt with `answer`, returns the status
+    /// and the prompts asked.
+    fn exec(fs: &mut dyn Vfs, line: &str, answer: bool) -> (ExitCode, Vec<String>) {
+        let cmd = parse_cmd(line);
+        let mut asked = Vec::new();
+        let status = run(fs, cmd, &mut |question| {
+            asked.push(question.to_owned());
+            answer
+        })
+        .expect("run");
+        (status, asked)
+    }
+
+    fn status(fs: &mut dyn Vfs, line: &str) -> ExitCode {
+        let (status, asked) = exec(fs, line, false);
+        assert!(asked.is_empty(), "{line} prompted: {asked:?}");
+        status
+    }
+
+    fn exists(fs: &MemVfs, path: &str) -> bool {
+        fs.exists(Path::new(path)).expect("exists")
+    }
+
+    fn contents(fs: &MemVfs, path: &str) -> String {
+        let snapshot = fs.snapshot();
+        let data = snapshot.file(Path::new(path)).expect("file");
+        String::from_utf8(data.to_vec()).expect("utf-8")
+    }
+
+    // -- mkdir -------------------------------------------------------------
+
+    #[test]
+    fn mkdir_creates_directories() {
+        let mut fs = test_fs();
+        assert_eq!(status(&mut fs, "mkdir new docs/newer"), ExitCode::SUCCESS);
+        assert!(fs.metadata(Path::new("/new")).expect("new").is_dir());
+        assert!(fs
+            .metadata(Path::new("/docs/newer"))
+            .expect("newer")
+            .is_dir());
+    }
+
+    #[test]
+    fn mkdir_needs_parents_unless_p() {
+        let mut fs = test_fs();
+        assert_eq!(status(&mut fs, "mkdir x/y/z"), ExitCode::FAILURE);
+        assert!(!exists(&fs, "/x"));
+        assert_eq!(status(&mut fs, "mkdir -p x/y/z docs"), ExitCode::SUCCESS);
+        assert!(exists(&fs, "/x/y/z"));
+    }
+
+    #[test]
+    fn mkdir_fails_on_existing_entries() {
+        let mut fs = test_fs();
+        assert_eq!(status(&mut fs, "mkdir docs"), ExitCode::FAILURE);
+        assert_eq!(status(&mut fs, "mkdir -p top.txt/sub"), ExitCode::FAILURE);
+    }
+
+    // -- rm ----------------------------------------------------------------
+
+    #[test]
+    fn rm_removes_files_but_not_directories() {
+        let mut fs = test_fs();
+        assert_eq!(status(&mut fs, "rm top.txt docs"), ExitCode::FAILURE);
+        assert!(!exists(&fs, "/top.txt"));
+        assert!(exists(&fs, "/docs"));
+    }
+
+    #[test]
+    fn rm_recursive_removes_trees() {
+        let mut fs = test_fs();
+        assert_eq!(status(&mut fs, "rm -r docs empty"), ExitCode::SUCCESS);
+        assert!(!exists(&fs, "/docs"));
+        assert!(!exists(&fs, "/docs/deep/c.txt"));
+        assert!(!exists(&fs, "/empty"));
+        assert!(exists(&fs, "/top.txt"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rm_removes_symlinks_not_their_targets() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        std::fs::create_dir_all(dir.path().join("d/sub")).expect("mkdir");
+        std::fs::write(dir.path().join("d/sub/f"), "f").expect("write");
+        symlink("sub", dir.path().join("d/sl")).expect("symlink");
+        symlink("sub", dir.path().join("d/sl2")).expect("symlink");
+        symlink("nowhere", dir.path().join("d/dangling")).expect("symlink");
+        let mut fs = crate::vfs::HostVfs::new(dir.path()).expect("host vfs");
+
+        assert_eq!(status(&mut fs, "rm d/sl d/dangling"), ExitCode::SUCCESS);
+        let (status, asked) = exec(&mut fs, "rm -i d/sl2", true);
+        assert_eq!(status, ExitCode::SUCCESS);
+        assert_eq!(asked, ["rm: remove symbolic link 'd/sl2'?"]);
+        let names: Vec<_> = std::fs::read_dir(dir.path().join("d"))
+            .expect("read_dir")
+            .map(|e| e.expect("entry").file_name())
+            .collect();
+        assert_eq!(names, ["sub"]);
+        assert!(dir.path().join("d/sub/f").is_file());
+    }
+
+    #[test]
+    fn rm_force_ignores_missing_files() {
+        let mut fs = test_fs();
+        assert_eq!(status(&mut fs, "rm nope top.txt"), ExitCode::FAILURE);
+        assert_eq!(status(&mut fs, "rm -f nope docs/a.txt"), ExitCode::SUCCESS);
+        assert!(!exists(&fs, "/docs/a.txt"));
+        assert_eq!(status(&mut fs, "rm -f"), ExitCode::SUCCESS);
+        assert!(try_parse_cmd::<VfsFileCommands>("rm").is_err());
+    }
+
+    #[test]
+    fn rm_refuses_dot_dotdot_and_root() {
+        let mut fs = test_fs();
+        fs.cd(Path::new("/docs")).expect("cd");
+        for line in ["rm -rf .", "rm -rf ..", "rm -rf /", "rm -rf deep/../.."] {
+            assert_eq!(status(&mut fs, line), ExitCode::FAILURE, "{line}");
+        }
+        assert!(exists(&fs, "/docs/deep/c.txt"));
+    }
+
+    #[test]
+    fn rm_interactive_asks_first() {
+        let mut fs = test_fs();
+        let (_, asked) = exec(&mut fs, "rm -i top.txt", false);
+        assert_eq!(asked, ["rm: remove file 'top.txt'?"]);
+        assert!(exists(&fs, "/top.txt"));
+
+        let (_, asked) = exec(&mut fs, "rm -ri top.txt docs", true);
+        assert_eq!(asked.len(), 2);
+        assert!(!exists(&fs, "/top.txt"));
+        assert!(!exists(&fs, "/docs"));
+    }
+
+    #[test]
+    fn rm_last_of_force_and_interactive_wins() {
+        let mut fs = test_fs();
+        let (_, asked) = exec(&mut fs, "rm -f -i docs/a.txt", true);
+        assert_eq!(asked.len(), 1);
+        assert_eq!(status(&mut fs, "rm -i -f docs/b.txt"), ExitCode::SUCCESS);
+        assert!(!exists(&fs, "/docs/b.txt"));
+    }
+
+    // -- mv ----------------------------------------------------------------
+
+    #[test]
+    fn mv_renames_files_and_directories() {
+        let mut fs = test_fs();
+        assert_eq!(status(&mut fs, "mv top.txt renamed.txt"), ExitCode::SUCCESS);
+        assert_eq!(contents(&fs, "/renamed.txt"), "top");
+        assert!(!exists(&fs, "/top.txt"));
+
+        assert_eq!(status(&mut fs, "mv docs papers"), ExitCode::SUCCESS);
+        assert_eq!(contents(&fs, "/papers/deep/c.txt"), "gamma");
+        assert!(!exists(&fs, "/docs"));
+    }
+
+    #[test]
+    fn mv_into_directory() {
+        let mut fs = test_fs();
+        assert_eq!(
+            status(&mut fs, "mv top.txt docs/a.txt empty"),
+            ExitCode::SUCCESS
+        );
+        assert_eq!(contents(&fs, "/empty/top.txt"), "top");
+        assert_eq!(contents(&fs, "/empty/a.txt"), "alpha");
+    }
+
+    #[test]
+    fn mv_several_sources_need_a_directory() {
+        let mut fs = test_fs();
+        assert!(try_parse_cmd::<VfsFileCommands>("mv top.txt").is_err());
+        let cmd = parse_cmd("mv top.txt docs/a.txt nope");
+        let result = run(&mut fs, cmd, &mut |_| true);
+        assert!(matches!(
+            result,
+            Err(ShellError::Vfs(VfsError::NotADirectory(_)))
+        ));
+        assert!(exists(&fs, "/top.txt"));
+    }
+
+    #[test]
+    fn mv_guards_against_itself() {
+        let mut fs = test_fs();
+        assert_eq!(status(&mut fs, "mv docs docs/deep"), ExitCode::FAILURE);
+        assert_eq!(status(&mut fs, "mv top.txt /top.txt"), ExitCode::FAILURE);
+        assert!(exists(&fs, "/docs/deep/c.txt"));
+        assert!(exists(&fs, "/top.txt"));
+    }
+
+    #[test]
+    fn mv_interactive_asks_before_overwriting() {
+        let mut fs = test_fs();
+        let (_, asked) = exec(&mut fs, "mv -i top.txt docs/a.txt", false);
+        assert_eq!(asked, ["mv: overwrite '/docs/a.txt'?"]);
+        assert_eq!(contents(&fs, "/docs/a.txt"), "alpha");
+
+        // Nothing to overwrite, nothing to ask
+        assert_eq!(
+            status(&mut fs, "mv -i top.txt docs/new.txt"),
+            ExitCode::SUCCESS
+        );
+        assert_eq!(
+            status(&mut fs, "mv -i -f docs/new.txt docs/a.txt"),
+            ExitCode::SUCCESS
+        );
+        assert_eq!(contents(&fs, "/docs/a.txt"), "top");
+    }
+
+    #[test]
+    fn mv_copies_between_mount_points() {
+        let mut fs = MountTable::from(Box::new(test_fs()) as Box<dyn Vfs>);
+        fs.mount("/scratch", Box::new(MemVfs::new()))
+            .expect("mount");
+        let cmd = parse_cmd("mv docs top.txt /scratch");
+        let status = run(&mut fs, cmd, &mut |_| false).expect("run");
+        assert_eq!(status, ExitCode::SUCCESS);
+        for path in ["/docs", "/top.txt"] {
+            assert!(!fs.exists(Path::new(path)).expect("exists"), "{path}");
+        }
+        for path in ["/scratch/docs/deep/c.txt", "/scratch/top.txt"] {
+            assert!(fs.exists(Path::new(path)).expect("exists"), "{path}");
+        }
+    }
+
+    // -- cp ----------------------------------------------------------------
+
+    #[test]
+    fn cp_copies_files() {
+        let mut fs = test_fs();
+        assert_eq!(status(&mut fs, "cp top.txt copy.txt"), ExitCode::SUCCESS);
+        assert_eq!(contents(&fs, "/copy.txt"), "top");
+        assert_eq!(contents(&fs, "/top.txt"), "top");
+
+        assert_eq!(status(&mut fs, "cp docs/a.txt copy.txt"), ExitCode::SUCCESS);
+        assert_eq!(contents(&fs, "/copy.txt"), "alpha");
+
+        assert_eq!(
+            status(&mut fs, "cp top.txt docs/b.txt empty"),
+            ExitCode::SUCCESS
+        );
+        assert_eq!(contents(&fs, "/empty/b.txt"), "beta");
+    }
+
+    #[test]
+    fn cp_needs_recursive_for_directories() {
+        let mut fs = test_fs();
+        assert_eq!(status(&mut fs, "cp docs backup"), ExitCode::FAILURE);
+        assert!(!exists(&fs, "/backup"));
+    }
+
+    #[test]
+    fn cp_recursive_copies_trees() {
+        let mut fs = test_fs();
+        assert_eq!(status(&mut fs, "cp -r docs backup"), ExitCode::SUCCESS);
+        assert_eq!(contents(&fs, "/backup/a.txt"), "alpha");
+        assert_eq!(contents(&fs, "/backup/deep/c.txt"), "gamma");
+        assert_eq!(contents(&fs, "/docs/deep/c.txt"), "gamma");
+
+        // An existing directory as destination gets a copy inside
+        assert_eq!(status(&mut fs, "cp -R docs empty"), ExitCode::SUCCESS);
+        assert_eq!(contents(&fs, "/empty/docs/deep/c.txt"), "gamma");
+    }
+
+    #[test]
+    fn cp_guards_against_itself() {
+        let mut fs = test_fs();
+        assert_eq!(status(&mut fs, "cp -r docs docs/deep"), ExitCode::FAILURE);
+        assert!(!exists(&fs, "/docs/deep/docs"));
+        assert_eq!(status(&mut fs, "cp top.txt ."), ExitCode::FAILURE);
+        assert_eq!(contents(&fs, "/top.txt"), "top");
+    }
+
+    #[test]
+    fn cp_interactive_asks_before_overwriting() {
+        let mut fs = test_fs();
+        let (_, asked) = exec(&mut fs, "cp -ri docs empty/docs", false);
+        assert!(asked.is_empty());
+        let (_, asked) = exec(&mut fs, "cp -ri docs empty", false);
+        assert_eq!(asked.len(), 3);
+        assert_eq!(contents(&fs, "/empty/docs/a.txt"), "alpha");
+        let (_, asked) = exec(&mut fs, "cp -i top.txt empty/docs/a.txt", true);
+        assert_eq!(asked, ["cp: overwrite '/empty/docs/a.txt'?"]);
+        assert_eq!(contents(&fs, "/empty/docs/a.txt"), "top");
+    }
+
+    // -- touch -------------------------------------------------------------
+
+    #[test]
+    fn touch_creates_and_updates_files() {
+        let mut fs = test_fs();
+        let old = UNIX_EPOCH + std::time::Duration::from_secs(100);
+        for path in ["/top.txt", "/docs"] {
+            fs.set_modified(Path::new(path), old).expect("mtime");
+        }
+        assert_eq!(
+            status(&mut fs, "touch new.txt top.txt docs"),
+            ExitCode::SUCCESS
+        );
+        assert_eq!(contents(&fs, "/new.txt"), "");
+        assert_eq!(contents(&fs, "/top.txt"), "top");
+        for path in ["/top.txt", "/docs"] {
+            let modified = fs.metadata(Path::new(path)).expect("meta").modified;
+            assert!(modified > Some(old), "{path}");
+        }
+    }
+
+    #[test]
+    fn touch_no_create() {
+        let mut fs = test_fs();
+        assert_eq!(status(&mut fs, "touch -c new.txt"), ExitCode::SUCCESS);
+        assert!(!exists(&fs, "/new.txt"));
+        assert_eq!(status(&mut fs, "touch nope/new.txt"), ExitCode::FAILURE);
+    }
+}
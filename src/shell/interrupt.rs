@@ -0,0 +1,52 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Once;
+
+use tracing::warn;
+
+/// How many [`Interrupt`]s are alive.
+static CATCHING: AtomicUsize = AtomicUsize::new(0);
+
+/// How often Ctrl-C was pressed while any were.
+static RAISED: AtomicUsize = AtomicUsize::new(0);
+
+/// Catches Ctrl-C while alive, for commands that run until interrupted, so
+/// they can return to the prompt instead of ending the process.
+///
+/// The first one installs a handler for Ctrl-C. Outside of an `Interrupt`,
+/// the handler ends the process as if it had none.
+pub(super) struct Interrupt {
+    /// The value of `RAISED` when it was created
+    seen: usize,
+}
+
+impl Interrupt {
+    pub(super) fn catch() -> Self {
+        static HANDLER: Once = Once::new();
+        HANDLER.call_once(|| {
+            let handler = || {
+                if CATCHING.load(Ordering::SeqCst) == 0 {
+                    std::process::exit(130);
+                }
+                RAISED.fetch_add(1, Ordering::SeqCst);
+            };
+            if let Err(e) = ctrlc::try_set_handler(handler) {
+                warn!("cannot catch Ctrl-C: {e}");
+            }
+        });
+        CATCHING.fetch_add(1, Ordering::SeqCst);
+        Self {
+            seen: RAISED.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Whether Ctrl-C was pressed since this was created.
+    pub(super) fn raised(&self) -> bool {
+        RAISED.load(Ordering::SeqCst) != self.seen
+    }
+}
+
+impl Drop for Interrupt {
+    fn drop(&mut self) {
+        CATCHING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
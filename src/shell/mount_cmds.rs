@@ -0,0 +1,192 @@
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use clap::{Subcommand, ValueHint};
+
+use super::{BasicShell, HandlerResult, ShellError, HANDLER_SUCCESS};
+use crate::vfs::{MemVfs, MountTable, Vfs, VfsError};
+
+#[derive(Subcommand)]
+pub enum VfsMountCommands {
+    /// Attach a backend at a mount point
+    Mount {
+        /// Mount an empty in-memory directory instead of the configured
+        /// backend
+        #[arg(long = "mem")]
+        mem: bool,
+
+        /// Mount the contents of the tar or zip archive FILE, read-only
+        #[cfg(any(feature = "tar", feature = "zip"))]
+        #[arg(long = "archive", value_name = "FILE", value_hint = ValueHint::FilePath, conflicts_with = "mem")]
+        archive: Option<PathBuf>,
+
+        /// Where to attach it; without an option, a configured mount point
+        #[arg(value_hint = ValueHint::DirPath)]
+        point: PathBuf,
+    },
+
+    /// Detach the backend at a mount point
+    Umount {
+        /// The mount point to detach
+        #[arg(value_hint = ValueHint::DirPath)]
+        point: PathBuf,
+    },
+
+    /// List the mount points
+    Mounts,
+}
+
+pub fn handle_vfs_mount_command(sh: &BasicShell, cmd: VfsMountCommands) -> HandlerResult {
+    sh.with_mounts(|mounts| {
+        run(
+            mounts,
+            cmd,
+            &|point| sh.lookup_vfs(point),
+            &mut std::io::stdout().lock(),
+        )
+    })
+}
+
+/// Creates the backend configured for a mount point, if there is one.
+type Lookup<'a> = &'a dyn Fn(&Path) -> Result<Option<Box<dyn Vfs>>, ShellError>;
+
+fn run(
+    mounts: &mut MountTable,
+    cmd: VfsMountCommands,
+    lookup: Lookup<'_>,
+    out: &mut dyn Write,
+) -> HandlerResult {
+    match cmd {
+        #[cfg(any(feature = "tar", feature = "zip"))]
+        VfsMountCommands::Mount {
+            archive: Some(archive),
+            point,
+            ..
+        } => {
+            let vfs = crate::vfs::ArchiveVfs::open(mounts, &archive)?;
+            let point = mounts.resolve(&point);
+            mounts.mount(point, Box::new(vfs))?;
+        }
+        VfsMountCommands::Mount { mem, point, .. } => {
+            let point = mounts.resolve(&point);
+            let vfs: Box<dyn Vfs> = if mem {
+                Box::new(MemVfs::new())
+            } else if let Some(vfs) = lookup(&point)? {
+                vfs
+            } else {
+                return Err(VfsError::Io {
+                    path: point,
+                    source: std::io::Error::new(
+                        ErrorKind::NotFound,
+                        "no backend configured, try --mem",
+                    ),
+                }
+                .into());
+            };
+            mounts.mount(point, vfs)?;
+        }
+        VfsMountCommands::Umount { point } => drop(mounts.umount(point)?),
+        VfsMountCommands::Mounts => {
+            for point in mounts.mount_points() {
+                writeln!(out, "{}", point.display())?;
+            }
+        }
+    }
+    HANDLER_SUCCESS
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::shell::tests::parse_cmd;
+
+    fn exec(mounts: &mut MountTable, line: &str) -> Result<String, ShellError> {
+        let lookup = |point: &Path| {
+            Ok((point == Path::new("/configured"))
+                .then(|| Box::new(MemVfs::from_files([("/a.txt", "a")])) as Box<dyn Vfs>))
+        };
+        let mut out = Vec::new();
+        run(mounts, parse_cmd(line), &lookup, &mut out)?;
+        Ok(String::from_utf8(out).expect("utf-8"))
+    }
+
+    #[test]
+    fn mount_list_and_umount() {
+        let mut mounts = MountTable::from(Box::new(MemVfs::new()) as Box<dyn Vfs>);
+        exec(&mut mounts, "mount --mem /scratch").expect("mount");
+        exec(&mut mounts, "mount --mem /data/archive").expect("mount");
+        assert_eq!(
+            exec(&mut mounts, "mounts").expect("mounts"),
+            "/\n/data/archive\n/scratch\n"
+        );
+        assert!(mounts.metadata(Path::new("/data")).expect("meta").is_dir());
+
+        exec(&mut mounts, "umount /data/archive").expect("umount");
+        assert_eq!(
+            exec(&mut mounts, "mounts").expect("mounts"),
+            "/\n/scratch\n"
+        );
+    }
+
+    #[test]
+    fn mount_configured_backend() {
+        let mut mounts = MountTable::new();
+        exec(&mut mounts, "mount configured").expect("mount");
+        assert!(mounts
+            .exists(Path::new("/configured/a.txt"))
+            .expect("exists"));
+
+        let err = exec(&mut mounts, "mount /other").expect_err("unconfigured");
+        assert_eq!(
+            err.to_string(),
+            "VFS error: /other: no backend configured, try --mem"
+        );
+    }
+
+    #[test]
+    fn mount_errors() {
+        let mut mounts = MountTable::from(Box::new(MemVfs::new()) as Box<dyn Vfs>);
+        exec(&mut mounts, "mount --mem /scratch").expect("mount");
+        match exec(&mut mounts, "mount --mem /scratch") {
+            Err(ShellError::Vfs(VfsError::AlreadyExists(p))) => {
+                assert_eq!(p, Path::new("/scratch"));
+            }
+            other => panic!("expected AlreadyExists, got {other:?}"),
+        }
+        mounts.cd(Path::new("/scratch")).expect("cd");
+        let err = exec(&mut mounts, "umount .").expect_err("busy");
+        assert_eq!(err.to_string(), "VFS error: /scratch: mount point busy");
+    }
+
+    #[cfg(feature = "tar")]
+    #[test]
+    fn mount_archive() {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(3);
+        builder
+            .append_data(&mut header, "docs/a.txt", &b"a!\n"[..])
+            .expect("append");
+        let tarball = builder.into_inner().expect("finish");
+        let mut mounts = MountTable::from(
+            Box::new(MemVfs::new().with_file("/backup.tar", tarball)) as Box<dyn Vfs>,
+        );
+
+        exec(&mut mounts, "mount --archive backup.tar /backup").expect("mount");
+        assert!(mounts
+            .metadata(Path::new("/backup/docs/a.txt"))
+            .expect("meta")
+            .is_file());
+        match mounts.remove_file(Path::new("/backup/docs/a.txt")) {
+            Err(VfsError::ReadOnly(p)) => assert_eq!(p, Path::new("/backup/docs/a.txt")),
+            other => panic!("expected ReadOnly, got {other:?}"),
+        }
+
+        let err = exec(&mut mounts, "mount --archive /missing.tar /other").expect_err("missing");
+        assert_eq!(
+            err.to_string(),
+            "VFS error: /missing.tar: no such file or directory"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use clap::Subcommand;
+
+use super::{BasicShell, HandlerResult, HANDLER_SUCCESS};
+
+#[derive(Subcommand)]
+pub enum VfsOpenCommands {
+    /// Replace the VFS with the one opened from SPEC
+    Open {
+        /// What to open, e.g. a directory or URI
+        spec: String,
+    },
+
+    /// Close the VFS until the next open or reopen
+    Close,
+
+    /// Open the last VFS again, from scratch
+    Reopen,
+}
+
+pub fn handle_vfs_open_command(sh: &BasicShell, cmd: VfsOpenCommands) -> HandlerResult {
+    let mut state = sh.lock_vfs()?;
+    match cmd {
+        // The current VFS stays if the new one cannot be opened
+        VfsOpenCommands::Open { spec } => {
+            state.mounts = Some(sh.open_vfs(Some(&spec))?);
+            state.spec = Some(spec);
+            state.closed = false;
+        }
+        VfsOpenCommands::Close => {
+            state.mounts = None;
+            state.closed = true;
+        }
+        VfsOpenCommands::Reopen => {
+            state.mounts = Some(sh.open_vfs(state.spec.as_deref())?);
+            state.closed = false;
+        }
+    }
+    HANDLER_SUCCESS
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use crate::shell::tests::build_basic_shell;
+    use crate::shell::{BasicShell, ShellConfig, ShellError, VfsLookup, VfsOpener};
+    use crate::vfs::{MemVfs, VfsError};
+    use std::path::Path;
+    use std::sync::Arc;
+
+    /// Opens a `MemVfs` with a file named after the spec, or fails for
+    /// `missing`.
+    fn shell(lookup: Option<VfsLookup>) -> Arc<BasicShell> {
+        let opener: VfsOpener = Arc::new(|spec| match spec {
+            "missing" => Err(VfsError::NotFound(spec.into()).into()),
+            _ => Ok(Box::new(
+                MemVfs::new().with_file(format!("/{spec}.txt"), spec),
+            )),
+        });
+        let mut cfg = ShellConfig::new("opensh", "test-pkg", "0.0.1")
+            .no_init_tracing()
+            .vfs_opener(opener);
+        if let Some(lookup) = lookup {
+            cfg = cfg.vfs_lookup(lookup);
+        }
+        build_basic_shell(cfg)
+    }
+
+    fn exists(sh: &BasicShell, path: &str) -> Result<bool, ShellError> {
+        sh.with_vfs(|vfs| Ok(vfs.exists(Path::new(path))?))
+    }
+
+    #[test]
+    fn open_switches_the_vfs() {
+        let sh = shell(None);
+        assert!(exists(&sh, "/").is_err());
+        assert_eq!(sh.prompt(), "opensh> ");
+
+        sh.run_line("open a").expect("open a");
+        assert!(exists(&sh, "/a.txt").expect("exists"));
+        assert_eq!(sh.prompt(), "opensh [a]> ");
+
+        sh.run_line("open b").expect("open b");
+        assert!(!exists(&sh, "/a.txt").expect("exists"));
+        assert!(exists(&sh, "/b.txt").expect("exists"));
+        assert_eq!(sh.prompt(), "opensh [b]> ");
+    }
+
+    #[test]
+    fn failed_open_keeps_the_current_vfs() {
+        let sh = shell(None);
+        sh.run_line("open a").expect("open a");
+        let err = sh.run_line("open missing").expect_err("missing");
+        assert_eq!(
+            err.to_string(),
+            "VFS error: missing: no such file or directory"
+        );
+        assert!(exists(&sh, "/a.txt").expect("exists"));
+        assert_eq!(sh.prompt(), "opensh [a]> ");
+    }
+
+    #[test]
+    fn close_and_reopen() {
+        let sh = shell(None);
+        sh.run_line("open a").expect("open a");
+        sh.with_vfs(|vfs| Ok(vfs.remove_file(Path::new("/a.txt"))?))
+            .expect("rm");
+
+        sh.run_line("close").expect("close");
+        assert_eq!(sh.prompt(), "opensh> ");
+        let err = sh.run_line("ls").expect_err("closed");
+        assert!(matches!(err, ShellError::NoVfsOpen), "{err}");
+
+        // Opened from scratch, so the removed file is back
+        sh.run_line("reopen").expect("reopen");
+        assert!(exists(&sh, "/a.txt").expect("exists"));
+        assert_eq!(sh.prompt(), "opensh [a]> ");
+    }
+
+    #[test]
+    fn reopen_runs_the_configured_lookup() {
+        let lookup: VfsLookup =
+            Arc::new(|_| Ok(Box::new(MemVfs::new().with_file("/configured", ""))));
+        let sh = shell(Some(lookup));
+        assert!(exists(&sh, "/configured").expect("exists"));
+
+        sh.run_line("close").expect("close");
+        assert!(exists(&sh, "/configured").is_err());
+        sh.run_line("reopen").expect("reopen");
+        assert!(exists(&sh, "/configured").expect("exists"));
+    }
+}
@@ -0,0 +1,155 @@
+use std::io::Write;
+
+use clap::Subcommand;
+
+use super::diff::DiffArgs;
+use super::{BasicShell, HandlerResult, HANDLER_SUCCESS};
+use crate::vfs::{Vfs, VfsChange, VfsChangeKind, VfsFileType};
+
+#[derive(Subcommand)]
+pub enum VfsOverlayCommands {
+    /// Compare files or directories, or show the changes not yet
+    /// committed to the lower layer
+    Diff(DiffArgs),
+
+    /// Write the changes through to the lower layer
+    Commit,
+
+    /// Drop the changes, going back to the lower layer
+    Discard,
+}
+
+pub fn handle_vfs_overlay_command(sh: &BasicShell, cmd: VfsOverlayCommands) -> HandlerResult {
+    sh.with_vfs(move |vfs| run(vfs, &cmd, &mut std::io::stdout().lock()))
+}
+
+fn run(vfs: &mut dyn Vfs, cmd: &VfsOverlayCommands, out: &mut dyn Write) -> HandlerResult {
+    match cmd {
+        VfsOverlayCommands::Diff(args) => {
+            if let Some((a, b)) = args.paths() {
+                return super::diff::compare(vfs, a, b, args, out);
+            }
+            for change in vfs.changes()? {
+                writeln!(out, "{}", format_change(&change))?;
+            }
+        }
+        VfsOverlayCommands::Commit => {
+            let count = vfs.commit()?;
+            writeln!(
+                out,
+                "{count} {} committed",
+                crate::pluralize!("change", count)
+            )?;
+        }
+        VfsOverlayCommands::Discard => {
+            let count = vfs.changes()?.len();
+            vfs.discard()?;
+            writeln!(
+                out,
+                "{count} {} discarded",
+                crate::pluralize!("change", count)
+            )?;
+        }
+    }
+    HANDLER_SUCCESS
+}
+
+/// `A path`, `M path` or `D path`, with a `/` after directories.
+pub(super) fn format_change(change: &VfsChange) -> String {
+    let kind = match change.kind {
+        VfsChangeKind::Added => 'A',
+        VfsChangeKind::Modified => 'M',
+        VfsChangeKind::Removed => 'D',
+    };
+    let slash = if change.file_type == VfsFileType::Dir {
+        "/"
+    } else {
+        ""
+    };
+    format!("{kind} {}{slash}", change.path.display())
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::shell::tests::parse_cmd;
+    use crate::vfs::{MemVfs, OverlayVfs, VfsError, VfsWriteMode};
+    use crate::ShellError;
+    use std::path::Path;
+
+    fn test_fs() -> OverlayVfs<MemVfs, MemVfs> {
+        let lower = MemVfs::new()
+            .with_file("/docs/a.txt", "a\n")
+            .with_file("/docs/b.txt", "b\n")
+            .with_dir("/empty");
+        OverlayVfs::new(lower, MemVfs::new())
+    }
+
+    fn exec(fs: &mut dyn Vfs, line: &str) -> Result<String, ShellError> {
+        let mut out = Vec::new();
+        run(fs, &parse_cmd(line), &mut out)?;
+        Ok(String::from_utf8(out).expect("utf-8"))
+    }
+
+    fn edit(fs: &mut dyn Vfs) {
+        fs.open_write(Path::new("/docs/a.txt"), VfsWriteMode::Append)
+            .expect("open")
+            .write_all(b"more\n")
+            .expect("write");
+        fs.remove_file(Path::new("/docs/b.txt")).expect("rm");
+        fs.create_dir(Path::new("/empty/new")).expect("mkdir");
+    }
+
+    #[test]
+    fn diff_lists_the_changes() {
+        let mut fs = test_fs();
+        assert_eq!(exec(&mut fs, "diff").expect("diff"), "");
+        edit(&mut fs);
+        assert_eq!(
+            exec(&mut fs, "diff").expect("diff"),
+            "M /docs/a.txt\nD /docs/b.txt\nA /empty/new/\n"
+        );
+    }
+
+    #[test]
+    fn commit_writes_through() {
+        let mut fs = test_fs();
+        edit(&mut fs);
+        assert_eq!(
+            exec(&mut fs, "commit").expect("commit"),
+            "3 changes committed\n"
+        );
+        assert_eq!(exec(&mut fs, "diff").expect("diff"), "");
+        let lower = fs.lower().snapshot();
+        assert_eq!(
+            lower.file(Path::new("/docs/a.txt")),
+            Some(&b"a\nmore\n"[..])
+        );
+        assert_eq!(lower.file(Path::new("/docs/b.txt")), None);
+        assert!(lower.dirs().any(|d| d == Path::new("/empty/new")));
+    }
+
+    #[test]
+    fn discard_goes_back_to_the_lower_layer() {
+        let mut fs = test_fs();
+        let before = fs.lower().snapshot();
+        edit(&mut fs);
+        assert_eq!(
+            exec(&mut fs, "discard").expect("discard"),
+            "3 changes discarded\n"
+        );
+        assert_eq!(exec(&mut fs, "diff").expect("diff"), "");
+        assert_eq!(fs.lower().snapshot(), before);
+        assert!(fs.exists(Path::new("/docs/b.txt")).expect("exists"));
+    }
+
+    #[test]
+    fn other_backends_are_unsupported() {
+        let mut fs = MemVfs::new();
+        match exec(&mut fs, "commit") {
+            Err(ShellError::Vfs(VfsError::Unsupported { op: "commit", .. })) => {}
+            other => panic!("expected unsupported, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,346 @@
+use std::io::{BufRead, IsTerminal, Read, Write};
+use std::process::ExitCode;
+
+use clap::error::ErrorKind;
+
+use super::{BasicShell, HandlerResult, ShellError, HANDLER_SUCCESS};
+
+/// Upper bound for a single input line, protects the REPL from unbounded
+/// allocations when fed garbage (e.g. a binary file on stdin).
+const MAX_LINE_LENGTH: usize = 64 * 1024;
+
+/// A source of input lines for the REPL.
+pub(super) trait LineSource {
+    /// Show `prompt` (if appropriate) and read the next line without its
+    /// line terminator. Returns `Ok(None)` at end of input.
+    fn read_line(&mut self, prompt: &str) -> std::io::Result<Option<String>>;
+}
+
+/// Reads lines from the process stdin, printing the prompt only when stdin
+/// is a terminal.
+pub(super) struct StdinLines {
+    interactive: bool,
+}
+
+impl StdinLines {
+    pub(super) fn new() -> Self {
+        Self {
+            interactive: std::io::stdin().is_terminal(),
+        }
+    }
+}
+
+impl LineSource for StdinLines {
+    fn read_line(&mut self, prompt: &str) -> std::io::Result<Option<String>> {
+        if self.interactive {
+            let mut stdout = std::io::stdout().lock();
+            write!(stdout, "{prompt}")?;
+            stdout.flush()?;
+        }
+        // Only lock stdin for the duration of a single line, handlers may
+        // want to read from it themselves.
+        let line = read_limited_line(&mut std::io::stdin().lock());
+        if self.interactive && matches!(line, Ok(None)) {
+            println!();
+        }
+        line
+    }
+}
+
+/// Reads lines from any [`BufRead`], without a prompt.
+#[cfg(test)]
+pub(super) struct ReaderLines<R>(pub(super) R);
+
+#[cfg(test)]
+impl<R: BufRead> LineSource for ReaderLines<R> {
+    fn read_line(&mut self, _prompt: &str) -> std::io::Result<Option<String>> {
+        read_limited_line(&mut self.0)
+    }
+}
+
+/// Read one line of at most [`MAX_LINE_LENGTH`] bytes.
+///
+/// Over-long lines are consumed up to their end and reported as
+/// [`std::io::ErrorKind::InvalidData`], so the caller can continue with the
+/// next line.
+fn read_limited_line(reader: &mut impl BufRead) -> std::io::Result<Option<String>> {
+    let mut buf = Vec::new();
+    let limit = u64::try_from(MAX_LINE_LENGTH).unwrap_or(u64::MAX);
+    let n = (&mut *reader).take(limit).read_until(b'\n', &mut buf)?;
+    if n == 0 {
+        return Ok(None);
+    }
+
+    if buf.last() == Some(&b'\n') {
+        buf.pop();
+        if buf.last() == Some(&b'\r') {
+            buf.pop();
+        }
+    } else if buf.len() >= MAX_LINE_LENGTH {
+        // Skip the remainder of the over-long line
+        let mut rest = Vec::new();
+        while (&mut *reader).take(limit).read_until(b'\n', &mut rest)? > 0 {
+            if rest.last() == Some(&b'\n') {
+                break;
+            }
+            rest.clear();
+        }
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("input line exceeds {MAX_LINE_LENGTH} bytes"),
+        ));
+    }
+
+    String::from_utf8(buf)
+        .map(Some)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+impl BasicShell {
+    /// Parse and execute a single line of interactive input.
+    pub(super) fn run_line(&self, line: &str) -> HandlerResult {
+        let words = crate::parse::shell_parse_line(line)?;
+        if words.is_empty() {
+            return HANDLER_SUCCESS;
+        }
+
+        let matches = match self.with_shell_cmd(|cmd| cmd.try_get_matches_from_mut(words))? {
+            Ok(m) => m,
+            Err(e) => match e.kind() {
+                ErrorKind::DisplayHelp | ErrorKind::DisplayVersion => {
+                    print!("{e}");
+                    return HANDLER_SUCCESS;
+                }
+                _ => {
+                    eprint!("{}", e.render());
+                    return Ok(ExitCode::from(2));
+                }
+            },
+        };
+
+        self.dispatch(&self.shell_group, &matches)
+    }
+
+    /// Run the read-eval-print loop until `exit` or end of input.
+    ///
+    /// Returns the status of the `exit` command, or of the last command
+    /// executed when input runs out.
+    pub(super) fn run_repl(&self, input: &mut dyn LineSource) -> HandlerResult {
+        let mut status = ExitCode::SUCCESS;
+        self.take_exit_code()?;
+
+        loop {
+            let line = match input.read_line(&self.prompt()) {
+                Ok(Some(line)) => line,
+                Ok(None) => return Ok(status),
+                Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+                    eprintln!("{}: {e}", self.name);
+                    status = ExitCode::FAILURE;
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            status = match self.run_line(&line) {
+                Ok(code) => code,
+                // die!() has already reported the error, leave the shell
+                Err(e @ ShellError::Fatal(_)) => return Err(e),
+                Err(e) => {
+                    eprintln!("{}: {e}", self.name);
+                    ExitCode::FAILURE
+                }
+            };
+
+            if let Some(code) = self.take_exit_code()? {
+                return Ok(code);
+            }
+        }
+    }
+
+    /// The prompt, with the spec of the VFS if it was opened with `open`.
+    pub(super) fn prompt(&self) -> String {
+        match self.lock_vfs() {
+            Ok(state) if !state.closed => state.spec.as_ref().map_or_else(
+                || format!("{}> ", self.name),
+                |spec| format!("{} [{spec}]> ", self.name),
+            ),
+            _ => format!("{}> ", self.name),
+        }
+    }
+
+    fn take_exit_code(&self) -> Result<Option<ExitCode>, ShellError> {
+        Ok(self
+            .exit_code
+            .lock()
+            .map_err(|e| ShellError::Internal(format!("exit mutex poisoned: {e}")))?
+            .take())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::shell::tests::build_basic_shell;
+    use crate::shell::ShellConfig;
+    use clap::Subcommand;
+    use std::io::Cursor;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn shell(name: &str) -> Arc<BasicShell> {
+        build_basic_shell(ShellConfig::new(name, "test-pkg", "0.0.1").no_init_tracing())
+    }
+
+    fn lines(input: &str) -> ReaderLines<Cursor<Vec<u8>>> {
+        ReaderLines(Cursor::new(input.as_bytes().to_vec()))
+    }
+
+    // -- read_limited_line -------------------------------------------------
+
+    #[test]
+    fn read_line_strips_terminators() {
+        let mut input = Cursor::new(b"one\ntwo\r\nthree".to_vec());
+        assert_eq!(read_limited_line(&mut input).ok(), Some(Some("one".into())));
+        assert_eq!(read_limited_line(&mut input).ok(), Some(Some("two".into())));
+        assert_eq!(
+            read_limited_line(&mut input).ok(),
+            Some(Some("three".into()))
+        );
+        assert_eq!(read_limited_line(&mut input).ok(), Some(None));
+    }
+
+    #[test]
+    fn read_line_rejects_overlong_line_and_recovers() {
+        let mut data = vec![b'x'; MAX_LINE_LENGTH * 2 + 10];
+        data.extend_from_slice(b"\nnext\n");
+        let mut input = Cursor::new(data);
+        let err = read_limited_line(&mut input).expect_err("line should be too long");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(
+            read_limited_line(&mut input).ok(),
+            Some(Some("next".into()))
+        );
+    }
+
+    #[test]
+    fn read_line_rejects_invalid_utf8() {
+        let mut input = Cursor::new(vec![0xff, 0xfe, b'\n']);
+        let err = read_limited_line(&mut input).expect_err("invalid utf-8");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    // -- run_repl ----------------------------------------------------------
+
+    #[test]
+    fn repl_runs_version_and_exit() {
+        let sh = shell("repl-version");
+        let result = sh.run_repl(&mut lines("version\nexit\n"));
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+    }
+
+    #[test]
+    fn repl_exit_with_code() {
+        let sh = shell("repl-exit-code");
+        let result = sh.run_repl(&mut lines("exit 3\nversion\n"));
+        assert_eq!(result.ok(), Some(ExitCode::from(3)));
+    }
+
+    #[test]
+    fn repl_ends_at_eof() {
+        let sh = shell("repl-eof");
+        let result = sh.run_repl(&mut lines("version\n\n   \n# just a comment\n"));
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+    }
+
+    #[test]
+    fn repl_continues_after_errors() {
+        let sh = shell("repl-errors");
+        let result = sh.run_repl(&mut lines("nosuchcmd\n'unterminated\nversion\n"));
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+    }
+
+    #[test]
+    fn repl_reports_status_of_last_command() {
+        let sh = shell("repl-status");
+        let result = sh.run_repl(&mut lines("version\nnosuchcmd\n"));
+        assert_eq!(result.ok(), Some(ExitCode::from(2)));
+    }
+
+    #[test]
+    fn repl_cli_only_commands_are_not_available() {
+        let sh = shell("repl-no-nesting");
+        let result = sh.run_line("shell");
+        assert_eq!(result.ok(), Some(ExitCode::from(2)));
+    }
+
+    #[derive(Subcommand)]
+    enum ReplCmds {
+        Bump,
+        Boom,
+    }
+
+    #[test]
+    fn repl_builds_the_command_tree_once() {
+        let builds = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&builds);
+        let sh = build_basic_shell(
+            ShellConfig::new("repl-cached", "test-pkg", "0.0.1")
+                .no_init_tracing()
+                .shell_cmds(Arc::new(move |cmd| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    cmd
+                })),
+        );
+
+        let before = builds.load(Ordering::SeqCst);
+        let result = sh.run_repl(&mut lines("version\nnope\nversion\n"));
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+        crate::Shell::complete(&*sh, "ver", 3);
+        assert_eq!(builds.load(Ordering::SeqCst), before + 1);
+
+        // Rebuilt once after invalidation, then cached again
+        crate::Shell::invalidate_commands(&*sh);
+        let result = sh.run_repl(&mut lines("version\nversion\n"));
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+        assert_eq!(builds.load(Ordering::SeqCst), before + 2);
+    }
+
+    #[test]
+    fn repl_dispatches_to_shell_handlers() {
+        static BUMPS: AtomicUsize = AtomicUsize::new(0);
+
+        let sh = build_basic_shell(
+            ShellConfig::new("repl-custom", "test-pkg", "0.0.1")
+                .no_init_tracing()
+                .shell_command(|_, cmd: ReplCmds| match cmd {
+                    ReplCmds::Bump => {
+                        BUMPS.fetch_add(1, Ordering::SeqCst);
+                        HANDLER_SUCCESS
+                    }
+                    ReplCmds::Boom => Err(ShellError::Fatal("boom".into())),
+                }),
+        );
+
+        let before = BUMPS.load(Ordering::SeqCst);
+        let result = sh.run_repl(&mut lines("bump\nbump\nboom\nbump\n"));
+        match result {
+            Err(ShellError::Fatal(msg)) => assert_eq!(msg, "boom"),
+            other => panic!("expected Fatal error, got: {other:?}"),
+        }
+        assert_eq!(BUMPS.load(Ordering::SeqCst), before + 2);
+    }
+
+    #[test]
+    fn repl_can_be_reentered_after_exit() {
+        let sh = shell("repl-reenter");
+        assert_eq!(
+            sh.run_repl(&mut lines("exit 1\n")).ok(),
+            Some(ExitCode::from(1))
+        );
+        assert_eq!(
+            sh.run_repl(&mut lines("version\n")).ok(),
+            Some(ExitCode::SUCCESS)
+        );
+    }
+}
@@ -0,0 +1,820 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::SystemTime;
+
+use clap::{Args, Subcommand, ValueHint};
+use regex::bytes::{Regex, RegexBuilder};
+
+use super::{BasicShell, HandlerResult};
+use crate::vfs::{Vfs, VfsFileType, VfsMetadata};
+
+const FIND_EXPRESSION_HELP: &str = "\
+Expression (all tests must match):
+  -name GLOB          Base name matches GLOB (*, ?, [...])
+  -iname GLOB         Like -name, ignoring case
+  -type f|d|l         Regular file, directory or symbolic link
+  -size [+|-]N[ckMG]  Size in 512-byte blocks, bytes, KiB, MiB or GiB
+  -mtime [+|-]N       Last modified N days ago
+  -maxdepth N         Descend at most N levels below the starting points
+  -mindepth N         Ignore entries less than N levels deep
+
+A leading + means more than N, a leading - less than N.";
+
+/// The find predicates, all of them take an argument.
+const FIND_PREDICATES: [&str; 7] = [
+    "-name",
+    "-iname",
+    "-type",
+    "-size",
+    "-mtime",
+    "-maxdepth",
+    "-mindepth",
+];
+
+/// Exit status of grep after an error, 1 only means nothing matched.
+const GREP_ERROR: u8 = 2;
+
+#[derive(Subcommand)]
+pub enum VfsSearchCommands {
+    /// Search for files in a directory hierarchy
+    #[command(after_help = FIND_EXPRESSION_HELP)]
+    Find {
+        /// Starting points, defaulting to the current directory, then the
+        /// expression
+        #[arg(
+            value_name = "PATH|EXPRESSION",
+            allow_hyphen_values = true,
+            trailing_var_arg = true,
+            value_hint = ValueHint::AnyPath
+        )]
+        args: Vec<String>,
+    },
+
+    /// Print lines matching a regular expression
+    Grep(GrepArgs),
+}
+
+#[derive(Args)]
+#[allow(clippy::struct_excessive_bools)] // one per grep flag
+pub struct GrepArgs {
+    /// Ignore case distinctions
+    #[arg(short = 'i', long = "ignore-case")]
+    ignore_case: bool,
+
+    /// Search directories recursively
+    #[arg(short = 'r', long = "recursive")]
+    recursive: bool,
+
+    /// Prefix each line with its line number
+    #[arg(short = 'n', long = "line-number")]
+    line_number: bool,
+
+    /// Print only the names of files with matches
+    #[arg(short = 'l', long = "files-with-matches")]
+    files_with_matches: bool,
+
+    /// Print NUM lines of trailing context
+    #[arg(short = 'A', long = "after-context", value_name = "NUM")]
+    after: Option<usize>,
+
+    /// Print NUM lines of leading context
+    #[arg(short = 'B', long = "before-context", value_name = "NUM")]
+    before: Option<usize>,
+
+    /// Print NUM lines of leading and trailing context
+    #[arg(short = 'C', long = "context", value_name = "NUM")]
+    context: Option<usize>,
+
+    /// Regular expression to search for
+    pattern: String,
+
+    /// Files to search, with -r defaults to the current directory
+    #[arg(required_unless_present = "recursive", value_hint = ValueHint::AnyPath)]
+    paths: Vec<PathBuf>,
+}
+
+pub fn handle_vfs_search_command(sh: &BasicShell, cmd: VfsSearchCommands) -> HandlerResult {
+    sh.with_vfs(|vfs| run(vfs, cmd, &mut std::io::stdout().lock()))
+}
+
+fn run(vfs: &dyn Vfs, cmd: VfsSearchCommands, out: &mut dyn Write) -> HandlerResult {
+    match cmd {
+        VfsSearchCommands::Find { args } => find(vfs, args, out),
+        VfsSearchCommands::Grep(args) => grep(vfs, &args, out),
+    }
+}
+
+/// Depth-first walk in name order that does not follow symlinks. Entries
+/// are handed out as soon as their directory is read; unreadable
+/// directories are reported and skipped.
+struct Walk<'a> {
+    vfs: &'a dyn Vfs,
+    cmd: &'static str,
+    max_depth: usize,
+    failed: bool,
+}
+
+impl<'a> Walk<'a> {
+    const fn new(vfs: &'a dyn Vfs, cmd: &'static str, max_depth: usize) -> Self {
+        Self {
+            vfs,
+            cmd,
+            max_depth,
+            failed: false,
+        }
+    }
+
+    /// Call `visit` with the path, type and depth of each entry below `dir`,
+    /// which is at `depth`.
+    fn run(
+        &mut self,
+        dir: &Path,
+        depth: usize,
+        visit: &mut dyn FnMut(&Path, VfsFileType, usize) -> std::io::Result<()>,
+    ) -> std::io::Result<()> {
+        if depth >= self.max_depth {
+            return Ok(());
+        }
+        let mut entries = match self.vfs.read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("{}: {e}", self.cmd);
+                self.failed = true;
+                return Ok(());
+            }
+        };
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        for entry in entries {
+            let path = dir.join(&entry.name);
+            visit(&path, entry.file_type, depth + 1)?;
+            if entry.file_type == VfsFileType::Dir {
+                self.run(&path, depth + 1, visit)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// -- find --------------------------------------------------------------------
+
+/// A numeric find argument: `+N`, `-N` or `N`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Cmp {
+    More(u64),
+    Less(u64),
+    Equal(u64),
+}
+
+impl Cmp {
+    fn parse(arg: &str) -> Option<Self> {
+        let make: fn(u64) -> Self = match arg.as_bytes().first() {
+            Some(b'+') => Self::More,
+            Some(b'-') => Self::Less,
+            _ => Self::Equal,
+        };
+        let digits = arg.strip_prefix(['+', '-']).unwrap_or(arg);
+        digits.parse().ok().map(make)
+    }
+
+    const fn matches(self, value: u64) -> bool {
+        match self {
+            Self::More(n) => value > n,
+            Self::Less(n) => value < n,
+            Self::Equal(n) => value == n,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Test {
+    Name {
+        glob: Vec<char>,
+        ignore_case: bool,
+    },
+    Type(VfsFileType),
+    /// Size in units of the given number of bytes, rounded up
+    Size(Cmp, u64),
+    /// Age in whole days
+    Mtime(Cmp),
+}
+
+#[derive(Debug)]
+struct FindExpr {
+    paths: Vec<PathBuf>,
+    tests: Vec<Test>,
+    min_depth: usize,
+    max_depth: usize,
+}
+
+impl FindExpr {
+    fn parse(args: Vec<String>) -> Result<Self, String> {
+        let mut args = args.into_iter().peekable();
+        let mut paths = Vec::new();
+        while let Some(path) = args.next_if(|arg| !arg.starts_with('-')) {
+            paths.push(PathBuf::from(path));
+        }
+        if paths.is_empty() {
+            paths.push(PathBuf::from("."));
+        }
+
+        let mut expr = Self {
+            paths,
+            tests: Vec::new(),
+            min_depth: 0,
+            max_depth: usize::MAX,
+        };
+        while let Some(arg) = args.next() {
+            if !FIND_PREDICATES.contains(&arg.as_str()) {
+                return Err(format!("unknown predicate '{arg}'"));
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing argument to '{arg}'"))?;
+            let invalid = || format!("invalid argument '{value}' to '{arg}'");
+            match arg.as_str() {
+                "-name" | "-iname" => {
+                    let ignore_case = arg == "-iname";
+                    let glob = if ignore_case {
+                        value.to_lowercase()
+                    } else {
+                        value.clone()
+                    };
+                    expr.tests.push(Test::Name {
+                        glob: glob.chars().collect(),
+                        ignore_case,
+                    });
+                }
+                "-type" => expr.tests.push(Test::Type(match value.as_str() {
+                    "f" => VfsFileType::File,
+                    "d" => VfsFileType::Dir,
+                    "l" => VfsFileType::Symlink,
+                    _ => return Err(invalid()),
+                })),
+                "-size" => {
+                    let (number, unit) = match value.char_indices().last() {
+                        Some((i, 'c')) => (value.get(..i), 1),
+                        Some((i, 'k')) => (value.get(..i), 1 << 10),
+                        Some((i, 'M')) => (value.get(..i), 1 << 20),
+                        Some((i, 'G')) => (value.get(..i), 1 << 30),
+                        _ => (Some(value.as_str()), 512),
+                    };
+                    let cmp = number.and_then(Cmp::parse).ok_or_else(invalid)?;
+                    expr.tests.push(Test::Size(cmp, unit));
+                }
+                "-mtime" => {
+                    let cmp = Cmp::parse(&value).ok_or_else(invalid)?;
+                    expr.tests.push(Test::Mtime(cmp));
+                }
+                "-maxdepth" => expr.max_depth = value.parse().map_err(|_| invalid())?,
+                "-mindepth" => expr.min_depth = value.parse().map_err(|_| invalid())?,
+                _ => return Err(format!("unknown predicate '{arg}'")),
+            }
+        }
+        Ok(expr)
+    }
+
+    fn matches(
+        &self,
+        vfs: &dyn Vfs,
+        path: &Path,
+        file_type: VfsFileType,
+        depth: usize,
+        now: SystemTime,
+    ) -> bool {
+        if depth < self.min_depth {
+            return false;
+        }
+        // Only fetched when a test needs it, and then only once
+        let mut meta: Option<Option<VfsMetadata>> = None;
+        let mut meta = || meta.get_or_insert_with(|| vfs.metadata(path).ok()).clone();
+        self.tests.iter().all(|test| match test {
+            Test::Name { glob, ignore_case } => {
+                let name = file_name(path);
+                let name = if *ignore_case {
+                    name.to_lowercase()
+                } else {
+                    name
+                };
+                glob_match(glob, &name.chars().collect::<Vec<_>>())
+            }
+            Test::Type(wanted) => file_type == *wanted,
+            Test::Size(cmp, unit) => {
+                meta().is_some_and(|meta| cmp.matches(meta.len.div_ceil(*unit)))
+            }
+            Test::Mtime(cmp) => meta()
+                .and_then(|meta| meta.modified)
+                .is_some_and(|modified| {
+                    let age = now.duration_since(modified).unwrap_or_default();
+                    cmp.matches(age.as_secs() / 86_400)
+                }),
+        })
+    }
+}
+
+/// The name find matches `-name` against: the last component, or the whole
+/// path for starting points like `.` and `/`.
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn find(vfs: &dyn Vfs, args: Vec<String>, out: &mut dyn Write) -> HandlerResult {
+    let expr = match FindExpr::parse(args) {
+        Ok(expr) => expr,
+        Err(e) => {
+            eprintln!("find: {e}");
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+    let now = SystemTime::now();
+    let mut failed = false;
+    for start in &expr.paths {
+        let meta = match vfs.metadata(start) {
+            Ok(meta) => meta,
+            Err(e) => {
+                eprintln!("find: {e}");
+                failed = true;
+                continue;
+            }
+        };
+        if expr.matches(vfs, start, meta.file_type, 0, now) {
+            writeln!(out, "{}", start.display())?;
+        }
+        if meta.is_dir() {
+            let mut walk = Walk::new(vfs, "find", expr.max_depth);
+            walk.run(start, 0, &mut |path, file_type, depth| {
+                if expr.matches(vfs, path, file_type, depth, now) {
+                    writeln!(out, "{}", path.display())?;
+                }
+                Ok(())
+            })?;
+            failed |= walk.failed;
+        }
+    }
+    Ok(if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Match `name` against a shell glob with `*`, `?`, `[abc]`, `[a-z]`,
+/// `[!abc]` and `\` escapes.
+fn glob_match(glob: &[char], name: &[char]) -> bool {
+    let (mut g, mut n) = (0, 0);
+    // Where to resume after the last `*` if the rest does not match
+    let mut star: Option<(usize, usize)> = None;
+    while let Some(&c) = name.get(n) {
+        let rest = glob.get(g..).unwrap_or_default();
+        if rest.first() == Some(&'*') {
+            star = Some((g, n));
+            g += 1;
+            continue;
+        }
+        if let Some(len) = match_one(rest, c) {
+            g += len;
+            n += 1;
+        } else if let Some((star_g, star_n)) = star {
+            // Let the `*` swallow one more character
+            star = Some((star_g, star_n + 1));
+            g = star_g + 1;
+            n = star_n + 1;
+        } else {
+            return false;
+        }
+    }
+    glob.get(g..).unwrap_or_default().iter().all(|&c| c == '*')
+}
+
+/// Match `c` against the start of `glob`, returning how much of the glob
+/// was used.
+fn match_one(glob: &[char], c: char) -> Option<usize> {
+    match glob {
+        ['?', ..] => Some(1),
+        ['\\', escaped, ..] => (*escaped == c).then_some(2),
+        ['[', class @ ..] => match match_class(class, c) {
+            Some((matched, len)) => matched.then_some(len + 1),
+            // An unclosed `[` is an ordinary character
+            None => (c == '[').then_some(1),
+        },
+        [literal, ..] => (*literal == c).then_some(1),
+        [] => None,
+    }
+}
+
+/// Match `c` against the bracket expression `class` that follows a `[`.
+/// Returns whether it matched and the length of the class up to and
+/// including the `]`, or `None` if the class is not closed.
+fn match_class(class: &[char], c: char) -> Option<(bool, usize)> {
+    let (negated, mut rest) = match class {
+        ['!' | '^', rest @ ..] => (true, rest),
+        _ => (false, class),
+    };
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        match rest {
+            // A `]` right at the start is part of the class
+            [']', ..] if !first => break,
+            [low, '-', high, tail @ ..] if *high != ']' => {
+                matched |= (*low..=*high).contains(&c);
+                rest = tail;
+            }
+            [literal, tail @ ..] => {
+                matched |= *literal == c;
+                rest = tail;
+            }
+            [] => return None,
+        }
+        first = false;
+    }
+    Some((matched != negated, class.len() - rest.len() + 1))
+}
+
+// -- grep --------------------------------------------------------------------
+
+fn grep(vfs: &dyn Vfs, args: &GrepArgs, out: &mut dyn Write) -> HandlerResult {
+    let regex = match RegexBuilder::new(&args.pattern)
+        .case_insensitive(args.ignore_case)
+        .build()
+    {
+        Ok(regex) => regex,
+        Err(e) => {
+            eprintln!("grep: {e}");
+            return Ok(ExitCode::from(GREP_ERROR));
+        }
+    };
+    let paths = if args.paths.is_empty() {
+        // Only with -r, names are then printed relative to the cwd
+        vec![PathBuf::new()]
+    } else {
+        args.paths.clone()
+    };
+    let mut grep = Grep {
+        regex,
+        args,
+        with_names: args.recursive || paths.len() > 1,
+        matched: false,
+        failed: false,
+        printed: false,
+    };
+
+    for path in &paths {
+        let meta = match vfs.metadata(path) {
+            Ok(meta) => meta,
+            Err(e) => {
+                grep.fail(e);
+                continue;
+            }
+        };
+        if !meta.is_dir() {
+            grep.file(vfs, path, out)?;
+        } else if args.recursive {
+            let mut walk = Walk::new(vfs, "grep", usize::MAX);
+            walk.run(path, 0, &mut |path, file_type, _| {
+                if file_type == VfsFileType::File {
+                    grep.file(vfs, path, out)?;
+                }
+                Ok(())
+            })?;
+            grep.failed |= walk.failed;
+        } else {
+            grep.fail(format_args!("{}: is a directory", path.display()));
+        }
+    }
+
+    Ok(if grep.failed {
+        ExitCode::from(GREP_ERROR)
+    } else if grep.matched {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+#[allow(clippy::struct_excessive_bools)] // independent bits of search state
+struct Grep<'a> {
+    regex: Regex,
+    args: &'a GrepArgs,
+    with_names: bool,
+    matched: bool,
+    failed: bool,
+    /// Whether a group of lines was printed, to separate the next one
+    printed: bool,
+}
+
+impl Grep<'_> {
+    fn fail(&mut self, e: impl std::fmt::Display) {
+        eprintln!("grep: {e}");
+        self.failed = true;
+    }
+
+    fn file(&mut self, vfs: &dyn Vfs, path: &Path, out: &mut dyn Write) -> std::io::Result<()> {
+        match vfs.open_read(path) {
+            Ok(reader) => self.lines(path, &mut BufReader::new(reader), out),
+            Err(e) => {
+                self.fail(e);
+                Ok(())
+            }
+        }
+    }
+
+    /// Search `reader` line by line, keeping only the leading context.
+    fn lines(
+        &mut self,
+        path: &Path,
+        reader: &mut dyn BufRead,
+        out: &mut dyn Write,
+    ) -> std::io::Result<()> {
+        let after = self.args.after.or(self.args.context).unwrap_or(0);
+        let before = self.args.before.or(self.args.context).unwrap_or(0);
+        let mut leading: VecDeque<(usize, Vec<u8>)> = VecDeque::with_capacity(before.min(1024));
+        let mut trailing = 0;
+        let mut last_printed = None;
+        let mut binary = false;
+        let mut number = 0;
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                return Ok(());
+            }
+            number += 1;
+            binary |= line.contains(&0);
+
+            let text = line.strip_suffix(b"\n").unwrap_or(&line);
+            if self.regex.is_match(text) {
+                self.matched = true;
+                if self.args.files_with_matches {
+                    return writeln!(out, "{}", path.display());
+                }
+                if binary {
+                    return writeln!(out, "Binary file {} matches", path.display());
+                }
+                let first = leading.front().map_or(number, |(n, _)| *n);
+                let adjacent = last_printed.is_some_and(|last: usize| last + 1 >= first);
+                if (before > 0 || after > 0) && self.printed && !adjacent {
+                    writeln!(out, "--")?;
+                }
+                while let Some((n, context)) = leading.pop_front() {
+                    self.write_line(out, path, n, b'-', &context)?;
+                }
+                self.write_line(out, path, number, b':', &line)?;
+                last_printed = Some(number);
+                trailing = after;
+                self.printed = true;
+            } else if trailing > 0 {
+                self.write_line(out, path, number, b'-', &line)?;
+                last_printed = Some(number);
+                trailing -= 1;
+            } else if before > 0 {
+                // Recycle the buffer of the line that drops out
+                let spare = if leading.len() == before {
+                    leading.pop_front().map(|(_, buf)| buf).unwrap_or_default()
+                } else {
+                    Vec::new()
+                };
+                leading.push_back((number, std::mem::replace(&mut line, spare)));
+            }
+        }
+    }
+
+    /// Write a line with its prefix; `sep` is `:` for matches and `-` for
+    /// context.
+    fn write_line(
+        &self,
+        out: &mut dyn Write,
+        path: &Path,
+        number: usize,
+        sep: u8,
+        line: &[u8],
+    ) -> std::io::Result<()> {
+        if self.with_names {
+            write!(out, "{}", path.display())?;
+            out.write_all(&[sep])?;
+        }
+        if self.args.line_number {
+            write!(out, "{number}")?;
+            out.write_all(&[sep])?;
+        }
+        out.write_all(line)?;
+        if !line.ends_with(b"\n") {
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::shell::tests::{parse_cmd, try_parse_cmd};
+    use crate::vfs::MemVfs;
+    use std::time::Duration;
+
+    fn test_fs() -> MemVfs {
+        let mut fs = MemVfs::new()
+            .with_file("/logs/app.log", "start\nERROR disk full\nretry\nok\n")
+            .with_file("/logs/old.LOG", "error: gone\n")
+            .with_file("/logs/archive/app.1.log", vec![b'x'; 2000])
+            .with_file("/readme.md", "# Error handling\nsee logs\n")
+            .with_file("/blob.bin", b"error\0binary\n".to_vec());
+        let now = SystemTime::now();
+        for (path, days) in [("/logs/old.LOG", 10), ("/logs/archive/app.1.log", 40)] {
+            let modified = now - Duration::from_secs(days * 86_400);
+            fs.set_modified(Path::new(path), modified).expect("mtime");
+        }
+        fs
+    }
+
+    fn exec(fs: &MemVfs, line: &str) -> (ExitCode, String) {
+        let mut out = Vec::new();
+        let status = run(fs, parse_cmd(line), &mut out).expect("run");
+        (status, String::from_utf8(out).expect("utf-8"))
+    }
+
+    fn output(fs: &MemVfs, line: &str) -> String {
+        let (status, out) = exec(fs, line);
+        assert_eq!(status, ExitCode::SUCCESS, "{line} failed");
+        out
+    }
+
+    // -- glob --------------------------------------------------------------
+
+    #[test]
+    fn glob_matching() {
+        let glob = |glob: &str, name: &str| {
+            glob_match(
+                &glob.chars().collect::<Vec<_>>(),
+                &name.chars().collect::<Vec<_>>(),
+            )
+        };
+        assert!(glob("*.log", "app.log"));
+        assert!(glob("*.log", ".log"));
+        assert!(!glob("*.log", "app.log.1"));
+        assert!(glob("a*b*c", "aXbYbZc"));
+        assert!(glob("?pp.*", "app.log"));
+        assert!(!glob("?", ""));
+        assert!(glob("[ab]*", "bob"));
+        assert!(glob("[a-c]x", "bx"));
+        assert!(!glob("[!a-c]x", "bx"));
+        assert!(glob("[]]", "]"));
+        assert!(glob("[", "["));
+        assert!(glob("\\*", "*"));
+        assert!(!glob("\\*", "x"));
+        assert!(glob("*", ""));
+        assert!(glob("ü*", "über"));
+    }
+
+    // -- find --------------------------------------------------------------
+
+    #[test]
+    fn find_lists_everything_below_the_start() {
+        let fs = test_fs();
+        assert_eq!(
+            output(&fs, "find logs"),
+            "logs\nlogs/app.log\nlogs/archive\nlogs/archive/app.1.log\nlogs/old.LOG\n"
+        );
+        assert_eq!(output(&fs, "find").lines().next(), Some("."));
+        assert!(output(&fs, "find").contains("./logs/app.log\n"));
+    }
+
+    #[test]
+    fn find_by_name_and_type() {
+        let fs = test_fs();
+        assert_eq!(
+            output(&fs, "find / -name '*.log'"),
+            "/logs/app.log\n/logs/archive/app.1.log\n"
+        );
+        assert_eq!(
+            output(&fs, "find / -iname '*.log' -type f -maxdepth 2"),
+            "/logs/app.log\n/logs/old.LOG\n"
+        );
+        assert_eq!(output(&fs, "find / -type d"), "/\n/logs\n/logs/archive\n");
+    }
+
+    #[test]
+    fn find_by_depth() {
+        let fs = test_fs();
+        assert_eq!(output(&fs, "find logs -maxdepth 0"), "logs\n");
+        assert_eq!(
+            output(&fs, "find logs -mindepth 2"),
+            "logs/archive/app.1.log\n"
+        );
+    }
+
+    #[test]
+    fn find_by_size_and_mtime() {
+        let fs = test_fs();
+        assert_eq!(output(&fs, "find / -size +1k"), "/logs/archive/app.1.log\n");
+        assert_eq!(output(&fs, "find / -size 12c"), "/logs/old.LOG\n");
+        assert_eq!(
+            output(&fs, "find / -type f -mtime +5"),
+            "/logs/archive/app.1.log\n/logs/old.LOG\n"
+        );
+        assert_eq!(output(&fs, "find logs -type f -mtime -1"), "logs/app.log\n");
+    }
+
+    #[test]
+    fn find_rejects_bad_expressions() {
+        let fs = test_fs();
+        for line in [
+            "find -bogus",
+            "find -name",
+            "find -type x",
+            "find -size lots",
+            "find -maxdepth -1",
+        ] {
+            let (status, out) = exec(&fs, line);
+            assert_eq!(status, ExitCode::FAILURE, "{line}");
+            assert!(out.is_empty(), "{line}");
+        }
+    }
+
+    #[test]
+    fn find_reports_missing_starts_and_continues() {
+        let fs = test_fs();
+        let (status, out) = exec(&fs, "find nope logs/archive");
+        assert_eq!(status, ExitCode::FAILURE);
+        assert_eq!(out, "logs/archive\nlogs/archive/app.1.log\n");
+    }
+
+    // -- grep --------------------------------------------------------------
+
+    #[test]
+    fn grep_prints_matching_lines() {
+        let fs = test_fs();
+        assert_eq!(output(&fs, "grep ERROR logs/app.log"), "ERROR disk full\n");
+        assert_eq!(
+            output(&fs, "grep -n -i 'error|see' logs/app.log readme.md"),
+            "logs/app.log:2:ERROR disk full\nreadme.md:1:# Error handling\nreadme.md:2:see logs\n"
+        );
+    }
+
+    #[test]
+    fn grep_exit_status() {
+        let fs = test_fs();
+        assert_eq!(exec(&fs, "grep nothing readme.md").0, ExitCode::FAILURE);
+        assert_eq!(exec(&fs, "grep e nope readme.md").0, ExitCode::from(2));
+        assert_eq!(exec(&fs, "grep '(' readme.md").0, ExitCode::from(2));
+        assert_eq!(exec(&fs, "grep e logs").0, ExitCode::from(2));
+    }
+
+    #[test]
+    fn grep_recursive() {
+        let fs = test_fs();
+        assert_eq!(
+            output(&fs, "grep -ri error logs"),
+            "logs/app.log:ERROR disk full\nlogs/old.LOG:error: gone\n"
+        );
+        let mut fs = test_fs();
+        fs.cd(Path::new("/logs")).expect("cd");
+        assert_eq!(output(&fs, "grep -r retry"), "app.log:retry\n");
+    }
+
+    #[test]
+    fn grep_files_with_matches() {
+        let fs = test_fs();
+        assert_eq!(
+            output(&fs, "grep -rli error /"),
+            "/blob.bin\n/logs/app.log\n/logs/old.LOG\n/readme.md\n"
+        );
+    }
+
+    #[test]
+    fn grep_context_lines() {
+        let fs = MemVfs::new().with_file("/f", "a\nb\nX\nc\nd\ne\nf\nX\ng\n");
+        assert_eq!(output(&fs, "grep -A 1 X f"), "X\nc\n--\nX\ng\n");
+        assert_eq!(output(&fs, "grep -n -B 1 X f"), "2-b\n3:X\n--\n7-f\n8:X\n");
+        assert_eq!(output(&fs, "grep -C 2 X f"), "a\nb\nX\nc\nd\ne\nf\nX\ng\n");
+        assert_eq!(output(&fs, "grep -C 1 -A 0 X f"), "b\nX\n--\nf\nX\n");
+        // Huge counts only keep what the file has
+        assert_eq!(
+            output(&fs, "grep -C 99999999999999 g f"),
+            "a\nb\nX\nc\nd\ne\nf\nX\ng\n"
+        );
+        assert_eq!(
+            output(&fs, &format!("grep -B {} b f", usize::MAX)),
+            "a\nb\n"
+        );
+    }
+
+    #[test]
+    fn grep_binary_files() {
+        let fs = test_fs();
+        assert_eq!(
+            output(&fs, "grep error blob.bin"),
+            "Binary file blob.bin matches\n"
+        );
+    }
+
+    #[test]
+    fn grep_needs_paths_unless_recursive() {
+        assert!(try_parse_cmd::<VfsSearchCommands>("grep x").is_err());
+    }
+}
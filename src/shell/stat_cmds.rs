@@ -0,0 +1,298 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::{ArgAction, Args, Subcommand, ValueHint};
+
+use super::vfs_cmds::{civil_from_days, format_mode, format_size, mode_bits};
+use super::{BasicShell, HandlerResult};
+use crate::vfs::{Vfs, VfsFileType, VfsMetadata};
+
+#[derive(Subcommand)]
+pub enum VfsStatCommands {
+    /// Print the sizes of directories and files
+    // -h means human-readable sizes, like in ls
+    #[command(disable_help_flag = true)]
+    Du(DuArgs),
+
+    /// Print the metadata of files and directories
+    Stat {
+        /// Files or directories to describe
+        #[arg(required = true, value_hint = ValueHint::AnyPath)]
+        paths: Vec<PathBuf>,
+    },
+}
+
+#[derive(Args)]
+#[allow(clippy::struct_excessive_bools)] // one per du flag
+pub struct DuArgs {
+    /// Print only a total for each argument
+    #[arg(short = 's', long = "summarize", conflicts_with = "depth")]
+    summarize: bool,
+
+    /// List files too, not only directories
+    #[arg(short = 'a', long = "all")]
+    all: bool,
+
+    /// List directories at most DEPTH levels below the arguments
+    #[arg(short = 'd', long = "max-depth", value_name = "DEPTH")]
+    depth: Option<usize>,
+
+    /// Print sizes like 1K 234M 2G
+    #[arg(short = 'h', long = "human-readable")]
+    human: bool,
+
+    /// Print a grand total at the end
+    #[arg(short = 'c', long = "total")]
+    total: bool,
+
+    /// Print help
+    #[arg(long, action = ArgAction::Help)]
+    help: Option<bool>,
+
+    /// Files or directories to measure, defaults to the current directory
+    #[arg(value_hint = ValueHint::AnyPath)]
+    paths: Vec<PathBuf>,
+}
+
+pub fn handle_vfs_stat_command(sh: &BasicShell, cmd: VfsStatCommands) -> HandlerResult {
+    sh.with_vfs(move |vfs| run(vfs, &cmd, &mut std::io::stdout().lock()))
+}
+
+fn run(vfs: &dyn Vfs, cmd: &VfsStatCommands, out: &mut dyn Write) -> HandlerResult {
+    match cmd {
+        VfsStatCommands::Du(args) => du(vfs, args, out),
+        VfsStatCommands::Stat { paths } => {
+            let mut status = ExitCode::SUCCESS;
+            for path in paths {
+                match vfs.metadata(&vfs.resolve(path)) {
+                    Ok(meta) => stat(out, path, &meta)?,
+                    Err(e) => {
+                        eprintln!("stat: {e}");
+                        status = ExitCode::FAILURE;
+                    }
+                }
+            }
+            Ok(status)
+        }
+    }
+}
+
+// -- du ----------------------------------------------------------------------
+
+/// Sizes are the lengths of the files, as no backend knows about blocks;
+/// the lengths that directories report themselves are left out.
+fn du(vfs: &dyn Vfs, args: &DuArgs, out: &mut dyn Write) -> HandlerResult {
+    let paths = if args.paths.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        args.paths.clone()
+    };
+    let mut walk = DuWalk {
+        vfs,
+        args,
+        depth: if args.summarize { Some(0) } else { args.depth },
+        out,
+        status: ExitCode::SUCCESS,
+    };
+    let mut total = 0;
+    for path in &paths {
+        let resolved = vfs.resolve(path);
+        match vfs.metadata(&resolved) {
+            Ok(meta) => total += walk.usage(path, &resolved, &meta, 0)?,
+            Err(e) => walk.fail(e),
+        }
+    }
+    if args.total {
+        walk.print(total, Path::new("total"))?;
+    }
+    Ok(walk.status)
+}
+
+struct DuWalk<'a> {
+    vfs: &'a dyn Vfs,
+    args: &'a DuArgs,
+    /// How deep below the arguments entries are printed
+    depth: Option<usize>,
+    out: &'a mut dyn Write,
+    status: ExitCode,
+}
+
+impl DuWalk<'_> {
+    fn fail(&mut self, e: impl std::fmt::Display) {
+        eprintln!("du: {e}");
+        self.status = ExitCode::FAILURE;
+    }
+
+    fn print(&mut self, size: u64, path: &Path) -> std::io::Result<()> {
+        writeln!(
+            self.out,
+            "{}\t{}",
+            format_size(size, self.args.human),
+            path.display()
+        )
+    }
+
+    /// The size of `resolved` and everything below it, shown as `path`,
+    /// `depth` levels below an argument. Directories are printed after
+    /// their entries.
+    fn usage(
+        &mut self,
+        path: &Path,
+        resolved: &Path,
+        meta: &VfsMetadata,
+        depth: usize,
+    ) -> std::io::Result<u64> {
+        let shown = self.depth.is_none_or(|max| depth <= max);
+        if !meta.is_dir() {
+            if shown && (self.args.all || depth == 0) {
+                self.print(meta.len, path)?;
+            }
+            return Ok(meta.len);
+        }
+
+        let mut size = 0;
+        match self.vfs.read_dir(resolved) {
+            Ok(mut entries) => {
+                entries.sort_by(|a, b| a.name.cmp(&b.name));
+                for entry in entries {
+                    let child = resolved.join(&entry.name);
+                    match self.vfs.metadata(&child) {
+                        // Symlinks to directories are not followed
+                        Ok(meta) if meta.is_dir() && entry.file_type != VfsFileType::Dir => {}
+                        Ok(meta) => {
+                            size +=
+                                self.usage(&path.join(&entry.name), &child, &meta, depth + 1)?;
+                        }
+                        Err(e) => self.fail(e),
+                    }
+                }
+            }
+            Err(e) => self.fail(e),
+        }
+        if shown {
+            self.print(size, path)?;
+        }
+        Ok(size)
+    }
+}
+
+// -- stat --------------------------------------------------------------------
+
+fn stat(out: &mut dyn Write, path: &Path, meta: &VfsMetadata) -> std::io::Result<()> {
+    let file_type = match meta.file_type {
+        VfsFileType::File => "regular file",
+        VfsFileType::Dir => "directory",
+        VfsFileType::Symlink => "symbolic link",
+        VfsFileType::Other => "other",
+    };
+    let readonly = if meta.readonly { "yes" } else { "no" };
+    writeln!(out, "  File: {}", path.display())?;
+    writeln!(out, "  Size: {:<15} Type: {file_type}", meta.len)?;
+    writeln!(
+        out,
+        "Access: ({:04o}/{})  Read-only: {readonly}",
+        mode_bits(meta),
+        format_mode(meta.file_type, Some(meta))
+    )?;
+    writeln!(out, "Modify: {}", format_timestamp(meta.modified))
+}
+
+/// Format a timestamp as `YYYY-MM-DD HH:MM:SS.NNNNNNNNN +0000`, or `-` if
+/// the backend does not know it.
+fn format_timestamp(time: Option<SystemTime>) -> String {
+    let Some(since_epoch) = time.and_then(|t| t.duration_since(UNIX_EPOCH).ok()) else {
+        return "-".into();
+    };
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days(secs / 86_400);
+    let secs = secs % 86_400;
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}.{:09} +0000",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+        since_epoch.subsec_nanos()
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::shell::tests::parse_cmd;
+    use crate::vfs::MemVfs;
+    use std::time::Duration;
+
+    fn test_fs() -> MemVfs {
+        MemVfs::new()
+            .with_file("/data/a.csv", vec![b'x'; 1000])
+            .with_file("/data/sub/b.csv", vec![b'y'; 2000])
+            .with_file("/data/sub/deeper/c.csv", vec![b'z'; 3000])
+            .with_file("/top.txt", "hello")
+    }
+
+    fn exec(fs: &dyn Vfs, line: &str) -> (HandlerResult, String) {
+        let mut out = Vec::new();
+        let result = run(fs, &parse_cmd(line), &mut out);
+        (result, String::from_utf8(out).expect("utf-8"))
+    }
+
+    fn output(fs: &dyn Vfs, line: &str) -> String {
+        let (result, out) = exec(fs, line);
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS), "{line} failed");
+        out
+    }
+
+    #[test]
+    fn du_lists_directories_after_their_entries() {
+        let mut fs = test_fs();
+        fs.cd(Path::new("/data")).expect("cd");
+        assert_eq!(
+            output(&fs, "du"),
+            "3000\t./sub/deeper\n5000\t./sub\n6000\t.\n"
+        );
+        assert_eq!(
+            output(&fs, "du -a sub"),
+            "2000\tsub/b.csv\n3000\tsub/deeper/c.csv\n3000\tsub/deeper\n5000\tsub\n"
+        );
+    }
+
+    #[test]
+    fn du_depth_summary_and_total() {
+        let fs = test_fs();
+        assert_eq!(
+            output(&fs, "du -d 1 /data"),
+            "5000\t/data/sub\n6000\t/data\n"
+        );
+        assert_eq!(output(&fs, "du -sh /data"), "5.9K\t/data\n");
+        assert_eq!(
+            output(&fs, "du -sc /data /top.txt"),
+            "6000\t/data\n5\t/top.txt\n6005\ttotal\n"
+        );
+
+        let (result, out) = exec(&fs, "du -s /data /missing");
+        assert_eq!(result.ok(), Some(ExitCode::FAILURE));
+        assert_eq!(out, "6000\t/data\n");
+    }
+
+    #[test]
+    fn stat_shows_the_metadata() {
+        let mut fs = test_fs();
+        let modified = UNIX_EPOCH + Duration::new(1_700_000_000, 5);
+        fs.set_modified(Path::new("/top.txt"), modified)
+            .expect("touch");
+        assert_eq!(
+            output(&fs, "stat top.txt"),
+            "  File: top.txt\n  Size: 5               Type: regular file\n\
+             Access: (0644/-rw-r--r--)  Read-only: no\n\
+             Modify: 2023-11-14 22:13:20.000000005 +0000\n"
+        );
+
+        let (result, out) = exec(&fs, "stat /nope /data");
+        assert_eq!(result.ok(), Some(ExitCode::FAILURE));
+        assert!(out.starts_with("  File: /data\n"));
+        assert!(out.contains("Type: directory"));
+    }
+}
@@ -0,0 +1,296 @@
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Args, Subcommand, ValueHint};
+use sha2::Digest;
+
+use super::{BasicShell, HandlerResult, ShellError};
+use crate::vfs::Vfs;
+
+#[derive(Subcommand)]
+pub enum VfsSumCommands {
+    /// Print or check SHA-256 checksums
+    Sha256sum(SumArgs),
+
+    /// Print or check SHA-1 checksums
+    Sha1sum(SumArgs),
+
+    /// Print or check MD5 checksums
+    Md5sum(SumArgs),
+}
+
+#[derive(Args)]
+pub struct SumArgs {
+    /// Read checksums from the files and check them
+    #[arg(short = 'c', long = "check")]
+    check: bool,
+
+    /// Files to checksum, or with -c files of checksums
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    paths: Vec<PathBuf>,
+}
+
+/// The hash function of a checksum command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+    Sha256,
+    Sha1,
+    Md5,
+}
+
+impl Algorithm {
+    const fn cmd(self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256sum",
+            Self::Sha1 => "sha1sum",
+            Self::Md5 => "md5sum",
+        }
+    }
+
+    /// The length of a checksum in hex digits.
+    const fn hex_len(self) -> usize {
+        match self {
+            Self::Sha256 => 64,
+            Self::Sha1 => 40,
+            Self::Md5 => 32,
+        }
+    }
+
+    /// The checksum of everything `reader` returns, in lowercase hex.
+    fn digest(self, reader: &mut dyn Read) -> std::io::Result<String> {
+        match self {
+            Self::Sha256 => hex_digest::<sha2::Sha256>(reader),
+            Self::Sha1 => hex_digest::<sha1::Sha1>(reader),
+            Self::Md5 => hex_digest::<md5::Md5>(reader),
+        }
+    }
+}
+
+fn hex_digest<D: Digest + Write>(reader: &mut dyn Read) -> std::io::Result<String> {
+    let mut hasher = D::new();
+    std::io::copy(reader, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        }))
+}
+
+pub fn handle_vfs_sum_command(sh: &BasicShell, cmd: VfsSumCommands) -> HandlerResult {
+    sh.with_vfs(move |vfs| run(vfs, &cmd, &mut std::io::stdout().lock()))
+}
+
+fn run(vfs: &dyn Vfs, cmd: &VfsSumCommands, out: &mut dyn Write) -> HandlerResult {
+    let (algorithm, args) = match cmd {
+        VfsSumCommands::Sha256sum(args) => (Algorithm::Sha256, args),
+        VfsSumCommands::Sha1sum(args) => (Algorithm::Sha1, args),
+        VfsSumCommands::Md5sum(args) => (Algorithm::Md5, args),
+    };
+    if args.check {
+        return check(vfs, algorithm, &args.paths, out);
+    }
+    let mut status = ExitCode::SUCCESS;
+    for path in &args.paths {
+        match checksum(vfs, algorithm, path) {
+            Ok(sum) => writeln!(out, "{sum}  {}", path.display())?,
+            Err(e) => {
+                eprintln!("{}: {e}", algorithm.cmd());
+                status = ExitCode::FAILURE;
+            }
+        }
+    }
+    Ok(status)
+}
+
+fn checksum(vfs: &dyn Vfs, algorithm: Algorithm, path: &Path) -> Result<String, ShellError> {
+    let mut reader = vfs.open_read(path)?;
+    algorithm
+        .digest(&mut reader)
+        .map_err(|e| crate::VfsError::from_io(vfs.resolve(path), e).into())
+}
+
+/// Split a line of a checksum file into the checksum and the path, in the
+/// format written above; a `*` before the path (binary mode) is accepted.
+fn parse_line(line: &str, algorithm: Algorithm) -> Option<(&str, &str)> {
+    let (sum, rest) = line.split_once(' ')?;
+    let path = rest.strip_prefix([' ', '*'])?;
+    let valid = sum.len() == algorithm.hex_len() && sum.bytes().all(|b| b.is_ascii_hexdigit());
+    (valid && !path.is_empty()).then_some((sum, path))
+}
+
+/// Check the checksums listed in `sum_files`, printing `OK` or `FAILED` per
+/// listed file. Fails if any does not match or cannot be read.
+fn check(
+    vfs: &dyn Vfs,
+    algorithm: Algorithm,
+    sum_files: &[PathBuf],
+    out: &mut dyn Write,
+) -> HandlerResult {
+    let cmd = algorithm.cmd();
+    let (mut checked, mut mismatched, mut unreadable, mut malformed) = (0, 0, 0, 0);
+    let mut status = ExitCode::SUCCESS;
+    for sum_file in sum_files {
+        let reader = match vfs.open_read(sum_file) {
+            Ok(reader) => BufReader::new(reader),
+            Err(e) => {
+                eprintln!("{cmd}: {e}");
+                status = ExitCode::FAILURE;
+                continue;
+            }
+        };
+        for line in reader.lines() {
+            let line = line.map_err(|e| crate::VfsError::from_io(vfs.resolve(sum_file), e))?;
+            let line = line.trim_end_matches('\r');
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((expected, path)) = parse_line(line, algorithm) else {
+                malformed += 1;
+                continue;
+            };
+            checked += 1;
+            match checksum(vfs, algorithm, Path::new(path)) {
+                Ok(sum) if sum.eq_ignore_ascii_case(expected) => writeln!(out, "{path}: OK")?,
+                Ok(_) => {
+                    writeln!(out, "{path}: FAILED")?;
+                    mismatched += 1;
+                }
+                Err(e) => {
+                    eprintln!("{cmd}: {e}");
+                    writeln!(out, "{path}: FAILED open or read")?;
+                    unreadable += 1;
+                }
+            }
+        }
+    }
+
+    if checked == 0 && status == ExitCode::SUCCESS {
+        eprintln!("{cmd}: no properly formatted checksum lines found");
+        return Ok(ExitCode::FAILURE);
+    }
+    if malformed > 0 {
+        let lines = crate::pluralize!("line", malformed);
+        eprintln!("{cmd}: WARNING: {malformed} {lines} improperly formatted");
+    }
+    if unreadable > 0 {
+        let files = crate::pluralize!("listed file", unreadable);
+        eprintln!("{cmd}: WARNING: {unreadable} {files} could not be read");
+    }
+    if mismatched > 0 {
+        let sums = crate::pluralize!("computed checksum", mismatched);
+        eprintln!("{cmd}: WARNING: {mismatched} {sums} did NOT match");
+    }
+    if mismatched > 0 || unreadable > 0 {
+        status = ExitCode::FAILURE;
+    }
+    Ok(status)
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::shell::tests::parse_cmd;
+    use crate::vfs::MemVfs;
+
+    const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+    const ABC_SHA1: &str = "a9993e364706816aba3e25717850c26c9cd0d89d";
+    const ABC_MD5: &str = "900150983cd24fb0d6963f7d28e17f72";
+    const EMPTY_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    fn test_fs() -> MemVfs {
+        MemVfs::new()
+            .with_file("/data/abc.txt", "abc")
+            .with_file("/data/empty.txt", "")
+    }
+
+    fn exec(fs: &dyn Vfs, line: &str) -> (HandlerResult, String) {
+        let mut out = Vec::new();
+        let result = run(fs, &parse_cmd(line), &mut out);
+        (result, String::from_utf8(out).expect("utf-8"))
+    }
+
+    #[test]
+    fn prints_known_checksums() {
+        let mut fs = test_fs();
+        fs.cd(Path::new("/data")).expect("cd");
+        for (cmd, sum) in [
+            ("sha256sum", ABC_SHA256),
+            ("sha1sum", ABC_SHA1),
+            ("md5sum", ABC_MD5),
+        ] {
+            let (result, out) = exec(&fs, &format!("{cmd} abc.txt"));
+            assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+            assert_eq!(out, format!("{sum}  abc.txt\n"));
+        }
+
+        let (result, out) = exec(&fs, "sha256sum /data/empty.txt missing.txt abc.txt");
+        assert_eq!(result.ok(), Some(ExitCode::FAILURE));
+        assert_eq!(
+            out,
+            format!("{EMPTY_SHA256}  /data/empty.txt\n{ABC_SHA256}  abc.txt\n")
+        );
+    }
+
+    #[test]
+    fn check_verifies_listed_files() {
+        let mut fs = test_fs().with_file(
+            "/data/SHA256SUMS",
+            format!(
+                "{ABC_SHA256}  abc.txt\n{} *empty.txt\n",
+                ABC_SHA256.to_uppercase()
+            ),
+        );
+        fs.cd(Path::new("/data")).expect("cd");
+        let (result, out) = exec(&fs, "sha256sum -c SHA256SUMS");
+        assert_eq!(result.ok(), Some(ExitCode::FAILURE));
+        assert_eq!(out, "abc.txt: OK\nempty.txt: FAILED\n");
+
+        let fs = fs.with_file(
+            "/data/SHA256SUMS",
+            format!("# delivery 42\n{ABC_SHA256}  abc.txt\r\n\n{EMPTY_SHA256}  empty.txt\n"),
+        );
+        let (result, out) = exec(&fs, "sha256sum --check SHA256SUMS");
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+        assert_eq!(out, "abc.txt: OK\nempty.txt: OK\n");
+    }
+
+    #[test]
+    fn check_reports_unreadable_and_malformed_lines() {
+        let fs = test_fs().with_file(
+            "/MD5SUMS",
+            format!(
+                "{ABC_MD5}  /data/abc.txt\n{ABC_MD5}  /data/gone.txt\n{ABC_SHA1}  /data/abc.txt\n"
+            ),
+        );
+        let (result, out) = exec(&fs, "md5sum -c /MD5SUMS");
+        assert_eq!(result.ok(), Some(ExitCode::FAILURE));
+        assert_eq!(
+            out,
+            "/data/abc.txt: OK\n/data/gone.txt: FAILED open or read\n"
+        );
+
+        // Nothing to check at all
+        let (result, out) = exec(&fs, "sha1sum -c /data/abc.txt");
+        assert_eq!(result.ok(), Some(ExitCode::FAILURE));
+        assert_eq!(out, "");
+    }
+
+    #[test]
+    fn parse_line_accepts_text_and_binary_mode() {
+        let line = format!("{ABC_MD5}  name with spaces");
+        assert_eq!(
+            parse_line(&line, Algorithm::Md5),
+            Some((ABC_MD5, "name with spaces"))
+        );
+        let line = format!("{ABC_MD5} *bin");
+        assert_eq!(parse_line(&line, Algorithm::Md5), Some((ABC_MD5, "bin")));
+        assert_eq!(parse_line(&line, Algorithm::Sha1), None);
+        assert_eq!(parse_line(&format!("{ABC_MD5}  "), Algorithm::Md5), None);
+        assert_eq!(parse_line("xyz  file", Algorithm::Md5), None);
+    }
+}
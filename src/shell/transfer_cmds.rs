@@ -0,0 +1,667 @@
+use std::fmt::Display;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Args, Subcommand, ValueHint};
+
+use super::vfs_cmds::format_size;
+use super::{BasicShell, HandlerResult, HostAccess, HostPolicyFn};
+use crate::vfs::{Vfs, VfsError, VfsFileType, VfsResult, VfsWriteMode};
+
+#[derive(Subcommand)]
+pub enum VfsTransferCommands {
+    /// Copy a file or directory from the VFS to the host
+    Get(GetArgs),
+
+    /// Copy a file or directory from the host into the VFS
+    Put(PutArgs),
+}
+
+#[derive(Args)]
+pub struct TransferOptions {
+    /// Copy directories recursively
+    #[arg(short = 'r', visible_short_alias = 'R', long = "recursive")]
+    recursive: bool,
+
+    /// Overwrite existing files
+    #[arg(short = 'f', long = "force")]
+    force: bool,
+
+    /// Report every file copied, and the total at the end
+    #[arg(long = "progress")]
+    progress: bool,
+}
+
+#[derive(Args)]
+pub struct GetArgs {
+    #[command(flatten)]
+    options: TransferOptions,
+
+    /// File or directory in the VFS
+    #[arg(value_hint = ValueHint::AnyPath)]
+    source: PathBuf,
+
+    /// Host destination, defaulting to the current host directory
+    #[arg(value_hint = ValueHint::Other)]
+    dest: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct PutArgs {
+    #[command(flatten)]
+    options: TransferOptions,
+
+    /// File or directory on the host
+    #[arg(value_hint = ValueHint::Other)]
+    source: PathBuf,
+
+    /// VFS destination, defaulting to the current directory
+    #[arg(value_hint = ValueHint::AnyPath)]
+    dest: Option<PathBuf>,
+}
+
+pub fn handle_vfs_transfer_command(sh: &BasicShell, cmd: VfsTransferCommands) -> HandlerResult {
+    let policy = sh.host_policy.clone();
+    sh.with_vfs(move |vfs| run(vfs, &cmd, policy.as_deref(), &mut std::io::stderr().lock()))
+}
+
+fn run(
+    vfs: &mut dyn Vfs,
+    cmd: &VfsTransferCommands,
+    policy: Option<&HostPolicyFn>,
+    progress: &mut dyn Write,
+) -> HandlerResult {
+    let transfer = match cmd {
+        VfsTransferCommands::Get(args) => {
+            let mut transfer = Transfer::new("get", &args.options, policy, progress);
+            let from = vfs.resolve(&args.source);
+            let dest = args.dest.as_deref().unwrap_or_else(|| Path::new("."));
+            let to = if dest.is_dir() {
+                dest.join(from.file_name().unwrap_or_default())
+            } else {
+                dest.to_path_buf()
+            };
+            transfer.get(vfs, &from, &to, false);
+            transfer
+        }
+        VfsTransferCommands::Put(args) => {
+            let mut transfer = Transfer::new("put", &args.options, policy, progress);
+            let dest = vfs.resolve(args.dest.as_deref().unwrap_or_else(|| Path::new(".")));
+            let to = if vfs.metadata(&dest).is_ok_and(|meta| meta.is_dir()) {
+                dest.join(args.source.file_name().unwrap_or_default())
+            } else {
+                dest
+            };
+            transfer.put(vfs, &args.source, &to, false);
+            transfer
+        }
+    };
+    transfer.finish()
+}
+
+/// A `get` or `put` in progress: failures are reported and turn the status
+/// into a failure, but the other files are still copied.
+struct Transfer<'a> {
+    cmd: &'static str,
+    options: &'a TransferOptions,
+    policy: Option<&'a HostPolicyFn>,
+    progress: &'a mut dyn Write,
+    files: u64,
+    bytes: u64,
+    status: ExitCode,
+}
+
+impl<'a> Transfer<'a> {
+    fn new(
+        cmd: &'static str,
+        options: &'a TransferOptions,
+        policy: Option<&'a HostPolicyFn>,
+        progress: &'a mut dyn Write,
+    ) -> Self {
+        Self {
+            cmd,
+            options,
+            policy,
+            progress,
+            files: 0,
+            bytes: 0,
+            status: ExitCode::SUCCESS,
+        }
+    }
+
+    fn fail(&mut self, e: impl Display) {
+        eprintln!("{}: {e}", self.cmd);
+        self.status = ExitCode::FAILURE;
+    }
+
+    fn check<T>(&mut self, result: VfsResult<T>) -> Option<T> {
+        result.map_err(|e| self.fail(e)).ok()
+    }
+
+    /// Ask the policy whether `path` may be accessed, and return the path to
+    /// access. With a policy, that is `path` with its symlinks resolved, so
+    /// that the policy judges the file that is actually read or written.
+    fn allow(&mut self, path: &Path, access: HostAccess) -> Option<PathBuf> {
+        let Some(policy) = self.policy else {
+            return Some(path.to_path_buf());
+        };
+        let resolved = resolve(path, access).map_err(|e| VfsError::from_io(path, e));
+        let resolved = self.check(resolved)?;
+        if policy(&resolved, access) {
+            Some(resolved)
+        } else {
+            self.fail(VfsError::PermissionDenied(path.into()));
+            None
+        }
+    }
+
+    /// Report a copied file.
+    fn copied(&mut self, from: &Path, to: &Path, bytes: u64) -> std::io::Result<()> {
+        self.files += 1;
+        self.bytes += bytes;
+        if self.options.progress {
+            writeln!(
+                self.progress,
+                "{} -> {} ({})",
+                from.display(),
+                to.display(),
+                format_size(bytes, true)
+            )?;
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> HandlerResult {
+        if self.options.progress {
+            writeln!(
+                self.progress,
+                "{} {} copied, {}",
+                self.files,
+                crate::pluralize!("file", self.files),
+                format_size(self.bytes, true)
+            )?;
+        }
+        Ok(self.status)
+    }
+
+    /// The error for a file that exists and may not be overwritten.
+    fn exists(&mut self, path: &Path) {
+        self.fail(format_args!(
+            "'{}' exists, use -f to overwrite",
+            path.display()
+        ));
+    }
+
+    /// Refuse directories without `-r`; returns whether to go on.
+    fn recurse(&mut self, path: &Path) -> bool {
+        if !self.options.recursive {
+            self.fail(format_args!(
+                "-r not specified; omitting directory '{}'",
+                path.display()
+            ));
+        }
+        self.options.recursive
+    }
+
+    /// Refuse a symlink to a directory met while recursing, as `cp -r` does:
+    /// it could lead anywhere, even into a loop.
+    fn omit(&mut self, path: &Path) {
+        self.fail(format_args!(
+            "omitting '{}': not a regular file or directory",
+            path.display()
+        ));
+    }
+
+    // -- get -------------------------------------------------------------
+
+    /// Copy `from` in the VFS to `to` on the host; `link` is whether `from`
+    /// is a symlink in a directory being copied.
+    fn get(&mut self, vfs: &dyn Vfs, from: &Path, to: &Path, link: bool) {
+        let Some(meta) = self.check(vfs.metadata(from)) else {
+            return;
+        };
+        if meta.is_dir() {
+            if link {
+                self.omit(from);
+                return;
+            }
+            if !self.recurse(from) {
+                return;
+            }
+            let Some(host) = self.allow(to, HostAccess::Write) else {
+                return;
+            };
+            match std::fs::create_dir(&host) {
+                Err(e) if e.kind() == ErrorKind::AlreadyExists && host.is_dir() => {}
+                result => {
+                    if self
+                        .check(result.map_err(|e| VfsError::from_io(to, e)))
+                        .is_none()
+                    {
+                        return;
+                    }
+                }
+            }
+            let Some(mut entries) = self.check(vfs.read_dir(from)) else {
+                return;
+            };
+            entries.sort_by(|a, b| a.name.cmp(&b.name));
+            for entry in entries {
+                let link = entry.file_type == VfsFileType::Symlink;
+                self.get(vfs, &from.join(&entry.name), &to.join(&entry.name), link);
+            }
+        } else if let Some(host) = self.allow(to, HostAccess::Write) {
+            if let Some(bytes) = self.get_file(vfs, from, to, &host) {
+                if let Err(e) = self.copied(from, to, bytes) {
+                    self.fail(e);
+                }
+            }
+        }
+    }
+
+    /// Copy the file `from` to `host`, the allowed path of `to`.
+    fn get_file(&mut self, vfs: &dyn Vfs, from: &Path, to: &Path, host: &Path) -> Option<u64> {
+        // The policy approved `host` without symlinks; don't follow one that
+        // has been put there since. Creating a new file never follows it.
+        if self.policy.is_some()
+            && self.options.force
+            && std::fs::symlink_metadata(host).is_ok_and(|m| m.is_symlink())
+        {
+            self.fail(VfsError::PermissionDenied(to.into()));
+            return None;
+        }
+        let mut options = OpenOptions::new();
+        if self.options.force {
+            options.write(true).create(true).truncate(true);
+        } else {
+            options.write(true).create_new(true);
+        }
+        let mut reader = self.check(vfs.open_read(from))?;
+        let file = match options.open(host) {
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                self.exists(to);
+                return None;
+            }
+            result => self.check(result.map_err(|e| VfsError::from_io(to, e)))?,
+        };
+        let mut writer = BufWriter::new(file);
+        let copied = std::io::copy(&mut reader, &mut writer).and_then(|bytes| {
+            writer.flush()?;
+            Ok(bytes)
+        });
+        self.check(copied.map_err(|e| VfsError::from_io(to, e)))
+    }
+
+    // -- put -------------------------------------------------------------
+
+    /// Copy `from` on the host to `to` in the VFS; `link` is whether `from`
+    /// is a symlink in a directory being copied.
+    fn put(&mut self, vfs: &mut dyn Vfs, from: &Path, to: &Path, link: bool) {
+        let Some(host) = self.allow(from, HostAccess::Read) else {
+            return;
+        };
+        let Some(meta) =
+            self.check(std::fs::metadata(&host).map_err(|e| VfsError::from_io(from, e)))
+        else {
+            return;
+        };
+        if meta.is_dir() {
+            if link {
+                self.omit(from);
+                return;
+            }
+            if !self.recurse(from) {
+                return;
+            }
+            match vfs.create_dir(to) {
+                Err(VfsError::AlreadyExists(_)) if vfs.metadata(to).is_ok_and(|m| m.is_dir()) => {}
+                result => {
+                    if self.check(result).is_none() {
+                        return;
+                    }
+                }
+            }
+            let entries = std::fs::read_dir(&host).and_then(|entries| {
+                entries
+                    .map(|e| e.and_then(|e| Ok((e.file_name(), e.file_type()?.is_symlink()))))
+                    .collect::<std::io::Result<Vec<_>>>()
+            });
+            let Some(mut entries) = self.check(entries.map_err(|e| VfsError::from_io(from, e)))
+            else {
+                return;
+            };
+            entries.sort();
+            for (name, link) in entries {
+                self.put(vfs, &from.join(&name), &to.join(&name), link);
+            }
+        } else if let Some(bytes) = self.put_file(vfs, from, to, &host) {
+            if let Err(e) = self.copied(from, to, bytes) {
+                self.fail(e);
+            }
+        }
+    }
+
+    /// Copy `host`, the allowed path of `from`, to the file `to`.
+    fn put_file(&mut self, vfs: &mut dyn Vfs, from: &Path, to: &Path, host: &Path) -> Option<u64> {
+        let mode = if self.options.force {
+            VfsWriteMode::Truncate
+        } else {
+            VfsWriteMode::CreateNew
+        };
+        let mut reader = self.check(File::open(host).map_err(|e| VfsError::from_io(from, e)))?;
+        let mut writer = match vfs.open_write(to, mode) {
+            Err(VfsError::AlreadyExists(_)) => {
+                self.exists(to);
+                return None;
+            }
+            result => self.check(result)?,
+        };
+        let copied = std::io::copy(&mut reader, &mut writer).and_then(|bytes| {
+            writer.flush()?;
+            Ok(bytes)
+        });
+        self.check(copied.map_err(|e| VfsError::from_io(to, e)))
+    }
+}
+
+/// `path` with its symlinks resolved. A file to be written that does not
+/// exist yet resolves to its canonical directory joined with its name.
+fn resolve(path: &Path, access: HostAccess) -> std::io::Result<PathBuf> {
+    match std::fs::canonicalize(path) {
+        Err(e) if e.kind() == ErrorKind::NotFound && access == HostAccess::Write => {
+            // A dangling symlink is not a missing file: writing would follow
+            // it to where the policy cannot see
+            if std::fs::symlink_metadata(path).is_ok() {
+                return Err(ErrorKind::PermissionDenied.into());
+            }
+            let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+                return Err(e);
+            };
+            let parent = if parent.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                parent
+            };
+            Ok(std::fs::canonicalize(parent)?.join(name))
+        }
+        result => result,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::shell::tests::parse_cmd;
+    use crate::vfs::MemVfs;
+    use std::io::Read;
+
+    fn test_fs() -> MemVfs {
+        MemVfs::new()
+            .with_file("/etc/app.conf", "debug = false\n")
+            .with_file("/data/a.csv", "x,y\n")
+            .with_file("/data/sub/b.csv", "1,2\n")
+    }
+
+    /// Run `line` with host paths relative to `host`.
+    fn exec_with(
+        vfs: &mut dyn Vfs,
+        host: &Path,
+        line: &str,
+        policy: Option<&HostPolicyFn>,
+    ) -> (HandlerResult, String) {
+        let mut cmd = parse_cmd(line);
+        match &mut cmd {
+            VfsTransferCommands::Get(args) => {
+                args.dest = Some(host.join(args.dest.take().unwrap_or_default()));
+            }
+            VfsTransferCommands::Put(args) => args.source = host.join(&args.source),
+        }
+        let mut progress = Vec::new();
+        let result = run(vfs, &cmd, policy, &mut progress);
+        (result, String::from_utf8(progress).expect("utf-8"))
+    }
+
+    fn exec(vfs: &mut dyn Vfs, host: &Path, line: &str) -> HandlerResult {
+        exec_with(vfs, host, line, None).0
+    }
+
+    fn read(vfs: &dyn Vfs, path: &str) -> String {
+        let mut s = String::new();
+        vfs.open_read(Path::new(path))
+            .expect("open")
+            .read_to_string(&mut s)
+            .expect("read");
+        s
+    }
+
+    #[test]
+    fn get_copies_files_to_the_host() {
+        let host = tempfile::tempdir().expect("failed to create tempdir");
+        let mut vfs = test_fs();
+        vfs.cd(Path::new("/etc")).expect("cd");
+
+        assert_eq!(
+            exec(&mut vfs, host.path(), "get app.conf").ok(),
+            Some(ExitCode::SUCCESS)
+        );
+        let copy = host.path().join("app.conf");
+        assert_eq!(
+            std::fs::read_to_string(&copy).expect("read"),
+            "debug = false\n"
+        );
+
+        assert_eq!(
+            exec(&mut vfs, host.path(), "get /data/a.csv renamed.csv").ok(),
+            Some(ExitCode::SUCCESS)
+        );
+        assert!(host.path().join("renamed.csv").is_file());
+    }
+
+    #[test]
+    fn get_and_put_refuse_to_overwrite_without_force() {
+        let host = tempfile::tempdir().expect("failed to create tempdir");
+        std::fs::write(host.path().join("app.conf"), "mine\n").expect("write");
+        std::fs::write(host.path().join("a.csv"), "new\n").expect("write");
+        let mut vfs = test_fs();
+
+        let get = "get /etc/app.conf";
+        assert_eq!(
+            exec(&mut vfs, host.path(), get).ok(),
+            Some(ExitCode::FAILURE)
+        );
+        assert_eq!(
+            std::fs::read_to_string(host.path().join("app.conf")).expect("read"),
+            "mine\n"
+        );
+        let put = "put a.csv /data";
+        assert_eq!(
+            exec(&mut vfs, host.path(), put).ok(),
+            Some(ExitCode::FAILURE)
+        );
+        assert_eq!(read(&vfs, "/data/a.csv"), "x,y\n");
+
+        assert_eq!(
+            exec(&mut vfs, host.path(), "get -f /etc/app.conf").ok(),
+            Some(ExitCode::SUCCESS)
+        );
+        assert_eq!(
+            std::fs::read_to_string(host.path().join("app.conf")).expect("read"),
+            "debug = false\n"
+        );
+        assert_eq!(
+            exec(&mut vfs, host.path(), "put -f a.csv /data").ok(),
+            Some(ExitCode::SUCCESS)
+        );
+        assert_eq!(read(&vfs, "/data/a.csv"), "new\n");
+    }
+
+    #[test]
+    fn directories_need_recursion() {
+        let host = tempfile::tempdir().expect("failed to create tempdir");
+        let mut vfs = test_fs();
+        assert_eq!(
+            exec(&mut vfs, host.path(), "get /data").ok(),
+            Some(ExitCode::FAILURE)
+        );
+        assert!(!host.path().join("data").exists());
+
+        assert_eq!(
+            exec(&mut vfs, host.path(), "get -r /data").ok(),
+            Some(ExitCode::SUCCESS)
+        );
+        let data = host.path().join("data");
+        assert_eq!(
+            std::fs::read_to_string(data.join("sub/b.csv")).expect("read"),
+            "1,2\n"
+        );
+
+        // Back into a fresh directory of the VFS
+        std::fs::write(data.join("c.csv"), "3,4\n").expect("write");
+        assert_eq!(
+            exec(&mut vfs, host.path(), "put data /copy").ok(),
+            Some(ExitCode::FAILURE)
+        );
+        assert_eq!(
+            exec(&mut vfs, host.path(), "put -r data /copy").ok(),
+            Some(ExitCode::SUCCESS)
+        );
+        assert_eq!(read(&vfs, "/copy/c.csv"), "3,4\n");
+        assert_eq!(read(&vfs, "/copy/sub/b.csv"), "1,2\n");
+    }
+
+    #[test]
+    fn progress_reports_files_and_total() {
+        let host = tempfile::tempdir().expect("failed to create tempdir");
+        let mut vfs = test_fs();
+        let (result, progress) = exec_with(&mut vfs, host.path(), "get -r --progress /data", None);
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+        let dest = host.path().join("data");
+        assert_eq!(
+            progress,
+            format!(
+                "/data/a.csv -> {} (4)\n/data/sub/b.csv -> {} (4)\n2 files copied, 8\n",
+                dest.join("a.csv").display(),
+                dest.join("sub/b.csv").display()
+            )
+        );
+    }
+
+    #[test]
+    fn policy_governs_host_paths() {
+        let host = tempfile::tempdir().expect("failed to create tempdir");
+        std::fs::write(host.path().join("secret"), "s\n").expect("write");
+        std::fs::create_dir(host.path().join("outbox")).expect("mkdir");
+        let outbox = host.path().join("outbox");
+        let allowed = std::fs::canonicalize(&outbox).expect("canonicalize");
+        let policy = move |path: &Path, access: HostAccess| {
+            access == HostAccess::Write && path.starts_with(&allowed)
+        };
+        let mut vfs = test_fs();
+
+        let (result, _) = exec_with(&mut vfs, host.path(), "get /etc/app.conf", Some(&policy));
+        assert_eq!(result.ok(), Some(ExitCode::FAILURE));
+        assert!(!host.path().join("app.conf").exists());
+        let (result, _) = exec_with(
+            &mut vfs,
+            host.path(),
+            "get /etc/app.conf outbox",
+            Some(&policy),
+        );
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+        assert!(outbox.join("app.conf").exists());
+
+        let (result, _) = exec_with(&mut vfs, host.path(), "put secret /", Some(&policy));
+        assert_eq!(result.ok(), Some(ExitCode::FAILURE));
+        assert!(!vfs.exists(Path::new("/secret")).expect("exists"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_directories_are_not_followed() {
+        use std::os::unix::fs::symlink;
+
+        let host = tempfile::tempdir().expect("failed to create tempdir");
+        let src = host.path().join("src");
+        std::fs::create_dir(&src).expect("mkdir");
+        std::fs::write(src.join("f"), "f\n").expect("write");
+        symlink("f", src.join("g")).expect("symlink");
+        symlink("..", src.join("up")).expect("symlink");
+        symlink(".", src.join("loop")).expect("symlink");
+
+        let mut vfs = test_fs();
+        assert_eq!(
+            exec(&mut vfs, host.path(), "put -r src /dst").ok(),
+            Some(ExitCode::FAILURE)
+        );
+        assert_eq!(read(&vfs, "/dst/f"), "f\n");
+        assert_eq!(read(&vfs, "/dst/g"), "f\n");
+        assert!(!vfs.exists(Path::new("/dst/up")).expect("exists"));
+        assert!(!vfs.exists(Path::new("/dst/loop")).expect("exists"));
+
+        let out = tempfile::tempdir().expect("failed to create tempdir");
+        let mut vfs = crate::vfs::HostVfs::new(host.path()).expect("host vfs");
+        assert_eq!(
+            exec(&mut vfs, out.path(), "get -r /src").ok(),
+            Some(ExitCode::FAILURE)
+        );
+        let copy = out.path().join("src");
+        assert_eq!(
+            std::fs::read_to_string(copy.join("f")).expect("read"),
+            "f\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(copy.join("g")).expect("read"),
+            "f\n"
+        );
+        assert!(!copy.join("up").exists());
+        assert!(!copy.join("loop").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn policy_sees_through_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let host = tempfile::tempdir().expect("failed to create tempdir");
+        let secret = host.path().join("secret");
+        std::fs::write(&secret, "s\n").expect("write");
+        let outbox = host.path().join("outbox");
+        std::fs::create_dir(&outbox).expect("mkdir");
+        symlink(&secret, outbox.join("link")).expect("symlink");
+        symlink(host.path().join("gone"), outbox.join("dangling")).expect("symlink");
+        symlink(host.path(), outbox.join("up")).expect("symlink");
+        let allowed = std::fs::canonicalize(&outbox).expect("canonicalize");
+        let policy = move |path: &Path, _| path.starts_with(&allowed);
+        let mut vfs = test_fs();
+
+        // Writes through a link land outside of the outbox
+        for line in [
+            "get -f /etc/app.conf outbox/link",
+            "get /etc/app.conf outbox/dangling",
+            "get /etc/app.conf outbox/up/app.conf",
+        ] {
+            let (result, _) = exec_with(&mut vfs, host.path(), line, Some(&policy));
+            assert_eq!(result.ok(), Some(ExitCode::FAILURE), "{line}");
+        }
+        assert_eq!(std::fs::read_to_string(&secret).expect("read"), "s\n");
+        assert!(!host.path().join("gone").exists());
+        assert!(!host.path().join("app.conf").exists());
+
+        // and so do reads
+        let (result, _) = exec_with(&mut vfs, host.path(), "put outbox/link /", Some(&policy));
+        assert_eq!(result.ok(), Some(ExitCode::FAILURE));
+        assert!(!vfs.exists(Path::new("/link")).expect("exists"));
+
+        let (result, _) = exec_with(
+            &mut vfs,
+            host.path(),
+            "get /etc/app.conf outbox/new.conf",
+            Some(&policy),
+        );
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+        assert!(outbox.join("new.conf").is_file());
+    }
+}
@@ -0,0 +1,601 @@
+use std::cmp::Reverse;
+use std::ffi::OsStr;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::{ArgAction, Args, Subcommand, ValueHint};
+
+use super::{BasicShell, HandlerResult, HANDLER_SUCCESS};
+use crate::vfs::{Vfs, VfsError, VfsFileType, VfsMetadata};
+
+#[derive(Subcommand)]
+pub enum VfsSharedCommands {
+    /// Print the current working directory
+    Pwd,
+
+    /// Change the current working directory
+    Cd {
+        /// Directory to change to, defaults to the root
+        #[arg(value_hint = ValueHint::DirPath)]
+        path: Option<PathBuf>,
+    },
+
+    /// List directory contents
+    // -h means human-readable sizes, like in every other ls
+    #[command(disable_help_flag = true)]
+    Ls(LsArgs),
+
+    /// List directory contents as a tree
+    Tree(TreeArgs),
+}
+
+#[derive(Args)]
+#[allow(clippy::struct_excessive_bools)] // one per ls flag
+pub struct LsArgs {
+    /// Use a long listing format
+    #[arg(short = 'l')]
+    long: bool,
+
+    /// Do not ignore entries starting with .
+    #[arg(short = 'a', long = "all")]
+    all: bool,
+
+    /// With -l, print sizes like 1K 234M 2G
+    #[arg(short = 'h', long = "human-readable")]
+    human: bool,
+
+    /// Sort by file size, largest first
+    #[arg(short = 'S', conflicts_with = "time")]
+    size: bool,
+
+    /// Sort by modification time, newest first
+    #[arg(short = 't')]
+    time: bool,
+
+    /// Reverse the sort order
+    #[arg(short = 'r', long = "reverse")]
+    reverse: bool,
+
+    /// Print help
+    #[arg(long, action = ArgAction::Help)]
+    help: Option<bool>,
+
+    /// Files or directories to list, defaults to the current directory
+    #[arg(value_hint = ValueHint::AnyPath)]
+    paths: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct TreeArgs {
+    /// Descend at most LEVEL directories deep
+    #[arg(short = 'L', value_name = "LEVEL", value_parser = clap::value_parser!(u32).range(1..))]
+    level: Option<u32>,
+
+    /// Do not ignore entries starting with .
+    #[arg(short = 'a')]
+    all: bool,
+
+    /// List directories only
+    #[arg(short = 'd')]
+    dirs_only: bool,
+
+    /// Directory to list, defaults to the current directory
+    #[arg(value_hint = ValueHint::DirPath)]
+    path: Option<PathBuf>,
+}
+
+pub fn handle_vfs_shared_command(sh: &BasicShell, cmd: VfsSharedCommands) -> HandlerResult {
+    sh.with_vfs(|vfs| run(vfs, cmd, &mut std::io::stdout().lock()))
+}
+
+fn run(vfs: &mut dyn Vfs, cmd: VfsSharedCommands, out: &mut dyn Write) -> HandlerResult {
+    match cmd {
+        VfsSharedCommands::Pwd => {
+            writeln!(out, "{}", vfs.cwd().display())?;
+            HANDLER_SUCCESS
+        }
+        VfsSharedCommands::Cd { path } => {
+            vfs.cd(path.as_deref().unwrap_or_else(|| Path::new("/")))?;
+            HANDLER_SUCCESS
+        }
+        VfsSharedCommands::Ls(args) => ls(vfs, &args, out),
+        VfsSharedCommands::Tree(args) => tree(vfs, &args, out),
+    }
+}
+
+fn is_hidden(name: &OsStr) -> bool {
+    name.as_encoded_bytes().first() == Some(&b'.')
+}
+
+// -- ls ----------------------------------------------------------------------
+
+/// One line of `ls` output.
+struct Item {
+    name: String,
+    file_type: VfsFileType,
+    meta: Option<VfsMetadata>,
+}
+
+fn ls(vfs: &dyn Vfs, args: &LsArgs, out: &mut dyn Write) -> HandlerResult {
+    let paths = if args.paths.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        args.paths.clone()
+    };
+
+    // Like ls, list the operands that are files first, then each directory
+    let mut status = ExitCode::SUCCESS;
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+    for path in &paths {
+        let resolved = vfs.resolve(path);
+        match vfs.metadata(&resolved) {
+            Ok(meta) if meta.is_dir() => dirs.push((path, resolved)),
+            Ok(meta) => files.push(Item {
+                name: path.display().to_string(),
+                file_type: meta.file_type,
+                meta: Some(meta),
+            }),
+            Err(e) => {
+                eprintln!("ls: {e}");
+                status = ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let mut needs_gap = !files.is_empty();
+    write_items(out, args, files)?;
+
+    for (path, resolved) in dirs {
+        if paths.len() > 1 {
+            if needs_gap {
+                writeln!(out)?;
+            }
+            writeln!(out, "{}:", path.display())?;
+            needs_gap = true;
+        }
+        match list_dir(vfs, &resolved, args) {
+            Ok(items) => write_items(out, args, items)?,
+            Err(e) => {
+                eprintln!("ls: {e}");
+                status = ExitCode::FAILURE;
+            }
+        }
+    }
+
+    Ok(status)
+}
+
+fn list_dir(vfs: &dyn Vfs, dir: &Path, args: &LsArgs) -> Result<Vec<Item>, VfsError> {
+    let needs_meta = args.long || args.size || args.time;
+    Ok(vfs
+        .read_dir(dir)?
+        .into_iter()
+        .filter(|entry| args.all || !is_hidden(&entry.name))
+        .map(|entry| Item {
+            meta: needs_meta
+                .then(|| vfs.metadata(&dir.join(&entry.name)).ok())
+                .flatten(),
+            name: entry.name.to_string_lossy().into_owned(),
+            file_type: entry.file_type,
+        })
+        .collect())
+}
+
+fn write_items(out: &mut dyn Write, args: &LsArgs, mut items: Vec<Item>) -> std::io::Result<()> {
+    items.sort_by(|a, b| a.name.cmp(&b.name));
+    if args.size {
+        items.sort_by_key(|item| Reverse(item.meta.as_ref().map_or(0, |m| m.len)));
+    } else if args.time {
+        items.sort_by_key(|item| Reverse(item.meta.as_ref().and_then(|m| m.modified)));
+    }
+    if args.reverse {
+        items.reverse();
+    }
+
+    if !args.long {
+        for item in &items {
+            writeln!(out, "{}", item.name)?;
+        }
+        return Ok(());
+    }
+
+    let sizes: Vec<String> = items
+        .iter()
+        .map(|item| {
+            item.meta
+                .as_ref()
+                .map_or_else(|| "?".into(), |m| format_size(m.len, args.human))
+        })
+        .collect();
+    let width = sizes.iter().map(String::len).max().unwrap_or(0);
+
+    for (item, size) in items.iter().zip(sizes) {
+        writeln!(
+            out,
+            "{} {size:>width$} {} {}",
+            format_mode(item.file_type, item.meta.as_ref()),
+            format_time(item.meta.as_ref().and_then(|m| m.modified)),
+            item.name
+        )?;
+    }
+    Ok(())
+}
+
+/// Format the file type and permission bits like `drwxr-xr-x`.
+pub(super) fn format_mode(file_type: VfsFileType, meta: Option<&VfsMetadata>) -> String {
+    let mut mode = String::with_capacity(10);
+    mode.push(match file_type {
+        VfsFileType::File => '-',
+        VfsFileType::Dir => 'd',
+        VfsFileType::Symlink => 'l',
+        VfsFileType::Other => '?',
+    });
+
+    let bits = meta.map_or(0, mode_bits);
+    for shift in [6, 3, 0] {
+        let triple = bits >> shift;
+        mode.push(if triple & 4 == 0 { '-' } else { 'r' });
+        mode.push(if triple & 2 == 0 { '-' } else { 'w' });
+        mode.push(if triple & 1 == 0 { '-' } else { 'x' });
+    }
+    mode
+}
+
+/// The permission bits of `meta`, made up for backends without them.
+pub(super) const fn mode_bits(meta: &VfsMetadata) -> u32 {
+    // Backends without permission bits only know whether they are writable
+    match meta.mode {
+        Some(mode) => mode,
+        None if meta.readonly => 0o444,
+        None => 0o644,
+    }
+}
+
+/// Format a size in bytes, or with `human` in powers of 1024 with one
+/// decimal below 10, rounding up like ls does.
+pub(super) fn format_size(len: u64, human: bool) -> String {
+    if !human || len < 1024 {
+        return len.to_string();
+    }
+    let mut divisor: u64 = 1024;
+    for unit in ["K", "M", "G", "T", "P"] {
+        if len / divisor < 1024 {
+            let tenths = (u128::from(len) * 10).div_ceil(u128::from(divisor));
+            if tenths < 100 {
+                return format!("{}.{}{unit}", tenths / 10, tenths % 10);
+            }
+            return format!("{}{unit}", len.div_ceil(divisor));
+        }
+        divisor *= 1024;
+    }
+    format!("{}E", len.div_ceil(divisor))
+}
+
+/// Format a timestamp as `YYYY-MM-DD HH:MM` in UTC.
+fn format_time(time: Option<SystemTime>) -> String {
+    let Some(secs) = time
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+    else {
+        return "????-??-?? ??:??".into();
+    };
+    let (year, month, day) = civil_from_days(secs / 86_400);
+    let secs = secs % 86_400;
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}",
+        secs / 3600,
+        secs % 3600 / 60
+    )
+}
+
+/// Convert days since 1970-01-01 into a proleptic Gregorian (year, month,
+/// day), see <https://howardhinnant.github.io/date_algorithms.html>.
+pub(super) const fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// -- tree --------------------------------------------------------------------
+
+struct TreeWalk<'a> {
+    vfs: &'a dyn Vfs,
+    args: &'a TreeArgs,
+    out: &'a mut dyn Write,
+    dirs: usize,
+    files: usize,
+}
+
+impl TreeWalk<'_> {
+    fn walk(&mut self, dir: &Path, prefix: &str, depth: u32) -> std::io::Result<()> {
+        let mut entries = match self.vfs.read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => return writeln!(self.out, "{prefix}[{e}]"),
+        };
+        entries.retain(|entry| {
+            (self.args.all || !is_hidden(&entry.name))
+                && (!self.args.dirs_only || entry.file_type == VfsFileType::Dir)
+        });
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let count = entries.len();
+        for (i, entry) in entries.into_iter().enumerate() {
+            let last = i + 1 == count;
+            let branch = if last { "└── " } else { "├── " };
+            writeln!(self.out, "{prefix}{branch}{}", entry.name.to_string_lossy())?;
+
+            if entry.file_type != VfsFileType::Dir {
+                self.files += 1;
+                continue;
+            }
+            self.dirs += 1;
+            if self.args.level.is_none_or(|level| depth < level) {
+                let indent = if last { "    " } else { "│   " };
+                self.walk(
+                    &dir.join(&entry.name),
+                    &format!("{prefix}{indent}"),
+                    depth + 1,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn tree(vfs: &dyn Vfs, args: &TreeArgs, out: &mut dyn Write) -> HandlerResult {
+    let path = args.path.clone().unwrap_or_else(|| PathBuf::from("."));
+    let root = vfs.resolve(&path);
+    if !vfs.metadata(&root)?.is_dir() {
+        return Err(VfsError::NotADirectory(root).into());
+    }
+
+    writeln!(out, "{}", path.display())?;
+    let mut walk = TreeWalk {
+        vfs,
+        args,
+        out,
+        dirs: 0,
+        files: 0,
+    };
+    walk.walk(&root, "", 1)?;
+
+    let (dirs, files) = (walk.dirs, walk.files);
+    write!(
+        out,
+        "\n{dirs} {}",
+        crate::pluralize!("directory", "directories", dirs)
+    )?;
+    if !args.dirs_only {
+        write!(out, ", {files} {}", crate::pluralize!("file", files))?;
+    }
+    writeln!(out)?;
+    HANDLER_SUCCESS
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::shell::tests::{parse_cmd, try_parse_cmd};
+    use crate::vfs::MemVfs;
+    use crate::ShellError;
+    use std::time::Duration;
+
+    fn test_fs() -> MemVfs {
+        let mut fs = MemVfs::new()
+            .with_file("/docs/big.bin", vec![0; 5 * 1024 * 1024])
+            .with_file("/docs/notes.txt", vec![b'n'; 1500])
+            .with_file("/docs/deep/er", "!")
+            .with_file("/readme.md", vec![b'r'; 42])
+            .with_file("/.hidden", ".");
+        for (path, mtime) in [
+            ("/docs", 100),
+            ("/docs/big.bin", 300),
+            ("/docs/notes.txt", 200),
+            ("/docs/deep", 100),
+            ("/docs/deep/er", 100),
+            ("/readme.md", 1_700_000_000),
+            ("/.hidden", 100),
+        ] {
+            let modified = UNIX_EPOCH + Duration::from_secs(mtime);
+            fs.set_modified(Path::new(path), modified).expect("mtime");
+        }
+        fs
+    }
+
+    fn exec(fs: &mut MemVfs, line: &str) -> (HandlerResult, String) {
+        let mut out = Vec::new();
+        let result = run(fs, parse_cmd(line), &mut out);
+        (result, String::from_utf8(out).expect("utf-8"))
+    }
+
+    fn output(fs: &mut MemVfs, line: &str) -> String {
+        let (result, out) = exec(fs, line);
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS), "{line} failed");
+        out
+    }
+
+    // -- cd / pwd ----------------------------------------------------------
+
+    #[test]
+    fn cd_changes_cwd_for_later_commands() {
+        let mut fs = test_fs();
+        output(&mut fs, "cd docs/deep");
+        assert_eq!(output(&mut fs, "pwd"), "/docs/deep\n");
+        output(&mut fs, "cd ..");
+        assert_eq!(output(&mut fs, "ls"), "big.bin\ndeep\nnotes.txt\n");
+        output(&mut fs, "cd");
+        assert_eq!(output(&mut fs, "pwd"), "/\n");
+    }
+
+    #[test]
+    fn cd_to_file_fails() {
+        let mut fs = test_fs();
+        let (result, _) = exec(&mut fs, "cd readme.md");
+        assert!(matches!(
+            result,
+            Err(ShellError::Vfs(VfsError::NotADirectory(_)))
+        ));
+        assert_eq!(fs.cwd(), Path::new("/"));
+    }
+
+    // -- ls ----------------------------------------------------------------
+
+    #[test]
+    fn ls_hides_dot_files_unless_all() {
+        let mut fs = test_fs();
+        assert_eq!(output(&mut fs, "ls"), "docs\nreadme.md\n");
+        assert_eq!(output(&mut fs, "ls -a"), ".hidden\ndocs\nreadme.md\n");
+    }
+
+    #[test]
+    fn ls_sorts_by_size_time_and_reverse() {
+        let mut fs = test_fs();
+        assert_eq!(output(&mut fs, "ls -S docs"), "big.bin\nnotes.txt\ndeep\n");
+        assert_eq!(output(&mut fs, "ls -t docs"), "big.bin\nnotes.txt\ndeep\n");
+        assert_eq!(output(&mut fs, "ls -r docs"), "notes.txt\ndeep\nbig.bin\n");
+        assert_eq!(output(&mut fs, "ls -Sr docs"), "deep\nnotes.txt\nbig.bin\n");
+    }
+
+    #[test]
+    fn ls_long_format() {
+        let mut fs = test_fs();
+        assert_eq!(
+            output(&mut fs, "ls -l"),
+            "drwxr-xr-x  0 1970-01-01 00:01 docs\n\
+             -rw-r--r-- 42 2023-11-14 22:13 readme.md\n"
+        );
+        assert_eq!(
+            output(&mut fs, "ls -lh docs"),
+            "-rw-r--r-- 5.0M 1970-01-01 00:05 big.bin\n\
+             drwxr-xr-x    0 1970-01-01 00:01 deep\n\
+             -rw-r--r-- 1.5K 1970-01-01 00:03 notes.txt\n"
+        );
+    }
+
+    #[test]
+    fn ls_files_and_directories() {
+        let mut fs = test_fs();
+        assert_eq!(
+            output(&mut fs, "ls readme.md docs/deep docs/notes.txt"),
+            "docs/notes.txt\nreadme.md\n\ndocs/deep:\ner\n"
+        );
+    }
+
+    #[test]
+    fn ls_reports_missing_paths_and_continues() {
+        let mut fs = test_fs();
+        let (result, out) = exec(&mut fs, "ls nope docs/deep");
+        assert_eq!(result.ok(), Some(ExitCode::FAILURE));
+        assert_eq!(out, "docs/deep:\ner\n");
+    }
+
+    #[test]
+    fn ls_help_is_long_only() {
+        let err = try_parse_cmd::<VfsSharedCommands>("ls --help")
+            .err()
+            .expect("help");
+        assert_eq!(err.kind(), clap::error::ErrorKind::DisplayHelp);
+        assert!(try_parse_cmd::<VfsSharedCommands>("ls -h").is_ok());
+    }
+
+    // -- tree --------------------------------------------------------------
+
+    #[test]
+    fn tree_draws_the_hierarchy() {
+        let mut fs = test_fs();
+        assert_eq!(
+            output(&mut fs, "tree"),
+            ".\n\
+             ├── docs\n\
+             │   ├── big.bin\n\
+             │   ├── deep\n\
+             │   │   └── er\n\
+             │   └── notes.txt\n\
+             └── readme.md\n\
+             \n\
+             2 directories, 4 files\n"
+        );
+    }
+
+    #[test]
+    fn tree_limits_depth() {
+        let mut fs = test_fs();
+        assert_eq!(
+            output(&mut fs, "tree -L 1 /"),
+            "/\n├── docs\n└── readme.md\n\n1 directory, 1 file\n"
+        );
+        assert!(try_parse_cmd::<VfsSharedCommands>("tree -L 0").is_err());
+    }
+
+    #[test]
+    fn tree_directories_only() {
+        let mut fs = test_fs();
+        assert_eq!(
+            output(&mut fs, "tree -d docs"),
+            "docs\n└── deep\n\n1 directory\n"
+        );
+    }
+
+    #[test]
+    fn tree_of_file_fails() {
+        let mut fs = test_fs();
+        let (result, _) = exec(&mut fs, "tree readme.md");
+        assert!(matches!(
+            result,
+            Err(ShellError::Vfs(VfsError::NotADirectory(_)))
+        ));
+    }
+
+    // -- formatting --------------------------------------------------------
+
+    #[test]
+    fn sizes_are_humanized() {
+        assert_eq!(format_size(1023, true), "1023");
+        assert_eq!(format_size(1024, true), "1.0K");
+        assert_eq!(format_size(1025, true), "1.1K");
+        assert_eq!(format_size(10 * 1024, true), "10K");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024, true), "3.0G");
+        assert_eq!(format_size(u64::MAX, true), "16E");
+        assert_eq!(format_size(4096, false), "4096");
+    }
+
+    #[test]
+    fn times_are_formatted_in_utc() {
+        let t = |secs| Some(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(format_time(t(0)), "1970-01-01 00:00");
+        assert_eq!(format_time(t(951_782_400)), "2000-02-29 00:00");
+        assert_eq!(format_time(t(1_700_000_000)), "2023-11-14 22:13");
+        assert_eq!(format_time(None), "????-??-?? ??:??");
+    }
+
+    #[test]
+    fn modes_fall_back_to_readonly_flag() {
+        let meta = |mode, readonly| VfsMetadata {
+            file_type: VfsFileType::File,
+            len: 0,
+            modified: None,
+            readonly,
+            mode,
+        };
+        assert_eq!(
+            format_mode(VfsFileType::Dir, Some(&meta(Some(0o750), false))),
+            "drwxr-x---"
+        );
+        assert_eq!(
+            format_mode(VfsFileType::File, Some(&meta(None, true))),
+            "-r--r--r--"
+        );
+        assert_eq!(format_mode(VfsFileType::Symlink, None), "l---------");
+    }
+}
@@ -0,0 +1,244 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use clap::{Args, Subcommand, ValueHint};
+
+use super::interrupt::Interrupt;
+use super::overlay_cmds::format_change;
+use super::{BasicShell, HandlerResult, ShellError, HANDLER_SUCCESS};
+use crate::vfs::{Vfs, VfsChangeKind};
+
+/// How long commands that wait for changes sleep between two looks, with
+/// the VFS unlocked.
+pub(super) const WAIT_TICK: Duration = Duration::from_millis(20);
+
+/// A VFS that others may use while a command waits for changes in it.
+pub(super) trait SharedVfs {
+    /// Run `f` on the VFS, keeping it to `f` until it returns.
+    fn step<T>(&self, f: impl FnOnce(&dyn Vfs) -> Result<T, ShellError>) -> Result<T, ShellError>;
+}
+
+impl SharedVfs for BasicShell {
+    fn step<T>(&self, f: impl FnOnce(&dyn Vfs) -> Result<T, ShellError>) -> Result<T, ShellError> {
+        self.with_vfs(|vfs| f(vfs))
+    }
+}
+
+impl<V: Vfs> SharedVfs for V {
+    fn step<T>(&self, f: impl FnOnce(&dyn Vfs) -> Result<T, ShellError>) -> Result<T, ShellError> {
+        f(self)
+    }
+}
+
+#[derive(Subcommand)]
+pub enum VfsWatchCommands {
+    /// Print changes below a path as they happen, until interrupted
+    Watch(WatchArgs),
+}
+
+#[derive(Args)]
+pub struct WatchArgs {
+    /// Stop after COUNT changes
+    #[arg(short = 'c', long = "count", value_name = "COUNT")]
+    count: Option<usize>,
+
+    /// Stop after SECS seconds
+    #[arg(short = 't', long = "timeout", value_name = "SECS")]
+    timeout: Option<u64>,
+
+    /// Directory or file to watch
+    #[arg(default_value = ".", value_hint = ValueHint::AnyPath)]
+    path: PathBuf,
+}
+
+pub fn handle_vfs_watch_command(sh: &BasicShell, cmd: VfsWatchCommands) -> HandlerResult {
+    let VfsWatchCommands::Watch(args) = cmd;
+    let interrupt = Interrupt::catch();
+    watch(
+        sh,
+        &args,
+        &|| interrupt.raised(),
+        &mut std::io::stdout().lock(),
+    )
+}
+
+/// Print the changes below `args.path` in the format of `diff`, until the
+/// path itself is removed, a limit of `args` is reached or `stop` returns
+/// true. The VFS is only locked while looking for changes.
+fn watch(
+    vfs: &impl SharedVfs,
+    args: &WatchArgs,
+    stop: &dyn Fn() -> bool,
+    out: &mut dyn Write,
+) -> HandlerResult {
+    let (path, mut watcher) = vfs.step(|vfs| {
+        let path = vfs.resolve(&args.path);
+        let watcher = vfs.watch(&path)?;
+        Ok((path, watcher))
+    })?;
+    let deadline = args
+        .timeout
+        .and_then(|secs| Instant::now().checked_add(Duration::from_secs(secs)));
+    let mut left = args.count.unwrap_or(usize::MAX);
+    loop {
+        let changes = vfs.step(|vfs| Ok(watcher.wait(vfs, Duration::ZERO)?))?;
+        for change in changes.into_iter().take(left) {
+            writeln!(out, "{}", format_change(&change))?;
+            left -= 1;
+            if change.path == path && change.kind == VfsChangeKind::Removed {
+                left = 0;
+            }
+        }
+        out.flush()?;
+        let wait = deadline.map_or(WAIT_TICK, |deadline| {
+            deadline
+                .saturating_duration_since(Instant::now())
+                .min(WAIT_TICK)
+        });
+        if left == 0 || wait.is_zero() || stop() {
+            break;
+        }
+        std::thread::sleep(wait);
+    }
+    HANDLER_SUCCESS
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::shell::tests::{build_basic_shell, parse_cmd, QuickPoll};
+    use crate::vfs::{HostVfs, MemVfs, VfsError};
+    use crate::ShellConfig;
+    use std::path::Path;
+    use std::process::ExitCode;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    fn exec(vfs: &impl SharedVfs, line: &str) -> (HandlerResult, String) {
+        exec_until(vfs, line, &|| false)
+    }
+
+    fn exec_until(
+        vfs: &impl SharedVfs,
+        line: &str,
+        stop: &dyn Fn() -> bool,
+    ) -> (HandlerResult, String) {
+        let VfsWatchCommands::Watch(args) = parse_cmd(line);
+        let mut out = Vec::new();
+        let result = watch(vfs, &args, stop, &mut out);
+        (result, String::from_utf8(out).expect("utf-8"))
+    }
+
+    /// Run `f` on the host directory `dir` shortly after `line` started.
+    fn exec_while(
+        vfs: &impl SharedVfs,
+        dir: &Path,
+        line: &str,
+        f: impl FnOnce(&Path) + Send,
+    ) -> (HandlerResult, String) {
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                std::thread::sleep(Duration::from_millis(50));
+                f(dir);
+            });
+            exec(vfs, line)
+        })
+    }
+
+    #[test]
+    fn streams_changes_until_the_count_is_reached() {
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        std::fs::create_dir(dir.path().join("in")).expect("mkdir");
+        let vfs = QuickPoll(HostVfs::new(dir.path()).expect("host vfs"));
+        let (result, out) = exec_while(&vfs, dir.path(), "watch -c 3 in", |dir| {
+            // Single steps, so polling never sees half of one
+            std::fs::write(dir.join("a.tmp"), "x\n").expect("write");
+            std::fs::rename(dir.join("a.tmp"), dir.join("in/a.csv")).expect("mv");
+            std::thread::sleep(Duration::from_millis(50));
+            std::fs::OpenOptions::new()
+                .append(true)
+                .open(dir.join("in/a.csv"))
+                .and_then(|mut file| file.write_all(b"y\n"))
+                .expect("append");
+            std::thread::sleep(Duration::from_millis(50));
+            std::fs::remove_file(dir.join("in/a.csv")).expect("rm");
+            std::fs::create_dir(dir.join("in/done")).expect("mkdir");
+        });
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+        assert_eq!(out, "A /in/a.csv\nM /in/a.csv\nD /in/a.csv\n");
+    }
+
+    #[test]
+    fn stops_when_the_watched_path_is_removed() {
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        std::fs::write(dir.path().join("app.log"), "").expect("write");
+        let vfs = QuickPoll(HostVfs::new(dir.path()).expect("host vfs"));
+        let (result, out) = exec_while(&vfs, dir.path(), "watch app.log", |dir| {
+            std::fs::remove_file(dir.join("app.log")).expect("rm");
+        });
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+        assert_eq!(out, "D /app.log\n");
+    }
+
+    #[test]
+    fn stops_at_the_timeout() {
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        let vfs = QuickPoll(HostVfs::new(dir.path()).expect("host vfs"));
+        let start = Instant::now();
+        let (result, out) = exec(&vfs, "watch -t 0");
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+        assert_eq!(out, "");
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn stops_when_interrupted() {
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        let vfs = QuickPoll(HostVfs::new(dir.path()).expect("host vfs"));
+        let interrupted = AtomicBool::new(false);
+        let (result, out) = std::thread::scope(|s| {
+            s.spawn(|| {
+                std::thread::sleep(Duration::from_millis(50));
+                interrupted.store(true, Ordering::SeqCst);
+            });
+            exec_until(&vfs, "watch", &|| interrupted.load(Ordering::SeqCst))
+        });
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+        assert_eq!(out, "");
+    }
+
+    #[test]
+    fn leaves_the_vfs_to_others_while_waiting() {
+        let lookup = Arc::new(|_: &clap::ArgMatches| {
+            Ok(Box::new(MemVfs::new().with_file("/in/a", "")) as Box<dyn Vfs>)
+        });
+        let sh = build_basic_shell(
+            ShellConfig::new("watchsh", "test-pkg", "0.0.1")
+                .no_init_tracing()
+                .vfs_lookup(lookup),
+        );
+        let (result, out) = std::thread::scope(|s| {
+            s.spawn(|| {
+                std::thread::sleep(Duration::from_millis(50));
+                sh.with_vfs(|vfs| Ok(vfs.create_dir(Path::new("/in/new"))?))
+                    .expect("mkdir");
+            });
+            exec(&*sh, "watch -c 1 -t 10 /in")
+        });
+        assert_eq!(result.ok(), Some(ExitCode::SUCCESS));
+        assert_eq!(out, "A /in/new/\n");
+    }
+
+    #[test]
+    fn missing_paths_fail() {
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        let vfs = QuickPoll(HostVfs::new(dir.path()).expect("host vfs"));
+        let (result, _) = exec(&vfs, "watch nope");
+        assert!(matches!(
+            result,
+            Err(ShellError::Vfs(VfsError::NotFound(path))) if path == Path::new("/nope")
+        ));
+    }
+}
@@ -8,6 +8,8 @@ use tracing_subscriber::{
     registry::Registry,
 };
 
+use std::ffi::OsString;
+use std::path::PathBuf;
 use std::sync::OnceLock;
 
 use crate::ShellError;
@@ -128,6 +130,47 @@ pub fn make_env_ident<T: AsRef<str>>(input: T) -> String {
     result
 }
 
+/// Return the default location of the interactive history file for the shell
+/// called `name`.
+///
+/// The file lives in the XDG data directory (`$XDG_DATA_HOME`, falling back to
+/// `$HOME/.local/share`, or `%APPDATA%` on Windows) under `esh/`, and is named
+/// after [`make_env_ident`] of `name`, lowercased, e.g. `my-app` becomes
+/// `esh/my_app_history`. Returns `None` if no data directory can be determined.
+#[must_use]
+pub fn get_history_path<T: AsRef<str>>(name: T) -> Option<PathBuf> {
+    history_path_from(
+        name.as_ref(),
+        std::env::var_os("XDG_DATA_HOME"),
+        std::env::var_os("HOME"),
+        std::env::var_os("APPDATA"),
+    )
+}
+
+fn history_path_from(
+    name: &str,
+    xdg_data_home: Option<OsString>,
+    home: Option<OsString>,
+    appdata: Option<OsString>,
+) -> Option<PathBuf> {
+    let non_empty = |v: Option<OsString>| v.filter(|v| !v.is_empty()).map(PathBuf::from);
+
+    // Relative XDG paths are invalid per the spec and must be ignored
+    let data_dir = non_empty(xdg_data_home)
+        .filter(|p| p.is_absolute())
+        .or_else(|| non_empty(home).map(|h| h.join(".local").join("share")))
+        .or_else(|| non_empty(appdata))?;
+
+    let ident = make_env_ident(name).to_lowercase();
+    let ident = if ident.is_empty() {
+        env!("CARGO_PKG_NAME").to_string()
+    } else {
+        ident
+    };
+
+    Some(data_dir.join("esh").join(format!("{ident}_history")))
+}
+
 /// Initialise the global tracing/logging subscriber.
 ///
 /// Sets up a compact stderr logger and installs a panic hook that logs panics.  When the
@@ -355,6 +398,65 @@ mod tests {
         assert_eq!(make_env_ident(&s), "HELLO");
     }
 
+    // -- get_history_path ----------------------------------------------------
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn os(s: &str) -> Option<OsString> {
+        Some(OsString::from(s))
+    }
+
+    #[test]
+    fn history_path_prefers_xdg_data_home() {
+        let p = history_path_from("my-app", os("/xdg"), os("/home/u"), None);
+        assert_eq!(p, Some(PathBuf::from("/xdg/esh/my_app_history")));
+    }
+
+    #[test]
+    fn history_path_falls_back_to_home() {
+        let p = history_path_from("MyApp", None, os("/home/u"), None);
+        assert_eq!(
+            p,
+            Some(PathBuf::from("/home/u/.local/share/esh/myapp_history"))
+        );
+    }
+
+    #[test]
+    fn history_path_ignores_empty_and_relative_xdg() {
+        let p = history_path_from("app", os(""), os("/home/u"), None);
+        assert_eq!(
+            p,
+            Some(PathBuf::from("/home/u/.local/share/esh/app_history"))
+        );
+        let p = history_path_from("app", os("relative"), os("/home/u"), None);
+        assert_eq!(
+            p,
+            Some(PathBuf::from("/home/u/.local/share/esh/app_history"))
+        );
+    }
+
+    #[test]
+    fn history_path_falls_back_to_appdata() {
+        let p = history_path_from("app", None, None, os("/appdata"));
+        assert_eq!(p, Some(PathBuf::from("/appdata/esh/app_history")));
+    }
+
+    #[test]
+    fn history_path_none_without_any_dir() {
+        assert_eq!(history_path_from("app", None, None, None), None);
+    }
+
+    #[test]
+    fn history_path_unusable_name_uses_package_name() {
+        let p = history_path_from("---", os("/xdg"), None, None);
+        assert_eq!(
+            p,
+            Some(PathBuf::from(format!(
+                "/xdg/esh/{}_history",
+                env!("CARGO_PKG_NAME")
+            )))
+        );
+    }
+
     // -- init_tracing level selection --------------------------------------
     //
     // init_tracing sets a global subscriber, so it can only succeed once per
@@ -0,0 +1,693 @@
+use std::ffi::OsString;
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+use thiserror::Error;
+
+#[cfg(any(feature = "tar", feature = "zip"))]
+mod archive;
+mod host;
+mod mem;
+mod mount;
+mod overlay;
+mod watch;
+
+#[cfg(any(feature = "tar", feature = "zip"))]
+pub use archive::ArchiveVfs;
+pub use host::HostVfs;
+pub use mem::{MemSnapshot, MemVfs};
+pub use mount::MountTable;
+pub use overlay::OverlayVfs;
+pub use watch::{PollWatcher, VfsWatcher};
+
+/// Errors returned by [`Vfs`] operations.
+///
+/// Every variant carries the VFS path the operation failed on, so the
+/// message can be shown to the user as is.
+#[derive(Error, Debug)]
+pub enum VfsError {
+    /// The path does not exist
+    #[error("{}: no such file or directory", .0.display())]
+    NotFound(PathBuf),
+
+    /// A directory was expected
+    #[error("{}: not a directory", .0.display())]
+    NotADirectory(PathBuf),
+
+    /// Something other than a directory was expected
+    #[error("{}: is a directory", .0.display())]
+    IsADirectory(PathBuf),
+
+    /// The path exists already
+    #[error("{}: already exists", .0.display())]
+    AlreadyExists(PathBuf),
+
+    /// A directory to be removed is not empty
+    #[error("{}: directory not empty", .0.display())]
+    NotEmpty(PathBuf),
+
+    /// The backend refused access to the path
+    #[error("{}: permission denied", .0.display())]
+    PermissionDenied(PathBuf),
+
+    /// The backend is read-only
+    #[error("{}: read-only filesystem", .0.display())]
+    ReadOnly(PathBuf),
+
+    /// A rename would move the entry to another backend
+    #[error("{}: invalid cross-device link", .0.display())]
+    CrossesDevices(PathBuf),
+
+    /// The backend does not implement the operation
+    #[error("{}: {op} not supported", path.display())]
+    Unsupported {
+        /// The operation that was attempted, e.g. `"read_dir"`
+        op: &'static str,
+        /// The path the operation was attempted on
+        path: PathBuf,
+    },
+
+    /// Any other I/O error of the backend
+    #[error("{}: {source}", path.display())]
+    Io {
+        /// The path the operation failed on
+        path: PathBuf,
+        /// The underlying error
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl VfsError {
+    /// Classify a host I/O error for the VFS path `path`.
+    ///
+    /// Well-known [`ErrorKind`]s map to the matching variant, everything else
+    /// ends up in [`VfsError::Io`].
+    #[must_use]
+    pub fn from_io(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
+        let path = path.into();
+        match source.kind() {
+            ErrorKind::NotFound => Self::NotFound(path),
+            ErrorKind::NotADirectory => Self::NotADirectory(path),
+            ErrorKind::IsADirectory => Self::IsADirectory(path),
+            ErrorKind::AlreadyExists => Self::AlreadyExists(path),
+            ErrorKind::DirectoryNotEmpty => Self::NotEmpty(path),
+            ErrorKind::PermissionDenied => Self::PermissionDenied(path),
+            ErrorKind::ReadOnlyFilesystem => Self::ReadOnly(path),
+            ErrorKind::CrossesDevices => Self::CrossesDevices(path),
+            _ => Self::Io { path, source },
+        }
+    }
+
+    /// Shorthand for [`VfsError::Unsupported`].
+    #[must_use]
+    pub fn unsupported(op: &'static str, path: impl Into<PathBuf>) -> Self {
+        Self::Unsupported {
+            op,
+            path: path.into(),
+        }
+    }
+}
+
+/// Result type returned by [`Vfs`] operations.
+pub type VfsResult<T> = Result<T, VfsError>;
+
+/// The kind of a VFS entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VfsFileType {
+    /// A regular file
+    File,
+    /// A directory
+    Dir,
+    /// A symbolic link (not followed)
+    Symlink,
+    /// Anything else, e.g. a device or socket
+    Other,
+}
+
+impl From<std::fs::FileType> for VfsFileType {
+    fn from(file_type: std::fs::FileType) -> Self {
+        if file_type.is_symlink() {
+            Self::Symlink
+        } else if file_type.is_dir() {
+            Self::Dir
+        } else if file_type.is_file() {
+            Self::File
+        } else {
+            Self::Other
+        }
+    }
+}
+
+/// One entry of a directory listing, see [`Vfs::read_dir`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VfsDirEntry {
+    /// The file name of the entry, without any directory components.
+    pub name: OsString,
+    /// The kind of the entry.
+    pub file_type: VfsFileType,
+}
+
+/// Metadata of a VFS entry, see [`Vfs::metadata`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VfsMetadata {
+    /// The kind of the entry.
+    pub file_type: VfsFileType,
+    /// Size in bytes; for directories the backend's notion of a size.
+    pub len: u64,
+    /// Last modification time, if the backend tracks it.
+    pub modified: Option<SystemTime>,
+    /// Whether the entry is read-only.
+    pub readonly: bool,
+    /// Unix permission bits (e.g. `0o644`), if the backend has them.
+    pub mode: Option<u32>,
+}
+
+impl VfsMetadata {
+    /// Return `true` if the entry is a directory.
+    #[must_use]
+    pub fn is_dir(&self) -> bool {
+        self.file_type == VfsFileType::Dir
+    }
+
+    /// Return `true` if the entry is a regular file.
+    #[must_use]
+    pub fn is_file(&self) -> bool {
+        self.file_type == VfsFileType::File
+    }
+}
+
+impl From<std::fs::Metadata> for VfsMetadata {
+    fn from(meta: std::fs::Metadata) -> Self {
+        #[cfg(unix)]
+        let mode = Some(std::os::unix::fs::PermissionsExt::mode(&meta.permissions()) & 0o7777);
+        #[cfg(not(unix))]
+        let mode = None;
+
+        Self {
+            file_type: meta.file_type().into(),
+            len: meta.len(),
+            modified: meta.modified().ok(),
+            readonly: meta.permissions().readonly(),
+            mode,
+        }
+    }
+}
+
+/// How [`Vfs::open_write`] treats an existing file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VfsWriteMode {
+    /// Create the file, or truncate it if it exists
+    Truncate,
+    /// Create the file, or append to it if it exists
+    Append,
+    /// Create the file, fail with [`VfsError::AlreadyExists`] if it exists
+    CreateNew,
+}
+
+/// What happened to a path, see [`VfsChange`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VfsChangeKind {
+    /// The path did not exist before
+    Added,
+    /// The file was written to, or the entry changed its kind
+    Modified,
+    /// The path no longer exists
+    Removed,
+}
+
+/// A change to a path: pending in a layered backend, see [`Vfs::changes`],
+/// or just seen by a [`VfsWatcher`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VfsChange {
+    /// What happened to the path.
+    pub kind: VfsChangeKind,
+    /// The absolute VFS path that changed.
+    pub path: PathBuf,
+    /// The kind of the entry now, or before it was removed.
+    pub file_type: VfsFileType,
+}
+
+/// Backend-agnostic VFS interface for the shell.
+///
+/// Implement this trait to plug in any filesystem backend. Paths passed to
+/// the methods are VFS paths: absolute paths start at the VFS root, relative
+/// paths are relative to [`cwd`](Vfs::cwd). Use [`resolve`](Vfs::resolve) to
+/// turn them into absolute, normalized paths.
+///
+/// Only [`cwd`](Vfs::cwd) is required. Every other operation defaults to
+/// [`VfsError::Unsupported`], so a backend can start small and grow.
+///
+/// The trait requires `Send` but not `Sync`: the shell keeps its VFS behind a
+/// `Mutex`, which provides the synchronization.
+pub trait Vfs: Send {
+    /// Return the current working directory of this filesystem.
+    fn cwd(&self) -> &Path;
+
+    /// Set the current working directory to `path`.
+    ///
+    /// `path` is always absolute and normalized, and has been checked to be a
+    /// directory. Use [`cd`](Vfs::cd) to change directory from user input.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`VfsError::Unsupported`] unless implemented.
+    fn set_cwd(&mut self, path: &Path) -> VfsResult<()> {
+        Err(VfsError::unsupported("set_cwd", path))
+    }
+
+    /// List the entries of the directory at `path`, in no particular order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `path` does not exist, is not a directory, or
+    /// cannot be read.
+    fn read_dir(&self, path: &Path) -> VfsResult<Vec<VfsDirEntry>> {
+        Err(VfsError::unsupported("read_dir", path))
+    }
+
+    /// Return the metadata of the entry at `path`, following symlinks.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`VfsError::NotFound`] if `path` does not exist, or another
+    /// error if it cannot be accessed.
+    fn metadata(&self, path: &Path) -> VfsResult<VfsMetadata> {
+        Err(VfsError::unsupported("metadata", path))
+    }
+
+    /// Open the file at `path` for reading.
+    ///
+    /// The stream does not borrow the VFS, so callers can read it in chunks
+    /// without buffering whole files in memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`VfsError::NotFound`] if `path` does not exist,
+    /// [`VfsError::IsADirectory`] if it is a directory, or another error if
+    /// it cannot be opened.
+    fn open_read(&self, path: &Path) -> VfsResult<Box<dyn Read + Send>> {
+        Err(VfsError::unsupported("open_read", path))
+    }
+
+    /// Open the file at `path` for writing, creating it if needed, see
+    /// [`VfsWriteMode`] for existing files. The parent directory must exist.
+    ///
+    /// Like with [`open_read`](Vfs::open_read), the stream does not borrow
+    /// the VFS. Data may be buffered until the stream is flushed or dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`VfsError::NotFound`] if the parent directory does not exist,
+    /// [`VfsError::IsADirectory`] if `path` is a directory,
+    /// [`VfsError::AlreadyExists`] for [`VfsWriteMode::CreateNew`], or
+    /// another error if the file cannot be opened.
+    fn open_write(&mut self, path: &Path, _mode: VfsWriteMode) -> VfsResult<Box<dyn Write + Send>> {
+        Err(VfsError::unsupported("open_write", path))
+    }
+
+    /// Create the directory `path`. The parent directory must exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`VfsError::AlreadyExists`] if `path` exists,
+    /// [`VfsError::NotFound`] if the parent does not exist, or another error
+    /// if the directory cannot be created.
+    fn create_dir(&mut self, path: &Path) -> VfsResult<()> {
+        Err(VfsError::unsupported("create_dir", path))
+    }
+
+    /// Create the directory `path` and all missing parents. Existing
+    /// directories are not an error.
+    ///
+    /// The default implementation calls [`create_dir`](Vfs::create_dir) for
+    /// each missing component.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`VfsError::NotADirectory`] if a component exists but is not a
+    /// directory, or any error of [`create_dir`](Vfs::create_dir).
+    fn create_dir_all(&mut self, path: &Path) -> VfsResult<()> {
+        let target = self.resolve(path);
+        let mut current = PathBuf::from("/");
+        for component in target.components().skip(1) {
+            current.push(component);
+            match self.metadata(&current) {
+                Ok(meta) if meta.is_dir() => {}
+                Ok(_) => return Err(VfsError::NotADirectory(current)),
+                Err(VfsError::NotFound(_)) => self.create_dir(&current)?,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the file or symlink at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`VfsError::IsADirectory`] if `path` is a directory,
+    /// [`VfsError::NotFound`] if it does not exist, or another error if it
+    /// cannot be removed.
+    fn remove_file(&mut self, path: &Path) -> VfsResult<()> {
+        Err(VfsError::unsupported("remove_file", path))
+    }
+
+    /// Remove the empty directory at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`VfsError::NotEmpty`] if the directory has entries,
+    /// [`VfsError::NotADirectory`] if `path` is not a directory, or another
+    /// error if it cannot be removed.
+    fn remove_dir(&mut self, path: &Path) -> VfsResult<()> {
+        Err(VfsError::unsupported("remove_dir", path))
+    }
+
+    /// Remove the directory at `path` with all its contents. Symlinks are
+    /// removed, not followed.
+    ///
+    /// The default implementation walks the tree with
+    /// [`read_dir`](Vfs::read_dir), [`remove_file`](Vfs::remove_file) and
+    /// [`remove_dir`](Vfs::remove_dir).
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error encountered; entries removed up to that point
+    /// stay removed.
+    fn remove_dir_all(&mut self, path: &Path) -> VfsResult<()> {
+        let dir = self.resolve(path);
+        for entry in self.read_dir(&dir)? {
+            let child = dir.join(&entry.name);
+            if entry.file_type == VfsFileType::Dir {
+                self.remove_dir_all(&child)?;
+            } else {
+                self.remove_file(&child)?;
+            }
+        }
+        self.remove_dir(&dir)
+    }
+
+    /// Rename the file or directory `from` to `to`, replacing `to` if it is
+    /// a file (or an empty directory, where the backend allows it).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`VfsError::NotFound`] if `from` or the parent of `to` does
+    /// not exist, or another error if the entry cannot be renamed.
+    fn rename(&mut self, from: &Path, _to: &Path) -> VfsResult<()> {
+        Err(VfsError::unsupported("rename", from))
+    }
+
+    /// Copy the contents of the file `from` to the file `to`, creating or
+    /// truncating it. Returns the number of bytes copied.
+    ///
+    /// The default implementation streams the data from
+    /// [`open_read`](Vfs::open_read) to [`open_write`](Vfs::open_write).
+    ///
+    /// # Errors
+    ///
+    /// Returns any error of opening either file, or [`VfsError::Io`] if the
+    /// data cannot be copied.
+    fn copy(&mut self, from: &Path, to: &Path) -> VfsResult<u64> {
+        let mut reader = self.open_read(from)?;
+        let mut writer = self.open_write(to, VfsWriteMode::Truncate)?;
+        let io_err = |source| VfsError::Io {
+            path: self.resolve(to),
+            source,
+        };
+        let copied = std::io::copy(&mut reader, &mut writer).map_err(io_err)?;
+        writer.flush().map_err(io_err)?;
+        Ok(copied)
+    }
+
+    /// Set the modification time of the entry at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`VfsError::NotFound`] if `path` does not exist, or another
+    /// error if the time cannot be set.
+    fn set_modified(&mut self, path: &Path, _time: SystemTime) -> VfsResult<()> {
+        Err(VfsError::unsupported("set_modified", path))
+    }
+
+    /// List the changes a layered backend has not written through yet, in
+    /// path order with parents before their children.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`VfsError::Unsupported`] unless implemented, or any error of
+    /// reading the layers.
+    fn changes(&self) -> VfsResult<Vec<VfsChange>> {
+        Err(VfsError::unsupported("changes", "/"))
+    }
+
+    /// Write the pending [`changes`](Vfs::changes) through and return how
+    /// many there were.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`VfsError::Unsupported`] unless implemented, or the first
+    /// error of applying a change; changes applied up to that point stay.
+    fn commit(&mut self) -> VfsResult<usize> {
+        Err(VfsError::unsupported("commit", "/"))
+    }
+
+    /// Drop the pending [`changes`](Vfs::changes).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`VfsError::Unsupported`] unless implemented, or any error of
+    /// clearing the changes.
+    fn discard(&mut self) -> VfsResult<()> {
+        Err(VfsError::unsupported("discard", "/"))
+    }
+
+    /// Watch `path`, and everything below it if it is a directory, for
+    /// changes made from outside the VFS, e.g. by other processes.
+    ///
+    /// The default implementation polls with a [`PollWatcher`], which works
+    /// for every backend with [`metadata`](Vfs::metadata) and
+    /// [`read_dir`](Vfs::read_dir). Backends that hear about changes from
+    /// elsewhere override it.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error of setting up the watcher, e.g. of reading the
+    /// entries below `path`.
+    fn watch(&self, path: &Path) -> VfsResult<Box<dyn VfsWatcher>> {
+        Ok(Box::new(PollWatcher::new(self, path)?))
+    }
+
+    /// Return the backend mounted at `point`, for tables of backends like
+    /// [`MountTable`]. Their watchers use it to reach the backend they watch
+    /// through the table, see [`VfsWatcher::wait`].
+    ///
+    /// The default implementation has no backends and returns `None`.
+    fn mounted(&self, _point: &Path) -> Option<&dyn Vfs> {
+        None
+    }
+
+    /// Return whether an entry exists at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if existence cannot be determined, e.g. because of
+    /// missing permissions.
+    fn exists(&self, path: &Path) -> VfsResult<bool> {
+        match self.metadata(path) {
+            Ok(_) => Ok(true),
+            Err(VfsError::NotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Resolve `path` against the current working directory, removing `.`
+    /// and `..` components lexically. `..` never leaves the root.
+    fn resolve(&self, path: &Path) -> PathBuf {
+        normalize(self.cwd(), path)
+    }
+
+    /// Change the current working directory to `path`, which may be relative
+    /// and contain `..`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`VfsError::NotFound`] or [`VfsError::NotADirectory`] if
+    /// `path` is not an existing directory, or any error of
+    /// [`set_cwd`](Vfs::set_cwd).
+    fn cd(&mut self, path: &Path) -> VfsResult<()> {
+        let target = self.resolve(path);
+        if !self.metadata(&target)?.is_dir() {
+            return Err(VfsError::NotADirectory(target));
+        }
+        self.set_cwd(&target)
+    }
+}
+
+/// Lexically resolve `path` against the absolute path `base`, removing `.`
+/// and `..`.
+pub fn normalize(base: &Path, path: &Path) -> PathBuf {
+    let mut out = PathBuf::from("/");
+    for component in base.join(path).components() {
+        match component {
+            Component::ParentDir => {
+                out.pop();
+            }
+            Component::Normal(c) => out.push(c),
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    /// Two directories and a file, only `metadata` and `set_cwd` implemented.
+    struct TinyFs {
+        cwd: PathBuf,
+    }
+
+    impl Vfs for TinyFs {
+        fn cwd(&self) -> &Path {
+            &self.cwd
+        }
+
+        fn set_cwd(&mut self, path: &Path) -> VfsResult<()> {
+            self.cwd = path.to_path_buf();
+            Ok(())
+        }
+
+        fn metadata(&self, path: &Path) -> VfsResult<VfsMetadata> {
+            let file_type = match path.to_str() {
+                Some("/" | "/docs") => VfsFileType::Dir,
+                Some("/docs/a.txt") => VfsFileType::File,
+                Some("/secret") => return Err(VfsError::PermissionDenied(path.into())),
+                _ => return Err(VfsError::NotFound(path.into())),
+            };
+            Ok(VfsMetadata {
+                file_type,
+                len: 0,
+                modified: None,
+                readonly: false,
+                mode: None,
+            })
+        }
+    }
+
+    fn tiny() -> TinyFs {
+        TinyFs {
+            cwd: PathBuf::from("/"),
+        }
+    }
+
+    #[test]
+    fn normalize_resolves_dots() {
+        assert_eq!(
+            normalize(Path::new("/a/b"), Path::new("../c/./d")),
+            PathBuf::from("/a/c/d")
+        );
+        assert_eq!(
+            normalize(Path::new("/a"), Path::new("../../..")),
+            PathBuf::from("/")
+        );
+        assert_eq!(
+            normalize(Path::new("/a"), Path::new("/x/")),
+            PathBuf::from("/x")
+        );
+        assert_eq!(
+            normalize(Path::new("/a"), Path::new("")),
+            PathBuf::from("/a")
+        );
+    }
+
+    #[test]
+    fn cd_resolves_relative_paths() {
+        let mut fs = tiny();
+        fs.cd(Path::new("docs")).expect("cd docs");
+        assert_eq!(fs.cwd(), Path::new("/docs"));
+        fs.cd(Path::new("..")).expect("cd ..");
+        assert_eq!(fs.cwd(), Path::new("/"));
+        fs.cd(Path::new("./docs/../docs/.")).expect("cd with dots");
+        assert_eq!(fs.cwd(), Path::new("/docs"));
+    }
+
+    #[test]
+    fn cd_rejects_files_and_missing_paths() {
+        let mut fs = tiny();
+        match fs.cd(Path::new("/docs/a.txt")) {
+            Err(VfsError::NotADirectory(p)) => assert_eq!(p, Path::new("/docs/a.txt")),
+            other => panic!("expected NotADirectory, got {other:?}"),
+        }
+        match fs.cd(Path::new("nope")) {
+            Err(VfsError::NotFound(p)) => assert_eq!(p, Path::new("/nope")),
+            other => panic!("expected NotFound, got {other:?}"),
+        }
+        assert_eq!(fs.cwd(), Path::new("/"));
+    }
+
+    #[test]
+    fn exists_maps_not_found_to_false() {
+        let fs = tiny();
+        assert!(fs.exists(Path::new("/docs/a.txt")).expect("exists"));
+        assert!(!fs.exists(Path::new("/nope")).expect("exists"));
+        assert!(matches!(
+            fs.exists(Path::new("/secret")),
+            Err(VfsError::PermissionDenied(_))
+        ));
+    }
+
+    #[test]
+    fn unimplemented_operations_are_unsupported() {
+        let fs = tiny();
+        let err = fs.read_dir(Path::new("/")).expect_err("read_dir");
+        assert!(matches!(err, VfsError::Unsupported { op: "read_dir", .. }));
+        assert_eq!(err.to_string(), "/: read_dir not supported");
+    }
+
+    #[test]
+    fn from_io_classifies_error_kinds() {
+        let e = |kind| VfsError::from_io("/x", std::io::Error::from(kind));
+        assert!(matches!(e(ErrorKind::NotFound), VfsError::NotFound(_)));
+        assert!(matches!(
+            e(ErrorKind::PermissionDenied),
+            VfsError::PermissionDenied(_)
+        ));
+        assert!(matches!(
+            e(ErrorKind::AlreadyExists),
+            VfsError::AlreadyExists(_)
+        ));
+        assert!(matches!(
+            e(ErrorKind::DirectoryNotEmpty),
+            VfsError::NotEmpty(_)
+        ));
+        assert!(matches!(
+            e(ErrorKind::CrossesDevices),
+            VfsError::CrossesDevices(_)
+        ));
+        assert!(matches!(e(ErrorKind::Interrupted), VfsError::Io { .. }));
+        assert_eq!(
+            e(ErrorKind::NotFound).to_string(),
+            "/x: no such file or directory"
+        );
+    }
+
+    #[test]
+    fn metadata_from_host() {
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        let file = dir.path().join("f");
+        std::fs::write(&file, b"hello").expect("write");
+
+        let meta = VfsMetadata::from(std::fs::metadata(&file).expect("metadata"));
+        assert!(meta.is_file());
+        assert_eq!(meta.len, 5);
+        assert!(meta.modified.is_some());
+
+        let meta = VfsMetadata::from(std::fs::metadata(dir.path()).expect("metadata"));
+        assert!(meta.is_dir());
+    }
+}
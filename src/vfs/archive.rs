@@ -0,0 +1,659 @@
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use super::{
+    normalize, Vfs, VfsDirEntry, VfsError, VfsFileType, VfsMetadata, VfsResult, VfsWriteMode,
+};
+
+/// How many symlinks a lookup follows before giving up.
+const MAX_SYMLINKS: usize = 40;
+
+#[cfg(feature = "zip")]
+type ZipArchive = zip::ZipArchive<Cursor<Arc<[u8]>>>;
+
+#[derive(Clone, Debug)]
+#[cfg_attr(not(feature = "tar"), allow(dead_code))]
+enum Content {
+    Dir,
+    /// Unpacked when the archive was read
+    File(Arc<[u8]>),
+    /// Unpacked from the shared archive data on every open
+    #[cfg(feature = "zip")]
+    Zipped(ZipArchive, usize),
+    /// The link target as stored in the archive
+    Symlink(PathBuf),
+    /// Devices, fifos and other entries that have no contents
+    Other,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    content: Content,
+    len: u64,
+    modified: Option<SystemTime>,
+    mode: Option<u32>,
+}
+
+impl Entry {
+    const fn dir() -> Self {
+        Self {
+            content: Content::Dir,
+            len: 0,
+            modified: None,
+            mode: None,
+        }
+    }
+
+    const fn file_type(&self) -> VfsFileType {
+        match self.content {
+            Content::Dir => VfsFileType::Dir,
+            #[cfg(feature = "zip")]
+            Content::Zipped(..) => VfsFileType::File,
+            Content::File(_) => VfsFileType::File,
+            Content::Symlink(_) => VfsFileType::Symlink,
+            Content::Other => VfsFileType::Other,
+        }
+    }
+}
+
+/// A read-only [`Vfs`] over the contents of a tar or zip archive.
+///
+/// The archive is indexed once when it is opened; nothing is extracted to
+/// disk. Tar entries are unpacked into memory right away, since compressed
+/// tarballs cannot be read out of order. Zip entries are unpacked whenever
+/// they are opened. Every operation that would modify the tree fails with
+/// [`VfsError::ReadOnly`].
+///
+/// Entry names are resolved like VFS paths, so `..` and absolute names stay
+/// inside the archive. Symlinks are followed within the archive as well.
+///
+/// Tar support needs the `tar` feature and also reads gzip-compressed
+/// tarballs; zip support needs the `zip` feature.
+///
+/// ```
+/// # #[cfg(feature = "tar")]
+/// # fn main() -> std::io::Result<()> {
+/// use std::path::Path;
+/// use esh::{ArchiveVfs, Vfs, VfsError};
+///
+/// let mut builder = tar::Builder::new(Vec::new());
+/// let mut header = tar::Header::new_gnu();
+/// header.set_size(6);
+/// builder.append_data(&mut header, "etc/motd", &b"hello\n"[..])?;
+///
+/// let mut vfs = ArchiveVfs::from_tar(builder.into_inner()?.as_slice())?;
+/// assert!(vfs.metadata(Path::new("/etc/motd")).unwrap().readonly);
+/// assert!(matches!(
+///     vfs.remove_file(Path::new("/etc/motd")),
+///     Err(VfsError::ReadOnly(_))
+/// ));
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "tar"))]
+/// # fn main() {}
+/// ```
+#[derive(Debug)]
+pub struct ArchiveVfs {
+    cwd: PathBuf,
+    entries: BTreeMap<PathBuf, Entry>,
+}
+
+impl ArchiveVfs {
+    fn empty() -> Self {
+        Self {
+            cwd: PathBuf::from("/"),
+            entries: BTreeMap::from([(PathBuf::from("/"), Entry::dir())]),
+        }
+    }
+
+    /// Open the archive at `path` in `vfs`, telling tar from zip by its
+    /// contents.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error of reading the file, or [`VfsError::Io`] if it is
+    /// not an archive this build supports.
+    pub fn open(vfs: &dyn Vfs, path: &Path) -> VfsResult<Self> {
+        let path = vfs.resolve(path);
+        let mut data = Vec::new();
+        vfs.open_read(&path)?
+            .read_to_end(&mut data)
+            .map_err(|e| VfsError::from_io(&path, e))?;
+        Self::from_bytes(data).map_err(|e| VfsError::from_io(path, e))
+    }
+
+    /// Open the archive at the host path `path`, telling tar from zip by its
+    /// contents.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error of reading the file, or [`VfsError::Io`] if it is
+    /// not an archive this build supports.
+    pub fn open_host(path: impl AsRef<Path>) -> VfsResult<Self> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|e| VfsError::from_io(path, e))?;
+        Self::from_bytes(data).map_err(|e| VfsError::from_io(path, e))
+    }
+
+    /// Read a tar or zip archive from memory, telling them apart by their
+    /// contents.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`std::io::ErrorKind::InvalidData`] or another I/O error if the data
+    /// is not an archive this build supports.
+    pub fn from_bytes(data: Vec<u8>) -> std::io::Result<Self> {
+        #[cfg(feature = "zip")]
+        if data.starts_with(b"PK\x03\x04") || data.starts_with(b"PK\x05\x06") {
+            return Self::from_zip_data(data.into());
+        }
+        #[cfg(feature = "tar")]
+        let archive = Self::from_tar(Cursor::new(data));
+        #[cfg(not(feature = "tar"))]
+        let archive = Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "not a zip archive",
+        ));
+        archive
+    }
+
+    /// Read a tar archive, gzip-compressed or not, from `reader`.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error of reading or unpacking the archive.
+    #[cfg(feature = "tar")]
+    pub fn from_tar(mut reader: impl Read) -> std::io::Result<Self> {
+        let mut magic = Vec::with_capacity(2);
+        reader.by_ref().take(2).read_to_end(&mut magic)?;
+        let gzipped = magic == [0x1f, 0x8b];
+        let reader = Cursor::new(magic).chain(reader);
+        let mut vfs = Self::empty();
+        if gzipped {
+            vfs.load_tar(flate2::read::GzDecoder::new(reader))?;
+        } else {
+            vfs.load_tar(reader)?;
+        }
+        Ok(vfs)
+    }
+
+    /// Read a zip archive from `reader`. The whole archive is kept in
+    /// memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error of reading the archive or its central directory.
+    #[cfg(feature = "zip")]
+    pub fn from_zip(mut reader: impl Read) -> std::io::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Self::from_zip_data(data.into())
+    }
+
+    #[cfg(feature = "tar")]
+    fn load_tar(&mut self, reader: impl Read) -> std::io::Result<()> {
+        use tar::EntryType;
+
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = normalize(Path::new("/"), &entry.path()?);
+            let header = entry.header();
+            let modified = header
+                .mtime()
+                .ok()
+                .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+            let mode = header.mode().ok().map(|mode| mode & 0o7777);
+            let link = entry.link_name()?.map(std::borrow::Cow::into_owned);
+            let (content, len) = match header.entry_type() {
+                EntryType::Directory => (Content::Dir, 0),
+                EntryType::Regular | EntryType::Continuous | EntryType::GNUSparse => {
+                    let mut data = Vec::new();
+                    entry.read_to_end(&mut data)?;
+                    let len = data.len() as u64;
+                    (Content::File(data.into()), len)
+                }
+                EntryType::Symlink => (Content::Symlink(link.unwrap_or_default()), 0),
+                // Hard links share the contents of an earlier entry
+                EntryType::Link => link
+                    .and_then(|link| self.entries.get(&normalize(Path::new("/"), &link)))
+                    .map_or((Content::Other, 0), |target| {
+                        (target.content.clone(), target.len)
+                    }),
+                _ => (Content::Other, 0),
+            };
+            self.insert(
+                path,
+                Entry {
+                    content,
+                    len,
+                    modified,
+                    mode,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "zip")]
+    fn from_zip_data(data: Arc<[u8]>) -> std::io::Result<Self> {
+        let mut archive = ZipArchive::new(Cursor::new(data))?;
+        let mut vfs = Self::empty();
+        for index in 0..archive.len() {
+            let (path, is_dir, is_symlink, len, modified, mode) = {
+                let file = archive.by_index_raw(index)?;
+                (
+                    normalize(Path::new("/"), Path::new(file.name())),
+                    file.is_dir(),
+                    file.is_symlink(),
+                    file.size(),
+                    file.last_modified().and_then(dos_time),
+                    file.unix_mode().map(|mode| mode & 0o7777),
+                )
+            };
+            let (content, len) = if is_dir {
+                (Content::Dir, 0)
+            } else if is_symlink {
+                let mut target = String::new();
+                archive.by_index(index)?.read_to_string(&mut target)?;
+                (Content::Symlink(target.into()), 0)
+            } else {
+                (Content::Zipped(archive.clone(), index), len)
+            };
+            vfs.insert(
+                path,
+                Entry {
+                    content,
+                    len,
+                    modified,
+                    mode,
+                },
+            );
+        }
+        Ok(vfs)
+    }
+
+    /// Add an entry, creating missing parent directories. A later entry for
+    /// the same path replaces the earlier one, as when extracting.
+    fn insert(&mut self, path: PathBuf, entry: Entry) {
+        if path.parent().is_none() && !matches!(entry.content, Content::Dir) {
+            return;
+        }
+        for parent in path.ancestors().skip(1) {
+            match self.entries.get_mut(parent) {
+                Some(entry) if matches!(entry.content, Content::Dir) => break,
+                Some(entry) => *entry = Entry::dir(),
+                None => {
+                    self.entries.insert(parent.to_path_buf(), Entry::dir());
+                }
+            }
+        }
+        self.entries.insert(path, entry);
+    }
+
+    /// Look up the absolute `path`, following symlinks within the archive in
+    /// any of its components.
+    ///
+    /// The path resolved so far never contains symlinks, so resolving `..`
+    /// in link targets lexically gives the same result as extracting would.
+    fn follow(&self, path: &Path) -> VfsResult<(PathBuf, &Entry)> {
+        // The components still to resolve, the next one last
+        let mut rest = normal_components(path);
+        let mut resolved = PathBuf::from("/");
+        let mut entry = self.entry(path, &resolved)?;
+        let mut links = 0;
+        while let Some(name) = rest.pop() {
+            let candidate = resolved.join(name);
+            let next = self.entry(path, &candidate)?;
+            let Content::Symlink(target) = &next.content else {
+                (resolved, entry) = (candidate, next);
+                continue;
+            };
+            links += 1;
+            if links > MAX_SYMLINKS {
+                return Err(VfsError::Io {
+                    path: path.into(),
+                    source: std::io::Error::other("too many levels of symbolic links"),
+                });
+            }
+            rest.extend(normal_components(&normalize(&resolved, target)));
+            resolved = PathBuf::from("/");
+            entry = self.entry(path, &resolved)?;
+        }
+        Ok((resolved, entry))
+    }
+
+    /// The entry at the resolved path `at`, reporting a missing one for the
+    /// looked up `path`.
+    fn entry(&self, path: &Path, at: &Path) -> VfsResult<&Entry> {
+        self.entries
+            .get(at)
+            .ok_or_else(|| VfsError::NotFound(path.into()))
+    }
+}
+
+/// The names in `path`, the first one last.
+fn normal_components(path: &Path) -> Vec<PathBuf> {
+    path.components()
+        .rev()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .map(|c| PathBuf::from(c.as_os_str()))
+        .collect()
+}
+
+/// Convert the local time of a zip entry, taking it as UTC.
+#[cfg(feature = "zip")]
+fn dos_time(time: zip::DateTime) -> Option<SystemTime> {
+    // Days from the civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let month = i64::from(time.month());
+    let year = i64::from(time.year()) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + i64::from(time.day()) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    let secs = days * 86_400
+        + i64::from(time.hour()) * 3_600
+        + i64::from(time.minute()) * 60
+        + i64::from(time.second());
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(u64::try_from(secs).ok()?))
+}
+
+impl Vfs for ArchiveVfs {
+    fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    fn set_cwd(&mut self, path: &Path) -> VfsResult<()> {
+        self.cwd = self.resolve(path);
+        Ok(())
+    }
+
+    fn read_dir(&self, path: &Path) -> VfsResult<Vec<VfsDirEntry>> {
+        let (path, entry) = self.follow(&self.resolve(path))?;
+        if !matches!(entry.content, Content::Dir) {
+            return Err(VfsError::NotADirectory(path));
+        }
+        Ok(self
+            .entries
+            .range(path.clone()..)
+            .skip(1)
+            .take_while(|(p, _)| p.starts_with(&path))
+            .filter(|(p, _)| p.parent() == Some(&path))
+            .filter_map(|(p, entry)| {
+                Some(VfsDirEntry {
+                    name: p.file_name()?.to_os_string(),
+                    file_type: entry.file_type(),
+                })
+            })
+            .collect())
+    }
+
+    fn metadata(&self, path: &Path) -> VfsResult<VfsMetadata> {
+        let (_, entry) = self.follow(&self.resolve(path))?;
+        Ok(VfsMetadata {
+            file_type: entry.file_type(),
+            len: entry.len,
+            modified: entry.modified,
+            readonly: true,
+            mode: entry.mode,
+        })
+    }
+
+    fn open_read(&self, path: &Path) -> VfsResult<Box<dyn Read + Send>> {
+        let (path, entry) = self.follow(&self.resolve(path))?;
+        match &entry.content {
+            Content::File(data) => Ok(Box::new(Cursor::new(Arc::clone(data)))),
+            #[cfg(feature = "zip")]
+            Content::Zipped(archive, index) => {
+                let mut archive = archive.clone();
+                let mut data = Vec::new();
+                archive
+                    .by_index(*index)
+                    .map_err(std::io::Error::from)
+                    .and_then(|mut file| file.read_to_end(&mut data))
+                    .map_err(|e| VfsError::from_io(&path, e))?;
+                Ok(Box::new(Cursor::new(data)))
+            }
+            Content::Dir => Err(VfsError::IsADirectory(path)),
+            Content::Symlink(_) | Content::Other => Err(VfsError::unsupported("open_read", path)),
+        }
+    }
+
+    fn open_write(&mut self, path: &Path, _mode: VfsWriteMode) -> VfsResult<Box<dyn Write + Send>> {
+        Err(VfsError::ReadOnly(self.resolve(path)))
+    }
+
+    fn create_dir(&mut self, path: &Path) -> VfsResult<()> {
+        Err(VfsError::ReadOnly(self.resolve(path)))
+    }
+
+    fn remove_file(&mut self, path: &Path) -> VfsResult<()> {
+        Err(VfsError::ReadOnly(self.resolve(path)))
+    }
+
+    fn remove_dir(&mut self, path: &Path) -> VfsResult<()> {
+        Err(VfsError::ReadOnly(self.resolve(path)))
+    }
+
+    fn rename(&mut self, from: &Path, _to: &Path) -> VfsResult<()> {
+        Err(VfsError::ReadOnly(self.resolve(from)))
+    }
+
+    fn set_modified(&mut self, path: &Path, _time: SystemTime) -> VfsResult<()> {
+        Err(VfsError::ReadOnly(self.resolve(path)))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+
+    fn p(path: &str) -> &Path {
+        Path::new(path)
+    }
+
+    fn read(vfs: &ArchiveVfs, path: &str) -> String {
+        let mut s = String::new();
+        vfs.open_read(p(path))
+            .expect("open")
+            .read_to_string(&mut s)
+            .expect("read");
+        s
+    }
+
+    fn names(vfs: &ArchiveVfs, path: &str) -> Vec<String> {
+        vfs.read_dir(p(path))
+            .expect("read_dir")
+            .into_iter()
+            .map(|e| e.name.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn garbage_is_not_an_archive() {
+        assert!(ArchiveVfs::from_bytes(b"PK\x03\x04junk".to_vec()).is_err());
+        assert!(ArchiveVfs::from_bytes(vec![0xff; 1024]).is_err());
+    }
+
+    #[test]
+    fn writes_are_read_only() {
+        let mut vfs = ArchiveVfs::empty();
+        vfs.cd(p("/")).expect("cd");
+        for result in [
+            vfs.open_write(p("new"), VfsWriteMode::Truncate).map(drop),
+            vfs.create_dir(p("/dir")),
+            vfs.create_dir_all(p("/a/b")),
+            vfs.remove_file(p("/file")),
+            vfs.rename(p("/x"), p("/y")),
+            vfs.set_modified(p("/"), SystemTime::UNIX_EPOCH),
+        ] {
+            match result {
+                Err(VfsError::ReadOnly(_)) => {}
+                other => panic!("expected ReadOnly, got {other:?}"),
+            }
+        }
+    }
+
+    #[cfg(feature = "tar")]
+    mod tar_archives {
+        use super::*;
+        use crate::vfs::MemVfs;
+        use std::io::Write;
+
+        fn append(builder: &mut tar::Builder<Vec<u8>>, name: &[u8], data: &[u8]) {
+            let mut header = tar::Header::new_gnu();
+            // Bypass the checks of `set_path` to get `..` into the archive
+            for (dst, src) in header.as_old_mut().name.iter_mut().zip(name) {
+                *dst = *src;
+            }
+            header.set_size(data.len() as u64);
+            header.set_mode(0o640);
+            header.set_mtime(1_700_000_000);
+            header.set_cksum();
+            builder.append(&header, data).expect("append");
+        }
+
+        fn tarball() -> Vec<u8> {
+            let mut builder = tar::Builder::new(Vec::new());
+            append(&mut builder, b"./docs/a.txt", b"a\n");
+            append(&mut builder, b"../../escape.txt", b"caught\n");
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            builder
+                .append_link(&mut header, "docs/latest", "a.txt")
+                .expect("symlink");
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Directory);
+            header.set_size(0);
+            builder
+                .append_data(&mut header, "empty/", std::io::empty())
+                .expect("dir");
+            builder.into_inner().expect("finish")
+        }
+
+        #[test]
+        fn lists_and_reads_entries() {
+            let vfs = ArchiveVfs::from_tar(tarball().as_slice()).expect("tar");
+            assert_eq!(names(&vfs, "/"), ["docs", "empty", "escape.txt"]);
+            assert_eq!(names(&vfs, "/docs"), ["a.txt", "latest"]);
+            assert_eq!(read(&vfs, "/docs/a.txt"), "a\n");
+            assert_eq!(read(&vfs, "/escape.txt"), "caught\n");
+
+            let meta = vfs.metadata(p("/docs/a.txt")).expect("meta");
+            assert!(meta.is_file() && meta.readonly);
+            assert_eq!(meta.len, 2);
+            assert_eq!(meta.mode, Some(0o640));
+            assert_eq!(
+                meta.modified,
+                Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+            );
+            assert!(vfs.metadata(p("/empty")).expect("meta").is_dir());
+        }
+
+        #[test]
+        fn follows_symlinks_inside_the_archive() {
+            let vfs = ArchiveVfs::from_tar(tarball().as_slice()).expect("tar");
+            let entries = vfs.read_dir(p("/docs")).expect("read_dir");
+            assert!(entries
+                .iter()
+                .any(|e| e.name == "latest" && e.file_type == VfsFileType::Symlink));
+            assert_eq!(read(&vfs, "/docs/latest"), "a\n");
+            assert!(vfs.metadata(p("/docs/latest")).expect("meta").is_file());
+        }
+
+        #[test]
+        fn follows_symlinked_directories() {
+            let mut builder = tar::Builder::new(Vec::new());
+            append(&mut builder, b"usr/lib/x.so", b"elf\n");
+            for (link, target) in [("lib", "usr/lib"), ("usr/lib64", "../lib")] {
+                let mut header = tar::Header::new_gnu();
+                header.set_entry_type(tar::EntryType::Symlink);
+                header.set_size(0);
+                builder
+                    .append_link(&mut header, link, target)
+                    .expect("symlink");
+            }
+            let tarball = builder.into_inner().expect("finish");
+
+            let vfs = ArchiveVfs::from_tar(tarball.as_slice()).expect("tar");
+            assert_eq!(read(&vfs, "/lib/x.so"), "elf\n");
+            assert_eq!(read(&vfs, "/usr/lib64/x.so"), "elf\n");
+            assert_eq!(names(&vfs, "/lib"), ["x.so"]);
+            assert!(vfs.metadata(p("/lib/x.so")).expect("meta").is_file());
+            assert!(matches!(
+                vfs.metadata(p("/lib/nope")),
+                Err(VfsError::NotFound(path)) if path == p("/lib/nope")
+            ));
+        }
+
+        #[test]
+        fn reads_gzipped_tarballs_from_another_vfs() {
+            let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            gz.write_all(&tarball()).expect("compress");
+            let host = MemVfs::new().with_file("/backup.tar.gz", gz.finish().expect("finish"));
+
+            let mut vfs = ArchiveVfs::open(&host, p("/backup.tar.gz")).expect("open");
+            vfs.cd(p("docs")).expect("cd");
+            assert_eq!(read(&vfs, "a.txt"), "a\n");
+            match vfs.remove_file(p("a.txt")) {
+                Err(VfsError::ReadOnly(path)) => assert_eq!(path, p("/docs/a.txt")),
+                other => panic!("expected ReadOnly, got {other:?}"),
+            }
+        }
+    }
+
+    #[cfg(feature = "zip")]
+    mod zip_archives {
+        use super::*;
+        use std::io::Write;
+        use zip::write::SimpleFileOptions;
+
+        fn zipfile() -> Vec<u8> {
+            let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+            let time = zip::DateTime::from_date_and_time(2024, 2, 29, 12, 30, 16).expect("time");
+            let options = SimpleFileOptions::default()
+                .last_modified_time(time)
+                .unix_permissions(0o600);
+            writer.add_directory("src/", options).expect("dir");
+            writer.start_file("src/main.rs", options).expect("file");
+            writer.write_all(b"fn main() {}\n").expect("write");
+            writer.start_file("../up.txt", options).expect("file");
+            writer.write_all(b"up\n").expect("write");
+            writer
+                .add_symlink("src/lib.rs", "main.rs", options)
+                .expect("symlink");
+            writer.finish().expect("finish").into_inner()
+        }
+
+        #[test]
+        fn lists_and_reads_entries() {
+            let vfs = ArchiveVfs::from_zip(zipfile().as_slice()).expect("zip");
+            assert_eq!(names(&vfs, "/"), ["src", "up.txt"]);
+            assert_eq!(names(&vfs, "/src"), ["lib.rs", "main.rs"]);
+            assert_eq!(read(&vfs, "/src/main.rs"), "fn main() {}\n");
+            assert_eq!(read(&vfs, "/src/lib.rs"), "fn main() {}\n");
+            assert_eq!(read(&vfs, "/up.txt"), "up\n");
+
+            let meta = vfs.metadata(p("/src/main.rs")).expect("meta");
+            assert!(meta.is_file() && meta.readonly);
+            assert_eq!(meta.len, 13);
+            assert_eq!(meta.mode, Some(0o600));
+            assert_eq!(
+                meta.modified,
+                Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_709_209_816))
+            );
+        }
+
+        #[test]
+        fn detected_by_contents() {
+            let vfs = ArchiveVfs::from_bytes(zipfile()).expect("zip");
+            assert!(vfs.metadata(p("/src")).expect("meta").is_dir());
+        }
+    }
+}
@@ -0,0 +1,507 @@
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+use super::{normalize, Vfs, VfsDirEntry, VfsError, VfsMetadata, VfsResult, VfsWriteMode};
+
+#[cfg(all(feature = "inotify", target_os = "linux"))]
+mod inotify;
+
+/// How many symlinks a lookup follows before giving up.
+const MAX_SYMLINKS: usize = 40;
+
+/// A [`Vfs`] confined to a directory of the host filesystem.
+///
+/// The VFS root is the host directory, and no VFS path reaches anything
+/// outside of it: `..` never leaves the root, absolute paths start at the
+/// root, and symlinks are resolved one component at a time by `HostVfs`
+/// itself rather than by the host. Symlinks that stay inside the root are
+/// followed, every other one fails with [`VfsError::PermissionDenied`].
+/// Symlinks are never followed for [`remove_file`](Vfs::remove_file),
+/// [`rename`](Vfs::rename) and listings, so they can be seen and removed.
+///
+/// This makes it suitable for users who must not see the rest of the host,
+/// with two caveats: hard links and mounts below the root are
+/// indistinguishable from regular entries, and a host process that swaps
+/// entries for symlinks while a path is being opened can win the race. The
+/// VFS itself offers no way of creating symlinks or hard links.
+///
+/// With the `inotify` feature on Linux, [`watch`](Vfs::watch) hears about
+/// changes from the kernel instead of polling for them.
+///
+/// ```
+/// use std::path::Path;
+/// use esh::{HostVfs, Vfs};
+///
+/// let dir = tempfile::tempdir()?;
+/// std::fs::write(dir.path().join("motd"), "hello\n")?;
+///
+/// let mut vfs = HostVfs::new(dir.path())?;
+/// vfs.cd(Path::new("../../.."))?;
+/// assert_eq!(vfs.cwd(), Path::new("/"));
+/// assert!(vfs.exists(Path::new("/motd"))?);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct HostVfs {
+    /// Canonical, so it contains no symlinks itself
+    root: PathBuf,
+    cwd: PathBuf,
+}
+
+impl HostVfs {
+    /// Open the host directory `root` as the VFS root, which is also the
+    /// cwd.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`VfsError::NotFound`] or [`VfsError::NotADirectory`] if
+    /// `root` is not an existing directory, or another error if it cannot
+    /// be accessed.
+    pub fn new(root: impl AsRef<Path>) -> VfsResult<Self> {
+        let root = root.as_ref();
+        let canonical = root
+            .canonicalize()
+            .map_err(|e| VfsError::from_io(root, e))?;
+        if !canonical.is_dir() {
+            return Err(VfsError::NotADirectory(root.into()));
+        }
+        Ok(Self {
+            root: canonical,
+            cwd: PathBuf::from("/"),
+        })
+    }
+
+    /// The host directory at the VFS root, canonicalized.
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Map `path` to the host, resolving symlinks inside the root. The last
+    /// component is only resolved if `follow` is set.
+    ///
+    /// The host path built so far never contains symlinks, so resolving `..`
+    /// in link targets lexically gives the same result as the host would.
+    fn host_path(&self, path: &Path, follow: bool) -> VfsResult<PathBuf> {
+        let vfs_path = self.resolve(path);
+        // The components still to resolve, the next one last
+        let mut rest: Vec<OsString> = vfs_path
+            .components()
+            .rev()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .map(|c| c.as_os_str().to_os_string())
+            .collect();
+        let mut host = self.root.clone();
+        let mut links = 0;
+        while let Some(name) = rest.pop() {
+            let candidate = host.join(&name);
+            match std::fs::symlink_metadata(&candidate) {
+                Ok(meta) if meta.file_type().is_symlink() && (follow || !rest.is_empty()) => {
+                    links += 1;
+                    if links > MAX_SYMLINKS {
+                        return Err(VfsError::Io {
+                            path: vfs_path,
+                            source: std::io::Error::other("too many levels of symbolic links"),
+                        });
+                    }
+                    let target = std::fs::read_link(&candidate)
+                        .map_err(|e| VfsError::from_io(&vfs_path, e))?;
+                    let target = normalize(&host, &target);
+                    let Ok(inside) = target.strip_prefix(&self.root) else {
+                        return Err(VfsError::PermissionDenied(vfs_path));
+                    };
+                    rest.extend(inside.components().rev().map(|c| c.as_os_str().into()));
+                    host.clone_from(&self.root);
+                }
+                Ok(_) => host = candidate,
+                // Nothing below a missing entry can be a symlink, let the
+                // operation itself report the error
+                Err(_) => {
+                    host = candidate;
+                    host.extend(rest.iter().rev());
+                    break;
+                }
+            }
+        }
+        Ok(host)
+    }
+
+    /// Apply `f` to the host path of `path`, reporting errors for `path`.
+    fn on_host<T>(
+        &self,
+        path: &Path,
+        follow: bool,
+        f: impl FnOnce(PathBuf) -> std::io::Result<T>,
+    ) -> VfsResult<T> {
+        f(self.host_path(path, follow)?).map_err(|e| VfsError::from_io(self.resolve(path), e))
+    }
+
+    /// The VFS root itself can be neither removed nor renamed.
+    fn not_root(&self, path: &Path) -> VfsResult<()> {
+        let path = self.resolve(path);
+        if path.parent().is_none() {
+            return Err(VfsError::PermissionDenied(path));
+        }
+        Ok(())
+    }
+}
+
+impl Vfs for HostVfs {
+    fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    fn set_cwd(&mut self, path: &Path) -> VfsResult<()> {
+        self.cwd = self.resolve(path);
+        Ok(())
+    }
+
+    fn read_dir(&self, path: &Path) -> VfsResult<Vec<VfsDirEntry>> {
+        let err = |e| VfsError::from_io(self.resolve(path), e);
+        std::fs::read_dir(self.host_path(path, true)?)
+            .map_err(err)?
+            .map(|entry| {
+                let entry = entry.map_err(err)?;
+                Ok(VfsDirEntry {
+                    name: entry.file_name(),
+                    file_type: entry.file_type().map_err(err)?.into(),
+                })
+            })
+            .collect()
+    }
+
+    fn metadata(&self, path: &Path) -> VfsResult<VfsMetadata> {
+        // The host path has no symlinks left to follow
+        self.on_host(path, true, std::fs::symlink_metadata)
+            .map(VfsMetadata::from)
+    }
+
+    fn open_read(&self, path: &Path) -> VfsResult<Box<dyn Read + Send>> {
+        // Opening a directory succeeds on some platforms, reading it does not
+        if self.metadata(path)?.is_dir() {
+            return Err(VfsError::IsADirectory(self.resolve(path)));
+        }
+        let file = self.on_host(path, true, File::open)?;
+        Ok(Box::new(file))
+    }
+
+    fn open_write(&mut self, path: &Path, mode: VfsWriteMode) -> VfsResult<Box<dyn Write + Send>> {
+        let mut options = OpenOptions::new();
+        match mode {
+            VfsWriteMode::Truncate => options.write(true).create(true).truncate(true),
+            VfsWriteMode::Append => options.append(true).create(true),
+            VfsWriteMode::CreateNew => options.write(true).create_new(true),
+        };
+        let file = self.on_host(path, true, |path| options.open(path))?;
+        Ok(Box::new(BufWriter::new(file)))
+    }
+
+    fn create_dir(&mut self, path: &Path) -> VfsResult<()> {
+        self.on_host(path, false, std::fs::create_dir)
+    }
+
+    fn remove_file(&mut self, path: &Path) -> VfsResult<()> {
+        self.on_host(path, false, std::fs::remove_file)
+    }
+
+    fn remove_dir(&mut self, path: &Path) -> VfsResult<()> {
+        self.not_root(path)?;
+        self.on_host(path, false, std::fs::remove_dir)
+    }
+
+    fn remove_dir_all(&mut self, path: &Path) -> VfsResult<()> {
+        self.not_root(path)?;
+        // Removes symlinks without following them
+        self.on_host(path, false, std::fs::remove_dir_all)
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path) -> VfsResult<()> {
+        self.not_root(from)?;
+        let to = self.host_path(to, false)?;
+        self.on_host(from, false, |from| std::fs::rename(from, to))
+    }
+
+    fn copy(&mut self, from: &Path, to: &Path) -> VfsResult<u64> {
+        let to = self.host_path(to, true)?;
+        self.on_host(from, true, |from| std::fs::copy(from, to))
+    }
+
+    fn set_modified(&mut self, path: &Path, time: SystemTime) -> VfsResult<()> {
+        self.on_host(path, true, |path| File::open(path)?.set_modified(time))
+    }
+
+    #[cfg(all(feature = "inotify", target_os = "linux"))]
+    fn watch(&self, path: &Path) -> VfsResult<Box<dyn super::VfsWatcher>> {
+        let host = self.host_path(path, true)?;
+        Ok(Box::new(inotify::InotifyWatcher::new(
+            &self.resolve(path),
+            &host,
+        )?))
+    }
+}
+
+#[cfg(all(test, unix))]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    fn p(path: &str) -> &Path {
+        Path::new(path)
+    }
+
+    /// A tempdir with the VFS root `jail` and a `secret` file next to it.
+    fn setup() -> (tempfile::TempDir, HostVfs) {
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        let jail = dir.path().join("jail");
+        std::fs::create_dir_all(jail.join("docs")).expect("mkdir");
+        std::fs::write(jail.join("docs/a.txt"), "a\n").expect("write");
+        std::fs::write(dir.path().join("secret"), "secret\n").expect("write");
+        let vfs = HostVfs::new(&jail).expect("host vfs");
+        (dir, vfs)
+    }
+
+    fn read(vfs: &HostVfs, path: &str) -> VfsResult<String> {
+        let mut s = String::new();
+        vfs.open_read(p(path))?
+            .read_to_string(&mut s)
+            .expect("read");
+        Ok(s)
+    }
+
+    fn assert_denied<T: std::fmt::Debug>(result: VfsResult<T>, path: &str) {
+        match result {
+            Err(VfsError::PermissionDenied(denied)) => assert_eq!(denied, p(path)),
+            other => panic!("expected PermissionDenied for {path}, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn root_must_be_a_directory() {
+        let (dir, _) = setup();
+        assert!(matches!(
+            HostVfs::new(dir.path().join("secret")),
+            Err(VfsError::NotADirectory(_))
+        ));
+        assert!(matches!(
+            HostVfs::new(dir.path().join("missing")),
+            Err(VfsError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn dot_dot_stays_inside() {
+        let (dir, mut vfs) = setup();
+        vfs.cd(p("docs/../../..")).expect("cd");
+        assert_eq!(vfs.cwd(), p("/"));
+        assert_eq!(read(&vfs, "../../docs/a.txt").expect("read"), "a\n");
+        assert!(matches!(
+            read(&vfs, "../secret"),
+            Err(VfsError::NotFound(_))
+        ));
+        vfs.open_write(p("../../escape"), VfsWriteMode::Truncate)
+            .expect("write");
+        assert!(vfs.root().join("escape").exists());
+        assert!(!dir.path().join("escape").exists());
+    }
+
+    #[test]
+    fn absolute_paths_start_at_the_root() {
+        let (dir, mut vfs) = setup();
+        let secret = dir.path().join("secret");
+        let secret = secret.to_str().expect("utf-8 tempdir");
+        assert!(matches!(read(&vfs, secret), Err(VfsError::NotFound(_))));
+        assert!(!vfs.exists(p("/etc/passwd")).expect("exists"));
+        vfs.create_dir(p("/etc")).expect("mkdir");
+        assert!(vfs.root().join("etc").is_dir());
+    }
+
+    #[test]
+    fn follows_symlinks_inside_the_root() {
+        let (_dir, vfs) = setup();
+        let root = vfs.root();
+        symlink("a.txt", root.join("docs/latest")).expect("symlink");
+        symlink(root.join("docs"), root.join("abs")).expect("symlink");
+        symlink("docs/../docs/latest", root.join("chain")).expect("symlink");
+
+        assert_eq!(read(&vfs, "/docs/latest").expect("read"), "a\n");
+        assert_eq!(read(&vfs, "/abs/a.txt").expect("read"), "a\n");
+        assert_eq!(read(&vfs, "/chain").expect("read"), "a\n");
+        assert!(vfs.metadata(p("/abs")).expect("meta").is_dir());
+        assert_eq!(vfs.read_dir(p("/abs")).expect("read_dir").len(), 2);
+    }
+
+    #[test]
+    fn refuses_symlinks_that_leave_the_root() {
+        let (dir, mut vfs) = setup();
+        let root = vfs.root().to_path_buf();
+        symlink("../secret", root.join("rel")).expect("symlink");
+        symlink(dir.path().join("secret"), root.join("abs")).expect("symlink");
+        symlink(dir.path(), root.join("outside")).expect("symlink");
+        symlink("docs/../../new", root.join("trap")).expect("symlink");
+
+        assert_denied(read(&vfs, "/rel"), "/rel");
+        assert_denied(read(&vfs, "abs"), "/abs");
+        assert_denied(vfs.metadata(p("/outside")), "/outside");
+        assert_denied(read(&vfs, "/outside/secret"), "/outside/secret");
+        assert_denied(vfs.read_dir(p("/outside")), "/outside");
+        assert_denied(vfs.cd(p("/outside")), "/outside");
+        assert_denied(
+            vfs.open_write(p("/outside/new"), VfsWriteMode::Truncate)
+                .map(drop),
+            "/outside/new",
+        );
+        assert_denied(
+            vfs.open_write(p("/trap"), VfsWriteMode::Truncate).map(drop),
+            "/trap",
+        );
+        assert_denied(vfs.copy(p("/docs/a.txt"), p("/trap")), "/trap");
+        assert_denied(vfs.create_dir(p("/outside/dir")), "/outside/dir");
+        assert_denied(vfs.remove_file(p("/outside/secret")), "/outside/secret");
+        assert!(!dir.path().join("new").exists());
+        assert!(dir.path().join("secret").exists());
+
+        // The links themselves can be listed and removed
+        let entries = vfs.read_dir(p("/")).expect("read_dir");
+        assert!(entries
+            .iter()
+            .any(|e| e.name == "outside" && e.file_type == crate::VfsFileType::Symlink));
+        vfs.remove_file(p("/outside")).expect("rm");
+        vfs.rename(p("/abs"), p("/docs/abs")).expect("mv");
+        assert_denied(read(&vfs, "/docs/abs"), "/docs/abs");
+        assert!(dir.path().join("secret").exists());
+    }
+
+    #[test]
+    fn symlink_loops_are_errors() {
+        let (_dir, vfs) = setup();
+        symlink("b", vfs.root().join("a")).expect("symlink");
+        symlink("a", vfs.root().join("b")).expect("symlink");
+        match vfs.metadata(p("/a")) {
+            Err(VfsError::Io { path, .. }) => assert_eq!(path, p("/a")),
+            other => panic!("expected Io, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn root_cannot_be_removed_or_renamed() {
+        let (_dir, mut vfs) = setup();
+        vfs.cd(p("docs")).expect("cd");
+        assert_denied(vfs.remove_dir_all(p("..")), "/");
+        assert_denied(vfs.rename(p("/"), p("/x")), "/");
+        assert!(vfs.root().is_dir());
+    }
+
+    #[cfg(all(feature = "inotify", target_os = "linux"))]
+    fn changes(
+        vfs: &HostVfs,
+        watcher: &mut dyn crate::vfs::VfsWatcher,
+    ) -> Vec<(crate::VfsChangeKind, PathBuf)> {
+        watcher
+            .wait(vfs, std::time::Duration::from_secs(5))
+            .expect("wait")
+            .into_iter()
+            .map(|change| (change.kind, change.path))
+            .collect()
+    }
+
+    #[cfg(all(feature = "inotify", target_os = "linux"))]
+    #[test]
+    fn inotify_follows_new_and_moved_directories() {
+        use crate::VfsChangeKind::{Added, Modified, Removed};
+        let (_dir, vfs) = setup();
+        let root = vfs.root().to_path_buf();
+        let mut watcher = vfs.watch(p("/")).expect("watch");
+
+        std::fs::create_dir(root.join("new")).expect("mkdir");
+        std::fs::write(root.join("new/c.txt"), "c\n").expect("write");
+        std::fs::write(root.join("docs/a.txt"), "a\nb\n").expect("write");
+        std::fs::write(root.join("docs/a.txt"), "a\nb\nc\n").expect("write");
+        assert_eq!(
+            changes(&vfs, watcher.as_mut()),
+            [
+                (Added, "/new".into()),
+                (Added, "/new/c.txt".into()),
+                (Modified, "/docs/a.txt".into()),
+            ]
+        );
+
+        std::fs::remove_file(root.join("docs/a.txt")).expect("rm");
+        std::fs::rename(root.join("new"), root.join("docs/moved")).expect("mv");
+        assert_eq!(
+            changes(&vfs, watcher.as_mut()),
+            [
+                (Removed, "/docs/a.txt".into()),
+                (Removed, "/new".into()),
+                (Added, "/docs/moved".into()),
+                (Added, "/docs/moved/c.txt".into()),
+            ]
+        );
+
+        std::fs::write(root.join("docs/moved/c.txt"), "").expect("write");
+        assert_eq!(
+            changes(&vfs, watcher.as_mut()),
+            [(Modified, "/docs/moved/c.txt".into())]
+        );
+    }
+
+    #[cfg(all(feature = "inotify", target_os = "linux"))]
+    #[test]
+    fn inotify_stays_inside_the_root() {
+        let (dir, vfs) = setup();
+        symlink(dir.path(), vfs.root().join("outside")).expect("symlink");
+        let mut watcher = vfs.watch(p("/")).expect("watch");
+        std::fs::write(dir.path().join("secret"), "changed\n").expect("write");
+        let changes = watcher
+            .wait(&vfs, std::time::Duration::from_millis(100))
+            .expect("wait");
+        assert!(changes.is_empty(), "{changes:?}");
+        assert_denied(vfs.watch(p("/outside")).map(drop), "/outside");
+    }
+
+    #[cfg(all(feature = "inotify", target_os = "linux"))]
+    #[test]
+    fn inotify_watches_single_files() {
+        use crate::VfsChangeKind::{Modified, Removed};
+        let (_dir, vfs) = setup();
+        let file = vfs.root().join("docs/a.txt");
+        let mut watcher = vfs.watch(p("/docs/a.txt")).expect("watch");
+
+        std::fs::write(vfs.root().join("docs/b.txt"), "b\n").expect("write");
+        std::fs::write(&file, "a\nmore\n").expect("write");
+        assert_eq!(
+            changes(&vfs, watcher.as_mut()),
+            [(Modified, "/docs/a.txt".into())]
+        );
+        std::fs::remove_file(&file).expect("rm");
+        assert_eq!(
+            changes(&vfs, watcher.as_mut()),
+            [(Removed, "/docs/a.txt".into())]
+        );
+    }
+
+    #[test]
+    fn modifies_files_inside_the_root() {
+        let (_dir, mut vfs) = setup();
+        vfs.create_dir_all(p("/a/b")).expect("mkdir -p");
+        let mut writer = vfs
+            .open_write(p("/a/b/f"), VfsWriteMode::CreateNew)
+            .expect("open");
+        writer.write_all(b"data").expect("write");
+        drop(writer);
+        assert_eq!(vfs.copy(p("/a/b/f"), p("/a/g")).expect("cp"), 4);
+        vfs.rename(p("/a/g"), p("/h")).expect("mv");
+        assert_eq!(read(&vfs, "/h").expect("read"), "data");
+        vfs.set_modified(p("/h"), SystemTime::UNIX_EPOCH)
+            .expect("touch");
+        assert_eq!(
+            vfs.metadata(p("/h")).expect("meta").modified,
+            Some(SystemTime::UNIX_EPOCH)
+        );
+        vfs.remove_dir_all(p("/a")).expect("rm -r");
+        assert!(!vfs.exists(p("/a")).expect("exists"));
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::mem::MaybeUninit;
+use std::os::fd::OwnedFd;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use rustix::event::{PollFd, PollFlags, Timespec};
+use rustix::fs::inotify::{self, CreateFlags, ReadFlags, WatchFlags};
+use rustix::io::Errno;
+
+use crate::vfs::{Vfs, VfsChange, VfsChangeKind, VfsError, VfsFileType, VfsResult, VfsWatcher};
+
+/// Events that make up the changes a [`VfsWatcher`] reports.
+const EVENTS: WatchFlags = WatchFlags::CREATE
+    .union(WatchFlags::DELETE)
+    .union(WatchFlags::MODIFY)
+    .union(WatchFlags::ATTRIB)
+    .union(WatchFlags::MOVED_FROM)
+    .union(WatchFlags::MOVED_TO)
+    .union(WatchFlags::DELETE_SELF)
+    .union(WatchFlags::MOVE_SELF)
+    .union(WatchFlags::DONT_FOLLOW);
+
+/// A [`VfsWatcher`] for a [`HostVfs`](super::HostVfs) that lets the kernel
+/// report changes, with one inotify watch per directory.
+///
+/// Directories are walked without following symlinks, so nothing outside
+/// the VFS root is watched.
+pub(super) struct InotifyWatcher {
+    fd: OwnedFd,
+    /// The watched path, for errors
+    path: PathBuf,
+    /// The watch descriptor of the watched path itself
+    root: i32,
+    /// The VFS and host path of each watch descriptor
+    watches: HashMap<i32, (PathBuf, PathBuf)>,
+}
+
+/// An event read from the inotify descriptor.
+struct Event {
+    wd: i32,
+    flags: ReadFlags,
+    name: Option<OsString>,
+}
+
+impl InotifyWatcher {
+    /// Watch the host path `host`, which is `path` in the VFS.
+    pub(super) fn new(path: &Path, host: &Path) -> VfsResult<Self> {
+        let err = |e: Errno| VfsError::from_io(path, e.into());
+        let fd = inotify::init(CreateFlags::CLOEXEC | CreateFlags::NONBLOCK).map_err(err)?;
+        let root = inotify::add_watch(&fd, host, EVENTS).map_err(err)?;
+        let mut watcher = Self {
+            fd,
+            path: path.to_path_buf(),
+            root,
+            watches: HashMap::new(),
+        };
+        watcher.watches.insert(root, (path.into(), host.into()));
+        if std::fs::symlink_metadata(host).is_ok_and(|meta| meta.is_dir()) {
+            watcher.add_children(path, host, None).map_err(err)?;
+        }
+        Ok(watcher)
+    }
+
+    /// Watch the directories below `host`, which is `path` in the VFS. The
+    /// entries found are reported as added if `added` is given, as they came
+    /// before their watch did.
+    fn add_children(
+        &mut self,
+        path: &Path,
+        host: &Path,
+        mut added: Option<&mut Vec<VfsChange>>,
+    ) -> Result<(), Errno> {
+        let mut todo = vec![(path.to_path_buf(), host.to_path_buf())];
+        while let Some((dir, host_dir)) = todo.pop() {
+            // Vanished in the meantime, which is reported as a removal
+            let Ok(entries) = std::fs::read_dir(&host_dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let Ok(file_type) = entry.file_type() else {
+                    continue;
+                };
+                let (child, host_child) = (dir.join(entry.file_name()), entry.path());
+                if let Some(added) = added.as_deref_mut() {
+                    push(added, VfsChangeKind::Added, child.clone(), file_type.into());
+                }
+                if file_type.is_dir() {
+                    match inotify::add_watch(&self.fd, &host_child, EVENTS | WatchFlags::ONLYDIR) {
+                        Ok(wd) => {
+                            self.watches.insert(wd, (child.clone(), host_child.clone()));
+                            todo.push((child, host_child));
+                        }
+                        Err(Errno::NOENT | Errno::NOTDIR) => {}
+                        Err(e) => return Err(e),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the events that are ready, without blocking.
+    fn read_events(&self) -> Result<Vec<Event>, Errno> {
+        let mut buf = [MaybeUninit::uninit(); 4096];
+        let mut reader = inotify::Reader::new(&self.fd, &mut buf);
+        let mut events = Vec::new();
+        loop {
+            match reader.next() {
+                Ok(event) => events.push(Event {
+                    wd: event.wd(),
+                    flags: event.events(),
+                    name: event
+                        .file_name()
+                        .map(|name| std::ffi::OsStr::from_bytes(name.to_bytes()).to_owned()),
+                }),
+                Err(Errno::AGAIN) => return Ok(events),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Turn `event` into changes.
+    fn apply(&mut self, event: &Event, changes: &mut Vec<VfsChange>) -> Result<(), Errno> {
+        if event.flags.contains(ReadFlags::IGNORED) {
+            self.watches.remove(&event.wd);
+            return Ok(());
+        }
+        let Some((dir, host_dir)) = self.watches.get(&event.wd) else {
+            // From a directory moved away
+            return Ok(());
+        };
+        let (path, host) = match &event.name {
+            Some(name) => (dir.join(name), host_dir.join(name)),
+            // Events of directories also reach the watch of their parent,
+            // so only the watched path itself is reported from its own
+            None if event.wd == self.root => (dir.clone(), host_dir.clone()),
+            None => return Ok(()),
+        };
+        let is_dir = event.flags.contains(ReadFlags::ISDIR);
+        let file_type = std::fs::symlink_metadata(&host).map_or(
+            if is_dir {
+                VfsFileType::Dir
+            } else {
+                VfsFileType::File
+            },
+            |meta| meta.file_type().into(),
+        );
+
+        if event
+            .flags
+            .intersects(ReadFlags::CREATE | ReadFlags::MOVED_TO)
+        {
+            push(changes, VfsChangeKind::Added, path.clone(), file_type);
+            if is_dir {
+                self.add_directory(&path, &host, changes)?;
+            }
+        } else if event
+            .flags
+            .intersects(ReadFlags::DELETE | ReadFlags::MOVED_FROM)
+        {
+            if is_dir {
+                // Moved out or gone, its watches report nothing from now on
+                self.watches
+                    .retain(|_, (watched, _)| !watched.starts_with(&path));
+            }
+            push(changes, VfsChangeKind::Removed, path, file_type);
+        } else if event
+            .flags
+            .intersects(ReadFlags::DELETE_SELF | ReadFlags::MOVE_SELF)
+        {
+            push(changes, VfsChangeKind::Removed, path, file_type);
+        } else if event
+            .flags
+            .intersects(ReadFlags::MODIFY | ReadFlags::ATTRIB)
+        {
+            push(changes, VfsChangeKind::Modified, path, file_type);
+        }
+        Ok(())
+    }
+
+    /// Watch the new directory `host`, which is `path` in the VFS, and
+    /// everything already in it.
+    fn add_directory(
+        &mut self,
+        path: &Path,
+        host: &Path,
+        changes: &mut Vec<VfsChange>,
+    ) -> Result<(), Errno> {
+        match inotify::add_watch(&self.fd, host, EVENTS | WatchFlags::ONLYDIR) {
+            Ok(wd) => {
+                self.watches.insert(wd, (path.into(), host.into()));
+                self.add_children(path, host, Some(changes))
+            }
+            Err(Errno::NOENT | Errno::NOTDIR) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Add a change to `changes`, folding it into the ones before: a path that
+/// was just added or modified is not modified again, and its removal drops
+/// the modifications.
+fn push(changes: &mut Vec<VfsChange>, kind: VfsChangeKind, path: PathBuf, file_type: VfsFileType) {
+    match kind {
+        VfsChangeKind::Removed => {
+            changes.retain(|c| c.path != path || c.kind != VfsChangeKind::Modified);
+        }
+        VfsChangeKind::Modified
+            if changes
+                .iter()
+                .any(|c| c.path == path && c.kind != VfsChangeKind::Removed) =>
+        {
+            return;
+        }
+        VfsChangeKind::Added | VfsChangeKind::Modified => {}
+    }
+    changes.push(VfsChange {
+        kind,
+        path,
+        file_type,
+    });
+}
+
+impl VfsWatcher for InotifyWatcher {
+    fn wait(&mut self, _vfs: &dyn Vfs, timeout: Duration) -> VfsResult<Vec<VfsChange>> {
+        let path = self.path.clone();
+        let err = |e: Errno| VfsError::from_io(&path, e.into());
+
+        let timeout = Timespec::try_from(timeout).unwrap_or(Timespec {
+            tv_sec: i64::MAX,
+            tv_nsec: 0,
+        });
+        let mut fds = [PollFd::new(&self.fd, PollFlags::IN)];
+        match rustix::event::poll(&mut fds, Some(&timeout)) {
+            Ok(0) | Err(Errno::INTR) => return Ok(Vec::new()),
+            Ok(_) => {}
+            Err(e) => return Err(err(e)),
+        }
+
+        let events = self.read_events().map_err(err)?;
+        if events
+            .iter()
+            .any(|e| e.flags.contains(ReadFlags::QUEUE_OVERFLOW))
+        {
+            return Err(VfsError::Io {
+                path,
+                source: std::io::Error::other("too many changes at once, some were lost"),
+            });
+        }
+        let mut changes = Vec::new();
+        for event in events {
+            self.apply(&event, &mut changes).map_err(err)?;
+        }
+        Ok(changes)
+    }
+}
@@ -0,0 +1,706 @@
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::SystemTime;
+
+use super::{Vfs, VfsDirEntry, VfsError, VfsFileType, VfsMetadata, VfsResult, VfsWriteMode};
+
+/// File contents, shared between snapshots until one of them is written to.
+type Data = Arc<Vec<u8>>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Content {
+    Dir,
+    File(Data),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Node {
+    content: Content,
+    modified: SystemTime,
+}
+
+impl Node {
+    fn dir() -> Self {
+        Self {
+            content: Content::Dir,
+            modified: SystemTime::now(),
+        }
+    }
+
+    fn file(data: Data) -> Self {
+        Self {
+            content: Content::File(data),
+            modified: SystemTime::now(),
+        }
+    }
+
+    const fn is_dir(&self) -> bool {
+        matches!(self.content, Content::Dir)
+    }
+}
+
+/// All nodes by absolute path. The ordering of [`Path`] keeps every subtree
+/// in one contiguous range right after its root.
+type Tree = BTreeMap<PathBuf, Node>;
+
+/// A [`Vfs`] that keeps directories and files in memory.
+///
+/// `MemVfs` supports every [`Vfs`] operation except symlinks, which it has no
+/// way of creating. Seed it with [`from_files`](MemVfs::from_files) or the
+/// `with_*` builders; [`snapshot`](MemVfs::snapshot) and
+/// [`restore`](MemVfs::restore) save and bring back the whole tree. Snapshots
+/// share file contents with the live tree until a file is written, so they
+/// are cheap to take.
+///
+/// ```
+/// use std::path::Path;
+/// use esh::{MemVfs, Vfs};
+///
+/// let mut vfs = MemVfs::from_files([("/etc/motd", "hello\n"), ("/tmp/scratch", "")]);
+/// let before = vfs.snapshot();
+/// vfs.remove_file(Path::new("/etc/motd")).unwrap();
+/// assert!(!vfs.exists(Path::new("/etc/motd")).unwrap());
+///
+/// vfs.restore(&before);
+/// assert_eq!(vfs.snapshot().file(Path::new("/etc/motd")), Some(&b"hello\n"[..]));
+/// ```
+#[derive(Debug)]
+pub struct MemVfs {
+    cwd: PathBuf,
+    // Shared with the writers handed out by `open_write`
+    tree: Arc<Mutex<Tree>>,
+}
+
+/// A copy of the tree of a [`MemVfs`], taken with [`MemVfs::snapshot`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemSnapshot {
+    tree: Tree,
+}
+
+impl MemSnapshot {
+    /// The files in the snapshot with their contents, ordered by path.
+    pub fn files(&self) -> impl Iterator<Item = (&Path, &[u8])> {
+        self.tree
+            .iter()
+            .filter_map(|(path, node)| match &node.content {
+                Content::File(data) => Some((path.as_path(), data.as_slice())),
+                Content::Dir => None,
+            })
+    }
+
+    /// The directories in the snapshot, ordered by path, starting with `/`.
+    pub fn dirs(&self) -> impl Iterator<Item = &Path> {
+        self.tree
+            .iter()
+            .filter(|(_, node)| node.is_dir())
+            .map(|(path, _)| path.as_path())
+    }
+
+    /// The contents of the file at the absolute `path`, if there is one.
+    #[must_use]
+    pub fn file(&self, path: &Path) -> Option<&[u8]> {
+        match &self.tree.get(path)?.content {
+            Content::File(data) => Some(data),
+            Content::Dir => None,
+        }
+    }
+}
+
+impl Default for MemVfs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<MemSnapshot> for MemVfs {
+    fn from(snapshot: MemSnapshot) -> Self {
+        Self {
+            cwd: PathBuf::from("/"),
+            tree: Arc::new(Mutex::new(snapshot.tree)),
+        }
+    }
+}
+
+impl MemVfs {
+    /// Create an empty tree with just the root directory, which is also the
+    /// cwd.
+    #[must_use]
+    pub fn new() -> Self {
+        let tree = Tree::from([(PathBuf::from("/"), Node::dir())]);
+        Self {
+            cwd: PathBuf::from("/"),
+            tree: Arc::new(Mutex::new(tree)),
+        }
+    }
+
+    /// Create a tree from `(path, contents)` pairs, for example a
+    /// `HashMap<PathBuf, Vec<u8>>`. Missing parent directories are created,
+    /// relative paths are taken from the root.
+    pub fn from_files<P, D>(files: impl IntoIterator<Item = (P, D)>) -> Self
+    where
+        P: AsRef<Path>,
+        D: Into<Vec<u8>>,
+    {
+        files
+            .into_iter()
+            .fold(Self::new(), |vfs, (path, data)| vfs.with_file(path, data))
+    }
+
+    /// Add the directory `path` and any missing parents.
+    #[must_use]
+    pub fn with_dir(self, path: impl AsRef<Path>) -> Self {
+        let path = self.resolve(path.as_ref());
+        let mut tree = self.lock();
+        add_parents(&mut tree, &path);
+        tree.entry(path).or_insert_with(Node::dir).content = Content::Dir;
+        drop(tree);
+        self
+    }
+
+    /// Add the file `path` with `data`, replacing an existing file and
+    /// creating missing parent directories.
+    #[must_use]
+    pub fn with_file(self, path: impl AsRef<Path>, data: impl Into<Vec<u8>>) -> Self {
+        let path = self.resolve(path.as_ref());
+        let mut tree = self.lock();
+        add_parents(&mut tree, &path);
+        tree.insert(path, Node::file(Arc::new(data.into())));
+        drop(tree);
+        self
+    }
+
+    /// Save the current tree. Open writers keep writing to the live tree.
+    #[must_use]
+    pub fn snapshot(&self) -> MemSnapshot {
+        MemSnapshot {
+            tree: self.lock().clone(),
+        }
+    }
+
+    /// Replace the tree with `snapshot`. The cwd is kept if it still is a
+    /// directory, otherwise it goes back to the root.
+    pub fn restore(&mut self, snapshot: &MemSnapshot) {
+        let mut tree = self.lock();
+        tree.clone_from(&snapshot.tree);
+        tree.entry(PathBuf::from("/")).or_insert_with(Node::dir);
+        let cwd_exists = tree.get(&self.cwd).is_some_and(Node::is_dir);
+        drop(tree);
+        if !cwd_exists {
+            self.cwd = PathBuf::from("/");
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Tree> {
+        // Every operation leaves the tree consistent, even if a panic
+        // poisoned the lock
+        self.tree.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Turn all ancestors of `path` into directories.
+fn add_parents(tree: &mut Tree, path: &Path) {
+    for parent in path.ancestors().skip(1) {
+        match tree.get_mut(parent) {
+            Some(node) if node.is_dir() => break,
+            Some(node) => node.content = Content::Dir,
+            None => {
+                tree.insert(parent.to_path_buf(), Node::dir());
+            }
+        }
+    }
+}
+
+fn node<'a>(tree: &'a Tree, path: &Path) -> VfsResult<&'a Node> {
+    tree.get(path)
+        .ok_or_else(|| VfsError::NotFound(path.into()))
+}
+
+/// Check that the parent of the absolute `path` is a directory.
+fn check_parent(tree: &Tree, path: &Path) -> VfsResult<()> {
+    let parent = path.parent().unwrap_or(path);
+    if node(tree, parent)?.is_dir() {
+        Ok(())
+    } else {
+        Err(VfsError::NotADirectory(parent.into()))
+    }
+}
+
+fn is_root(path: &Path) -> bool {
+    path.parent().is_none()
+}
+
+/// The paths of `path` and everything below it.
+fn subtree(tree: &Tree, path: &Path) -> Vec<PathBuf> {
+    tree.range(path.to_path_buf()..)
+        .map(|(p, _)| p)
+        .take_while(|p| p.starts_with(path))
+        .cloned()
+        .collect()
+}
+
+fn has_children(tree: &Tree, path: &Path) -> bool {
+    tree.range(path.to_path_buf()..)
+        .nth(1)
+        .is_some_and(|(p, _)| p.starts_with(path))
+}
+
+impl Vfs for MemVfs {
+    fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    fn set_cwd(&mut self, path: &Path) -> VfsResult<()> {
+        self.cwd = self.resolve(path);
+        Ok(())
+    }
+
+    fn read_dir(&self, path: &Path) -> VfsResult<Vec<VfsDirEntry>> {
+        let path = self.resolve(path);
+        let tree = self.lock();
+        if !node(&tree, &path)?.is_dir() {
+            return Err(VfsError::NotADirectory(path));
+        }
+        Ok(tree
+            .range(path.clone()..)
+            .skip(1)
+            .take_while(|(p, _)| p.starts_with(&path))
+            .filter(|(p, _)| p.parent() == Some(&path))
+            .filter_map(|(p, node)| {
+                Some(VfsDirEntry {
+                    name: p.file_name()?.to_os_string(),
+                    file_type: if node.is_dir() {
+                        VfsFileType::Dir
+                    } else {
+                        VfsFileType::File
+                    },
+                })
+            })
+            .collect())
+    }
+
+    fn metadata(&self, path: &Path) -> VfsResult<VfsMetadata> {
+        let path = self.resolve(path);
+        // Cloning only clones the `Arc` of the contents
+        let node = node(&self.lock(), &path)?.clone();
+        let (file_type, len, mode) = match &node.content {
+            Content::Dir => (VfsFileType::Dir, 0, 0o755),
+            Content::File(data) => (VfsFileType::File, data.len() as u64, 0o644),
+        };
+        Ok(VfsMetadata {
+            file_type,
+            len,
+            modified: Some(node.modified),
+            readonly: false,
+            mode: Some(mode),
+        })
+    }
+
+    fn open_read(&self, path: &Path) -> VfsResult<Box<dyn Read + Send>> {
+        let path = self.resolve(path);
+        match &node(&self.lock(), &path)?.content {
+            // Later writes do not show up in the reader
+            Content::File(data) => Ok(Box::new(Cursor::new(SharedData(Arc::clone(data))))),
+            Content::Dir => Err(VfsError::IsADirectory(path)),
+        }
+    }
+
+    fn open_write(&mut self, path: &Path, mode: VfsWriteMode) -> VfsResult<Box<dyn Write + Send>> {
+        let path = self.resolve(path);
+        let mut tree = self.lock();
+        check_parent(&tree, &path)?;
+        match (tree.get_mut(&path), mode) {
+            (Some(node), _) if node.is_dir() => return Err(VfsError::IsADirectory(path)),
+            (Some(_), VfsWriteMode::CreateNew) => return Err(VfsError::AlreadyExists(path)),
+            (Some(node), VfsWriteMode::Truncate) => *node = Node::file(Data::default()),
+            (Some(_), VfsWriteMode::Append) => {}
+            (None, _) => {
+                tree.insert(path.clone(), Node::file(Data::default()));
+            }
+        }
+        drop(tree);
+        Ok(Box::new(MemWriter {
+            tree: Arc::clone(&self.tree),
+            path,
+        }))
+    }
+
+    fn create_dir(&mut self, path: &Path) -> VfsResult<()> {
+        let path = self.resolve(path);
+        let mut tree = self.lock();
+        check_parent(&tree, &path)?;
+        if tree.contains_key(&path) {
+            return Err(VfsError::AlreadyExists(path));
+        }
+        tree.insert(path, Node::dir());
+        drop(tree);
+        Ok(())
+    }
+
+    fn remove_file(&mut self, path: &Path) -> VfsResult<()> {
+        let path = self.resolve(path);
+        let mut tree = self.lock();
+        if node(&tree, &path)?.is_dir() {
+            return Err(VfsError::IsADirectory(path));
+        }
+        tree.remove(&path);
+        drop(tree);
+        Ok(())
+    }
+
+    fn remove_dir(&mut self, path: &Path) -> VfsResult<()> {
+        let path = self.resolve(path);
+        let mut tree = self.lock();
+        if !node(&tree, &path)?.is_dir() {
+            return Err(VfsError::NotADirectory(path));
+        }
+        if is_root(&path) {
+            return Err(VfsError::PermissionDenied(path));
+        }
+        if has_children(&tree, &path) {
+            return Err(VfsError::NotEmpty(path));
+        }
+        tree.remove(&path);
+        drop(tree);
+        Ok(())
+    }
+
+    fn remove_dir_all(&mut self, path: &Path) -> VfsResult<()> {
+        let path = self.resolve(path);
+        let mut tree = self.lock();
+        if !node(&tree, &path)?.is_dir() {
+            return Err(VfsError::NotADirectory(path));
+        }
+        if is_root(&path) {
+            return Err(VfsError::PermissionDenied(path));
+        }
+        for p in subtree(&tree, &path) {
+            tree.remove(&p);
+        }
+        drop(tree);
+        Ok(())
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path) -> VfsResult<()> {
+        let from = self.resolve(from);
+        let to = self.resolve(to);
+        let mut tree = self.lock();
+        let moving_dir = node(&tree, &from)?.is_dir();
+        check_parent(&tree, &to)?;
+        if from == to {
+            return Ok(());
+        }
+        if is_root(&from) || to.starts_with(&from) {
+            return Err(VfsError::PermissionDenied(from));
+        }
+        if let Some(existing) = tree.get(&to) {
+            match (existing.is_dir(), moving_dir) {
+                (true, true) if has_children(&tree, &to) => return Err(VfsError::NotEmpty(to)),
+                (true, false) => return Err(VfsError::IsADirectory(to)),
+                (false, true) => return Err(VfsError::NotADirectory(to)),
+                _ => {}
+            }
+        }
+
+        for old in subtree(&tree, &from) {
+            if let (Some(node), Ok(rest)) = (tree.remove(&old), old.strip_prefix(&from)) {
+                tree.insert(to.join(rest), node);
+            }
+        }
+        drop(tree);
+        Ok(())
+    }
+
+    fn copy(&mut self, from: &Path, to: &Path) -> VfsResult<u64> {
+        let from = self.resolve(from);
+        let to = self.resolve(to);
+        let mut tree = self.lock();
+        let Content::File(data) = &node(&tree, &from)?.content else {
+            return Err(VfsError::IsADirectory(from));
+        };
+        // The copy shares the contents until either file is written
+        let data = Arc::clone(data);
+        check_parent(&tree, &to)?;
+        if tree.get(&to).is_some_and(Node::is_dir) {
+            return Err(VfsError::IsADirectory(to));
+        }
+        let len = data.len() as u64;
+        tree.insert(to, Node::file(data));
+        drop(tree);
+        Ok(len)
+    }
+
+    fn set_modified(&mut self, path: &Path, time: SystemTime) -> VfsResult<()> {
+        let path = self.resolve(path);
+        match self.lock().get_mut(&path) {
+            Some(node) => {
+                node.modified = time;
+                Ok(())
+            }
+            None => Err(VfsError::NotFound(path)),
+        }
+    }
+}
+
+/// Shared file contents as a [`Cursor`] source.
+struct SharedData(Data);
+
+impl AsRef<[u8]> for SharedData {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Appends everything written to the file at `path`.
+struct MemWriter {
+    tree: Arc<Mutex<Tree>>,
+    path: PathBuf,
+}
+
+impl Write for MemWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut tree = self.tree.lock().unwrap_or_else(PoisonError::into_inner);
+        // The file may have been removed or replaced in the meantime
+        match tree.get_mut(&self.path) {
+            Some(Node {
+                content: Content::File(data),
+                modified,
+            }) => {
+                Arc::make_mut(data).extend_from_slice(buf);
+                *modified = SystemTime::now();
+            }
+            _ => return Err(std::io::ErrorKind::NotFound.into()),
+        }
+        drop(tree);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn p(path: &str) -> &Path {
+        Path::new(path)
+    }
+
+    fn read(vfs: &MemVfs, path: &str) -> String {
+        let mut s = String::new();
+        vfs.open_read(p(path))
+            .expect("open")
+            .read_to_string(&mut s)
+            .expect("read");
+        s
+    }
+
+    fn names(vfs: &MemVfs, path: &str) -> Vec<String> {
+        vfs.read_dir(p(path))
+            .expect("read_dir")
+            .into_iter()
+            .map(|e| e.name.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn seeded_from_a_map() {
+        let files = HashMap::from([
+            (PathBuf::from("/a/b/c.txt"), b"c".to_vec()),
+            (PathBuf::from("top"), b"top".to_vec()),
+        ]);
+        let vfs = MemVfs::from_files(files);
+        assert_eq!(read(&vfs, "/a/b/c.txt"), "c");
+        assert_eq!(read(&vfs, "/top"), "top");
+        assert!(vfs.metadata(p("/a/b")).expect("meta").is_dir());
+        assert_eq!(names(&vfs, "/"), ["a", "top"]);
+        assert_eq!(
+            vfs.snapshot().dirs().collect::<Vec<_>>(),
+            [p("/"), p("/a"), p("/a/b")]
+        );
+    }
+
+    #[test]
+    fn read_dir_lists_direct_children_only() {
+        let vfs = MemVfs::new()
+            .with_file("/a/x", "")
+            .with_file("/a/y/z", "")
+            .with_file("/a.txt", "");
+        assert_eq!(names(&vfs, "/a"), ["x", "y"]);
+        assert_eq!(names(&vfs, "/"), ["a", "a.txt"]);
+        assert!(matches!(
+            vfs.read_dir(p("/a.txt")),
+            Err(VfsError::NotADirectory(_))
+        ));
+        assert!(matches!(vfs.read_dir(p("/b")), Err(VfsError::NotFound(_))));
+    }
+
+    #[test]
+    fn write_modes() {
+        let mut vfs = MemVfs::new().with_file("/f", "old");
+        write!(
+            vfs.open_write(p("/f"), VfsWriteMode::Append)
+                .expect("append"),
+            "+"
+        )
+        .expect("write");
+        assert_eq!(read(&vfs, "/f"), "old+");
+        write!(
+            vfs.open_write(p("f"), VfsWriteMode::Truncate)
+                .expect("truncate"),
+            "new"
+        )
+        .expect("write");
+        assert_eq!(read(&vfs, "/f"), "new");
+        assert!(matches!(
+            vfs.open_write(p("/f"), VfsWriteMode::CreateNew),
+            Err(VfsError::AlreadyExists(_))
+        ));
+        assert!(matches!(
+            vfs.open_write(p("/"), VfsWriteMode::Append),
+            Err(VfsError::IsADirectory(_))
+        ));
+        assert!(matches!(
+            vfs.open_write(p("/f/g"), VfsWriteMode::Truncate),
+            Err(VfsError::NotADirectory(_))
+        ));
+        assert!(matches!(
+            vfs.open_write(p("/no/g"), VfsWriteMode::Truncate),
+            Err(VfsError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn readers_see_the_contents_at_open_time() {
+        let mut vfs = MemVfs::new().with_file("/f", "before");
+        let mut reader = vfs.open_read(p("/f")).expect("open");
+        write!(
+            vfs.open_write(p("/f"), VfsWriteMode::Append).expect("open"),
+            "!"
+        )
+        .expect("write");
+        let mut s = String::new();
+        reader.read_to_string(&mut s).expect("read");
+        assert_eq!(s, "before");
+        assert_eq!(read(&vfs, "/f"), "before!");
+    }
+
+    #[test]
+    fn directories() {
+        let mut vfs = MemVfs::new();
+        vfs.create_dir(p("/d")).expect("create");
+        assert!(matches!(
+            vfs.create_dir(p("/d")),
+            Err(VfsError::AlreadyExists(_))
+        ));
+        assert!(matches!(
+            vfs.create_dir(p("/x/y")),
+            Err(VfsError::NotFound(_))
+        ));
+        vfs.create_dir_all(p("/x/y/z")).expect("create all");
+        assert!(vfs.exists(p("/x/y/z")).expect("exists"));
+
+        assert!(matches!(
+            vfs.remove_dir(p("/x")),
+            Err(VfsError::NotEmpty(_))
+        ));
+        assert!(matches!(
+            vfs.remove_file(p("/x")),
+            Err(VfsError::IsADirectory(_))
+        ));
+        vfs.remove_dir(p("/d")).expect("remove");
+        vfs.remove_dir_all(p("/x")).expect("remove all");
+        assert_eq!(names(&vfs, "/"), Vec::<String>::new());
+        assert!(matches!(
+            vfs.remove_dir_all(p("/")),
+            Err(VfsError::PermissionDenied(_))
+        ));
+    }
+
+    #[test]
+    fn rename_moves_subtrees() {
+        let mut vfs = MemVfs::new()
+            .with_file("/a/b/c", "c")
+            .with_file("/a.txt", "a")
+            .with_dir("/empty");
+        vfs.rename(p("/a"), p("/empty")).expect("rename");
+        assert_eq!(read(&vfs, "/empty/b/c"), "c");
+        assert!(!vfs.exists(p("/a")).expect("exists"));
+        assert_eq!(read(&vfs, "/a.txt"), "a");
+
+        assert!(matches!(
+            vfs.rename(p("/empty"), p("/empty/b/x")),
+            Err(VfsError::PermissionDenied(_))
+        ));
+        assert!(matches!(
+            vfs.rename(p("/a.txt"), p("/empty")),
+            Err(VfsError::IsADirectory(_))
+        ));
+        vfs.rename(p("/a.txt"), p("/empty/b/c"))
+            .expect("replace file");
+        assert_eq!(read(&vfs, "/empty/b/c"), "a");
+    }
+
+    #[test]
+    fn copies_share_contents_until_written() {
+        let mut vfs = MemVfs::new().with_file("/src", "data");
+        assert_eq!(vfs.copy(p("/src"), p("/dst")).expect("copy"), 4);
+        write!(
+            vfs.open_write(p("/dst"), VfsWriteMode::Append)
+                .expect("open"),
+            "!"
+        )
+        .expect("write");
+        assert_eq!(read(&vfs, "/src"), "data");
+        assert_eq!(read(&vfs, "/dst"), "data!");
+        assert!(matches!(
+            vfs.copy(p("/"), p("/x")),
+            Err(VfsError::IsADirectory(_))
+        ));
+    }
+
+    #[test]
+    fn snapshot_and_restore() {
+        let mut vfs = MemVfs::new().with_file("/keep", "1").with_dir("/gone");
+        vfs.cd(p("/gone")).expect("cd");
+        let empty = MemVfs::new().snapshot();
+        let before = vfs.snapshot();
+
+        write!(
+            vfs.open_write(p("/keep"), VfsWriteMode::Append)
+                .expect("open"),
+            "2"
+        )
+        .expect("write");
+        vfs.create_dir(p("/new")).expect("create");
+        assert_eq!(before.file(p("/keep")), Some(&b"1"[..]));
+        assert_ne!(vfs.snapshot(), before);
+
+        vfs.restore(&before);
+        assert_eq!(vfs.snapshot(), before);
+        assert_eq!(vfs.cwd(), p("/gone"));
+        vfs.restore(&empty);
+        assert_eq!(vfs.cwd(), p("/"));
+        assert_eq!(vfs.snapshot().files().count(), 0);
+
+        let vfs = MemVfs::from(before);
+        assert_eq!(read(&vfs, "/keep"), "1");
+    }
+
+    #[test]
+    fn set_modified() {
+        let mut vfs = MemVfs::new().with_file("/f", "");
+        let time = SystemTime::UNIX_EPOCH;
+        vfs.set_modified(p("/f"), time).expect("set");
+        assert_eq!(vfs.metadata(p("/f")).expect("meta").modified, Some(time));
+        assert!(matches!(
+            vfs.set_modified(p("/nope"), time),
+            Err(VfsError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,653 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
+use std::fmt;
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::{
+    PollWatcher, Vfs, VfsChange, VfsDirEntry, VfsError, VfsFileType, VfsMetadata, VfsResult,
+    VfsWatcher, VfsWriteMode,
+};
+
+/// A [`Vfs`] that attaches several backends to one namespace, each at its
+/// own mount point.
+///
+/// Every path goes to the backend of the longest mount point it starts with,
+/// with the mount point cut off: with a backend at `/data`, `/data/a.csv` is
+/// `/a.csv` of that backend. Directories leading to a mount point exist even
+/// where no backend has them, but cannot be written to.
+///
+/// Entries cannot be renamed from one backend to another, see
+/// [`VfsError::CrossesDevices`]; [`copy`](Vfs::copy) streams across.
+///
+/// ```
+/// use std::path::Path;
+/// use esh::{MemVfs, MountTable, Vfs};
+///
+/// let mut vfs = MountTable::new();
+/// vfs.mount("/data", Box::new(MemVfs::from_files([("/a.csv", "x,y\n")]))).unwrap();
+/// vfs.mount("/scratch", Box::new(MemVfs::new())).unwrap();
+///
+/// vfs.copy(Path::new("/data/a.csv"), Path::new("/scratch/a.csv")).unwrap();
+/// assert!(vfs.exists(Path::new("/scratch/a.csv")).unwrap());
+/// assert_eq!(vfs.read_dir(Path::new("/")).unwrap().len(), 2);
+/// ```
+pub struct MountTable {
+    mounts: BTreeMap<PathBuf, Box<dyn Vfs>>,
+    cwd: PathBuf,
+}
+
+impl fmt::Debug for MountTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MountTable")
+            .field("mounts", &self.mounts.keys().collect::<Vec<_>>())
+            .field("cwd", &self.cwd)
+            .finish()
+    }
+}
+
+impl Default for MountTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<Box<dyn Vfs>> for MountTable {
+    /// A table with `vfs` mounted at `/`, in the cwd of `vfs`.
+    fn from(vfs: Box<dyn Vfs>) -> Self {
+        let cwd = vfs.resolve(vfs.cwd());
+        Self {
+            mounts: BTreeMap::from([(PathBuf::from("/"), vfs)]),
+            cwd,
+        }
+    }
+}
+
+impl MountTable {
+    /// Create a table without mounts. Its root is an empty, read-only
+    /// directory until a backend is mounted at `/`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            mounts: BTreeMap::new(),
+            cwd: PathBuf::from("/"),
+        }
+    }
+
+    /// Attach `vfs` at `point`, which may be relative to the cwd. A backend
+    /// mounted at `/` brings its cwd along.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`VfsError::AlreadyExists`] if something is mounted at
+    /// `point` already.
+    pub fn mount(&mut self, point: impl AsRef<Path>, vfs: Box<dyn Vfs>) -> VfsResult<()> {
+        let point = self.resolve(point.as_ref());
+        if self.mounts.contains_key(&point) {
+            return Err(VfsError::AlreadyExists(point));
+        }
+        if point.parent().is_none() && self.mounts.is_empty() {
+            self.cwd = vfs.resolve(vfs.cwd());
+        }
+        self.mounts.insert(point, vfs);
+        Ok(())
+    }
+
+    /// Detach the backend at `point` and hand it back. Backends mounted
+    /// below `point` stay.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if nothing is mounted at `point`, or if the cwd is
+    /// below it.
+    pub fn umount(&mut self, point: impl AsRef<Path>) -> VfsResult<Box<dyn Vfs>> {
+        let point = self.resolve(point.as_ref());
+        if !self.mounts.contains_key(&point) {
+            return Err(io_error(point, ErrorKind::InvalidInput, "not mounted"));
+        }
+        if self.cwd.starts_with(&point) && self.mount_point(&self.cwd) == Some(&point) {
+            return Err(busy(point));
+        }
+        self.mounts.remove(&point).ok_or(VfsError::NotFound(point))
+    }
+
+    /// The mount points, in path order.
+    pub fn mount_points(&self) -> impl Iterator<Item = &Path> {
+        self.mounts.keys().map(PathBuf::as_path)
+    }
+
+    /// The mount point `path` belongs to.
+    fn mount_point(&self, path: &Path) -> Option<&PathBuf> {
+        path.ancestors()
+            .find_map(|a| self.mounts.get_key_value(a).map(|(point, _)| point))
+    }
+
+    /// Whether `path` leads to a mount point, so it exists as a directory.
+    fn is_virtual(&self, path: &Path) -> bool {
+        path.parent().is_none() || self.mounts.keys().any(|m| m != path && m.starts_with(path))
+    }
+
+    /// The names of the directories in `dir` that lead to mount points.
+    fn mount_children(&self, dir: &Path) -> BTreeSet<OsString> {
+        self.mounts
+            .keys()
+            .filter_map(|m| m.strip_prefix(dir).ok()?.iter().next())
+            .map(OsString::from)
+            .collect()
+    }
+
+    /// Refuse to remove or rename `path` while backends are mounted there or
+    /// below.
+    fn check_not_busy(&self, path: &Path) -> VfsResult<()> {
+        if self.mounts.keys().any(|m| m.starts_with(path)) {
+            return Err(busy(path.into()));
+        }
+        Ok(())
+    }
+
+    /// The error for a path outside every mount.
+    fn unrouted(&self, path: PathBuf) -> VfsError {
+        if self.is_virtual(&path) || path.parent().is_some_and(|p| self.is_virtual(p)) {
+            VfsError::ReadOnly(path)
+        } else {
+            VfsError::NotFound(path)
+        }
+    }
+
+    /// Run `f` on the backend of `path`, with the path inside the backend.
+    fn on<T>(&self, path: &Path, f: impl FnOnce(&dyn Vfs, &Path) -> VfsResult<T>) -> VfsResult<T> {
+        let path = self.resolve(path);
+        let Some((point, vfs)) = self
+            .mount_point(&path)
+            .and_then(|point| self.mounts.get_key_value(point))
+        else {
+            return Err(self.unrouted(path));
+        };
+        f(vfs.as_ref(), &inner(point, &path)).map_err(|e| reroot(point, e))
+    }
+
+    /// Like [`on`](Self::on), with mutable access to the backend.
+    fn on_mut<T>(
+        &mut self,
+        path: &Path,
+        f: impl FnOnce(&mut dyn Vfs, &Path) -> VfsResult<T>,
+    ) -> VfsResult<T> {
+        let path = self.resolve(path);
+        let Some(point) = self.mount_point(&path).cloned() else {
+            return Err(self.unrouted(path));
+        };
+        let vfs = self
+            .mounts
+            .get_mut(&point)
+            .ok_or_else(|| VfsError::NotFound(path.clone()))?;
+        f(vfs.as_mut(), &inner(&point, &path)).map_err(|e| reroot(&point, e))
+    }
+}
+
+/// The watcher of the backend at `point`, reporting table paths.
+struct MountedWatcher {
+    point: PathBuf,
+    watcher: Box<dyn VfsWatcher>,
+}
+
+impl VfsWatcher for MountedWatcher {
+    fn wait(&mut self, vfs: &dyn Vfs, timeout: std::time::Duration) -> VfsResult<Vec<VfsChange>> {
+        let backend = vfs
+            .mounted(&self.point)
+            .ok_or_else(|| VfsError::NotFound(self.point.clone()))?;
+        let changes = self
+            .watcher
+            .wait(backend, timeout)
+            .map_err(|e| reroot(&self.point, e))?;
+        Ok(changes
+            .into_iter()
+            .map(|change| VfsChange {
+                path: outer(&self.point, &change.path),
+                ..change
+            })
+            .collect())
+    }
+}
+
+/// `path` below `point`, as seen by the backend mounted there.
+fn inner(point: &Path, path: &Path) -> PathBuf {
+    let rest = path.strip_prefix(point).unwrap_or(path);
+    Path::new("/").join(rest)
+}
+
+/// The backend path `path` of the backend at `point`, as seen in the table.
+fn outer(point: &Path, path: &Path) -> PathBuf {
+    match path.strip_prefix("/") {
+        Ok(rest) if !rest.as_os_str().is_empty() => point.join(rest),
+        _ => point.to_path_buf(),
+    }
+}
+
+/// Rewrite the path of an error of the backend at `point`.
+fn reroot(point: &Path, e: VfsError) -> VfsError {
+    let outer = |path: PathBuf| outer(point, &path);
+    match e {
+        VfsError::NotFound(path) => VfsError::NotFound(outer(path)),
+        VfsError::NotADirectory(path) => VfsError::NotADirectory(outer(path)),
+        VfsError::IsADirectory(path) => VfsError::IsADirectory(outer(path)),
+        VfsError::AlreadyExists(path) => VfsError::AlreadyExists(outer(path)),
+        VfsError::NotEmpty(path) => VfsError::NotEmpty(outer(path)),
+        VfsError::PermissionDenied(path) => VfsError::PermissionDenied(outer(path)),
+        VfsError::ReadOnly(path) => VfsError::ReadOnly(outer(path)),
+        VfsError::CrossesDevices(path) => VfsError::CrossesDevices(outer(path)),
+        VfsError::Unsupported { op, path } => VfsError::Unsupported {
+            op,
+            path: outer(path),
+        },
+        VfsError::Io { path, source } => VfsError::Io {
+            path: outer(path),
+            source,
+        },
+    }
+}
+
+fn io_error(path: PathBuf, kind: ErrorKind, message: &str) -> VfsError {
+    VfsError::Io {
+        path,
+        source: std::io::Error::new(kind, message),
+    }
+}
+
+fn busy(path: PathBuf) -> VfsError {
+    io_error(path, ErrorKind::ResourceBusy, "mount point busy")
+}
+
+const fn virtual_dir() -> VfsMetadata {
+    VfsMetadata {
+        file_type: VfsFileType::Dir,
+        len: 0,
+        modified: None,
+        readonly: true,
+        mode: Some(0o555),
+    }
+}
+
+impl Vfs for MountTable {
+    fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    fn set_cwd(&mut self, path: &Path) -> VfsResult<()> {
+        self.cwd = self.resolve(path);
+        Ok(())
+    }
+
+    fn read_dir(&self, path: &Path) -> VfsResult<Vec<VfsDirEntry>> {
+        let path = self.resolve(path);
+        let is_virtual = self.is_virtual(&path);
+        let listed = match self.on(&path, |vfs, p| vfs.read_dir(p)) {
+            Ok(entries) => entries,
+            Err(VfsError::NotFound(_) | VfsError::NotADirectory(_) | VfsError::ReadOnly(_))
+                if is_virtual =>
+            {
+                Vec::new()
+            }
+            Err(e) => return Err(e),
+        };
+        let mut entries: BTreeMap<_, _> = listed
+            .into_iter()
+            .map(|entry| (entry.name.clone(), entry))
+            .collect();
+        // Mount points hide whatever the backend below has there
+        for name in self.mount_children(&path) {
+            entries.insert(
+                name.clone(),
+                VfsDirEntry {
+                    name,
+                    file_type: VfsFileType::Dir,
+                },
+            );
+        }
+        Ok(entries.into_values().collect())
+    }
+
+    fn metadata(&self, path: &Path) -> VfsResult<VfsMetadata> {
+        let path = self.resolve(path);
+        match self.on(&path, |vfs, p| vfs.metadata(p)) {
+            Err(VfsError::NotFound(_) | VfsError::NotADirectory(_) | VfsError::ReadOnly(_))
+                if self.is_virtual(&path) =>
+            {
+                Ok(virtual_dir())
+            }
+            result => result,
+        }
+    }
+
+    fn open_read(&self, path: &Path) -> VfsResult<Box<dyn Read + Send>> {
+        let path = self.resolve(path);
+        match self.on(&path, |vfs, p| vfs.open_read(p)) {
+            Err(VfsError::ReadOnly(_)) => Err(VfsError::IsADirectory(path)),
+            result => result,
+        }
+    }
+
+    fn open_write(&mut self, path: &Path, mode: VfsWriteMode) -> VfsResult<Box<dyn Write + Send>> {
+        self.on_mut(path, |vfs, p| vfs.open_write(p, mode))
+    }
+
+    fn create_dir(&mut self, path: &Path) -> VfsResult<()> {
+        self.on_mut(path, |vfs, p| vfs.create_dir(p))
+    }
+
+    fn create_dir_all(&mut self, path: &Path) -> VfsResult<()> {
+        if self.metadata(path).is_ok_and(|meta| meta.is_dir()) {
+            return Ok(());
+        }
+        self.on_mut(path, |vfs, p| vfs.create_dir_all(p))
+    }
+
+    fn remove_file(&mut self, path: &Path) -> VfsResult<()> {
+        self.on_mut(path, |vfs, p| vfs.remove_file(p))
+    }
+
+    fn remove_dir(&mut self, path: &Path) -> VfsResult<()> {
+        self.check_not_busy(&self.resolve(path))?;
+        self.on_mut(path, |vfs, p| vfs.remove_dir(p))
+    }
+
+    fn remove_dir_all(&mut self, path: &Path) -> VfsResult<()> {
+        self.check_not_busy(&self.resolve(path))?;
+        self.on_mut(path, |vfs, p| vfs.remove_dir_all(p))
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path) -> VfsResult<()> {
+        let from = self.resolve(from);
+        let to = self.resolve(to);
+        self.check_not_busy(&from)?;
+        self.check_not_busy(&to)?;
+        let Some(point) = self.mount_point(&from).cloned() else {
+            return Err(self.unrouted(from));
+        };
+        if self.mount_point(&to) != Some(&point) {
+            return Err(VfsError::CrossesDevices(from));
+        }
+        let to = inner(&point, &to);
+        self.on_mut(&from, |vfs, p| vfs.rename(p, &to))
+    }
+
+    fn copy(&mut self, from: &Path, to: &Path) -> VfsResult<u64> {
+        let from = self.resolve(from);
+        let to = self.resolve(to);
+        let same_mount = self
+            .mount_point(&from)
+            .filter(|&point| self.mount_point(&to) == Some(point))
+            .cloned();
+        if let Some(point) = same_mount {
+            let to = inner(&point, &to);
+            return self.on_mut(&from, |vfs, p| vfs.copy(p, &to));
+        }
+        let mut reader = self.open_read(&from)?;
+        let mut writer = self.open_write(&to, VfsWriteMode::Truncate)?;
+        let io_err = |source| VfsError::Io {
+            path: to.clone(),
+            source,
+        };
+        let copied = std::io::copy(&mut reader, &mut writer).map_err(io_err)?;
+        writer.flush().map_err(io_err)?;
+        Ok(copied)
+    }
+
+    fn set_modified(&mut self, path: &Path, time: SystemTime) -> VfsResult<()> {
+        self.on_mut(path, |vfs, p| vfs.set_modified(p, time))
+    }
+
+    fn watch(&self, path: &Path) -> VfsResult<Box<dyn VfsWatcher>> {
+        let path = self.resolve(path);
+        // Only polling sees several backends at once
+        if self
+            .mounts
+            .keys()
+            .any(|m| m != &path && m.starts_with(&path))
+        {
+            return Ok(Box::new(PollWatcher::new(self, &path)?));
+        }
+        let Some((point, vfs)) = self
+            .mount_point(&path)
+            .and_then(|point| self.mounts.get_key_value(point))
+        else {
+            return Err(self.unrouted(path));
+        };
+        let watcher = vfs
+            .watch(&inner(point, &path))
+            .map_err(|e| reroot(point, e))?;
+        Ok(Box::new(MountedWatcher {
+            point: point.clone(),
+            watcher,
+        }))
+    }
+
+    fn mounted(&self, point: &Path) -> Option<&dyn Vfs> {
+        self.mounts.get(point).map(AsRef::as_ref)
+    }
+
+    fn changes(&self) -> VfsResult<Vec<VfsChange>> {
+        let mut all = None;
+        for (point, vfs) in &self.mounts {
+            match vfs.changes() {
+                Ok(changes) => all
+                    .get_or_insert_with(Vec::new)
+                    .extend(changes.into_iter().map(|change| VfsChange {
+                        path: outer(point, &change.path),
+                        ..change
+                    })),
+                Err(VfsError::Unsupported { .. }) => {}
+                Err(e) => return Err(reroot(point, e)),
+            }
+        }
+        let mut all = all.ok_or_else(|| VfsError::unsupported("changes", "/"))?;
+        all.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(all)
+    }
+
+    fn commit(&mut self) -> VfsResult<usize> {
+        let mut total = None;
+        for (point, vfs) in &mut self.mounts {
+            match vfs.commit() {
+                Ok(count) => *total.get_or_insert(0) += count,
+                Err(VfsError::Unsupported { .. }) => {}
+                Err(e) => return Err(reroot(point, e)),
+            }
+        }
+        total.ok_or_else(|| VfsError::unsupported("commit", "/"))
+    }
+
+    fn discard(&mut self) -> VfsResult<()> {
+        let mut supported = false;
+        for (point, vfs) in &mut self.mounts {
+            match vfs.discard() {
+                Ok(()) => supported = true,
+                Err(VfsError::Unsupported { .. }) => {}
+                Err(e) => return Err(reroot(point, e)),
+            }
+        }
+        if !supported {
+            return Err(VfsError::unsupported("discard", "/"));
+        }
+        if !self.metadata(&self.cwd).is_ok_and(|meta| meta.is_dir()) {
+            self.cwd = PathBuf::from("/");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::vfs::{HostVfs, MemVfs, OverlayVfs};
+    use std::time::Duration;
+
+    fn p(path: &str) -> &Path {
+        Path::new(path)
+    }
+
+    fn table() -> MountTable {
+        let mut vfs = MountTable::new();
+        vfs.mount("/", Box::new(MemVfs::from_files([("/etc/motd", "hi\n")])))
+            .expect("mount /");
+        vfs.mount(
+            "/data",
+            Box::new(MemVfs::from_files([
+                ("/a.csv", "x,y\n"),
+                ("/sub/b.csv", ""),
+            ])),
+        )
+        .expect("mount /data");
+        vfs.mount("/mnt/scratch", Box::new(MemVfs::new()))
+            .expect("mount /mnt/scratch");
+        vfs
+    }
+
+    fn names(vfs: &dyn Vfs, path: &str) -> Vec<String> {
+        vfs.read_dir(p(path))
+            .expect("read_dir")
+            .into_iter()
+            .map(|e| e.name.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn watchers_report_table_paths() {
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        let mut vfs = table();
+        vfs.mount(
+            "/host",
+            Box::new(HostVfs::new(dir.path()).expect("host vfs")),
+        )
+        .expect("mount /host");
+        let added = |vfs: &MountTable, watcher: &mut Box<dyn VfsWatcher>| {
+            watcher
+                .wait(vfs, Duration::from_secs(5))
+                .expect("wait")
+                .into_iter()
+                .map(|change| change.path)
+                .collect::<Vec<_>>()
+        };
+
+        let mut watcher = vfs.watch(p("/host")).expect("watch");
+        std::fs::write(dir.path().join("a"), "").expect("write");
+        assert_eq!(added(&vfs, &mut watcher), [p("/host/a")]);
+
+        // With backends below, the whole table is polled
+        let mut watcher = vfs.watch(p("/")).expect("watch");
+        std::fs::write(dir.path().join("b"), "").expect("write");
+        assert_eq!(added(&vfs, &mut watcher), [p("/host/b")]);
+
+        assert!(matches!(
+            vfs.watch(p("/data/missing")).map(drop),
+            Err(VfsError::NotFound(path)) if path == p("/data/missing")
+        ));
+    }
+
+    #[test]
+    fn paths_go_to_the_longest_mount_point() {
+        let mut vfs = table();
+        assert_eq!(names(&vfs, "/"), ["data", "etc", "mnt"]);
+        assert_eq!(names(&vfs, "/data"), ["a.csv", "sub"]);
+        assert_eq!(names(&vfs, "/mnt"), ["scratch"]);
+        assert!(vfs.metadata(p("/mnt")).expect("meta").readonly);
+        assert!(vfs.metadata(p("/data/sub")).expect("meta").is_dir());
+
+        vfs.cd(p("/data/sub")).expect("cd");
+        vfs.open_write(p("new.csv"), VfsWriteMode::CreateNew)
+            .expect("create");
+        assert!(vfs.exists(p("/data/sub/new.csv")).expect("exists"));
+        let scratch = vfs.umount("/mnt/scratch").expect("umount");
+        assert!(scratch.read_dir(p("/")).expect("read_dir").is_empty());
+        assert_eq!(names(&vfs, "/"), ["data", "etc"]);
+    }
+
+    #[test]
+    fn errors_name_the_table_path() {
+        let mut vfs = table();
+        let err = vfs.metadata(p("/data/nope")).expect_err("missing");
+        assert_eq!(err.to_string(), "/data/nope: no such file or directory");
+        let err = vfs.create_dir(p("/data")).expect_err("mount point");
+        assert_eq!(err.to_string(), "/data: already exists");
+    }
+
+    #[test]
+    fn namespace_without_root_mount_is_read_only() {
+        let mut vfs = MountTable::new();
+        assert!(names(&vfs, "/").is_empty());
+        vfs.mount("/a/b", Box::new(MemVfs::new())).expect("mount");
+        assert_eq!(names(&vfs, "/"), ["a"]);
+        assert!(matches!(
+            vfs.create_dir(p("/x")),
+            Err(VfsError::ReadOnly(_))
+        ));
+        assert!(matches!(
+            vfs.open_read(p("/a")),
+            Err(VfsError::IsADirectory(_))
+        ));
+        assert!(matches!(
+            vfs.metadata(p("/x/y")),
+            Err(VfsError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn renames_stay_within_a_backend() {
+        let mut vfs = table();
+        vfs.rename(p("/data/a.csv"), p("/data/sub/a.csv"))
+            .expect("rename");
+        assert!(matches!(
+            vfs.rename(p("/data/sub"), p("/sub")),
+            Err(VfsError::CrossesDevices(_))
+        ));
+        assert_eq!(
+            vfs.copy(p("/data/sub/a.csv"), p("/mnt/scratch/a.csv"))
+                .expect("copy"),
+            4
+        );
+        assert!(vfs.exists(p("/mnt/scratch/a.csv")).expect("exists"));
+    }
+
+    #[test]
+    fn mount_points_are_busy() {
+        let mut vfs = table();
+        assert!(matches!(
+            vfs.mount("/data", Box::new(MemVfs::new())),
+            Err(VfsError::AlreadyExists(_))
+        ));
+        let err = vfs.remove_dir_all(p("/mnt")).expect_err("busy");
+        assert_eq!(err.to_string(), "/mnt: mount point busy");
+        assert!(vfs.rename(p("/data"), p("/d")).is_err());
+
+        vfs.cd(p("/data/sub")).expect("cd");
+        assert!(vfs.umount("/data").is_err());
+        assert!(vfs.umount("/mnt/scratch").is_ok());
+        let Err(err) = vfs.umount("/mnt/scratch") else {
+            panic!("unmounted twice");
+        };
+        assert_eq!(err.to_string(), "/mnt/scratch: not mounted");
+        assert_eq!(vfs.mount_points().collect::<Vec<_>>(), [p("/"), p("/data")]);
+    }
+
+    #[test]
+    fn changes_are_collected_from_every_mount() {
+        let mut vfs = table();
+        assert!(matches!(
+            vfs.changes(),
+            Err(VfsError::Unsupported { op: "changes", .. })
+        ));
+
+        let lower = MemVfs::from_files([("/keep.txt", "")]);
+        vfs.mount("/ovl", Box::new(OverlayVfs::new(lower, MemVfs::new())))
+            .expect("mount");
+        vfs.remove_file(p("/ovl/keep.txt")).expect("rm");
+        let changes = vfs.changes().expect("changes");
+        let [change] = changes.as_slice() else {
+            panic!("expected one change, got {changes:?}");
+        };
+        assert_eq!(change.path, p("/ovl/keep.txt"));
+        assert_eq!(vfs.commit().expect("commit"), 1);
+        assert!(!vfs.exists(p("/ovl/keep.txt")).expect("exists"));
+    }
+}
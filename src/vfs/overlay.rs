@@ -0,0 +1,669 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::{
+    Vfs, VfsChange, VfsChangeKind, VfsDirEntry, VfsError, VfsFileType, VfsMetadata, VfsResult,
+    VfsWriteMode,
+};
+
+/// A copy-on-write [`Vfs`] that stacks a writable upper layer on a lower
+/// layer, which it never writes to until [`commit`](Vfs::commit).
+///
+/// Reads see the upper layer where it has an entry and the lower layer
+/// everywhere else. Writing to a file of the lower layer first copies it up,
+/// removing an entry of the lower layer records a whiteout that hides it and
+/// everything below it. [`changes`](Vfs::changes) lists the difference to the
+/// lower layer, [`commit`](Vfs::commit) writes it down and
+/// [`discard`](Vfs::discard) drops it.
+///
+/// ```
+/// use std::path::Path;
+/// use esh::{MemVfs, OverlayVfs, Vfs, VfsChangeKind};
+///
+/// let lower = MemVfs::from_files([("/etc/motd", "hello\n")]);
+/// let mut vfs = OverlayVfs::new(lower, MemVfs::new());
+/// vfs.remove_file(Path::new("/etc/motd")).unwrap();
+///
+/// let changes = vfs.changes().unwrap();
+/// assert_eq!(changes[0].kind, VfsChangeKind::Removed);
+/// assert!(vfs.lower().exists(Path::new("/etc/motd")).unwrap());
+///
+/// vfs.discard().unwrap();
+/// assert!(vfs.exists(Path::new("/etc/motd")).unwrap());
+/// ```
+#[derive(Debug)]
+pub struct OverlayVfs<L, U> {
+    lower: L,
+    upper: U,
+    /// Paths of the lower layer that were removed
+    whiteouts: BTreeSet<PathBuf>,
+    cwd: PathBuf,
+}
+
+impl<L: Vfs, U: Vfs> OverlayVfs<L, U> {
+    /// Stack `upper` on `lower`, starting in the cwd of `lower`. `upper` is
+    /// expected to be empty, for example a fresh [`MemVfs`](super::MemVfs).
+    pub fn new(lower: L, upper: U) -> Self {
+        let cwd = lower.cwd().to_path_buf();
+        Self {
+            lower,
+            upper,
+            whiteouts: BTreeSet::new(),
+            cwd,
+        }
+    }
+
+    /// The lower layer, without the pending changes.
+    pub const fn lower(&self) -> &L {
+        &self.lower
+    }
+
+    /// The upper layer with the added and modified entries.
+    pub const fn upper(&self) -> &U {
+        &self.upper
+    }
+
+    /// Split the overlay into its lower and upper layer. Whiteouts are lost.
+    pub fn into_layers(self) -> (L, U) {
+        (self.lower, self.upper)
+    }
+
+    /// Whether `path` or one of its parents was removed from the lower layer.
+    fn whited_out(&self, path: &Path) -> bool {
+        path.ancestors().any(|p| self.whiteouts.contains(p))
+    }
+
+    fn in_upper(&self, path: &Path) -> VfsResult<Option<VfsMetadata>> {
+        found(self.upper.metadata(path))
+    }
+
+    fn in_lower(&self, path: &Path) -> VfsResult<Option<VfsMetadata>> {
+        if self.whited_out(path) {
+            return Ok(None);
+        }
+        found(self.lower.metadata(path))
+    }
+
+    /// The metadata of the merged view, `None` if `path` does not exist.
+    fn lookup(&self, path: &Path) -> VfsResult<Option<VfsMetadata>> {
+        self.in_upper(path)?
+            .map_or_else(|| self.in_lower(path), |meta| Ok(Some(meta)))
+    }
+
+    /// Make sure the parent directories of `path` exist in the upper layer.
+    fn copy_up_parents(&mut self, path: &Path) -> VfsResult<()> {
+        let Some(parent) = path.parent() else {
+            return Ok(());
+        };
+        match self.lookup(parent)? {
+            Some(meta) if meta.is_dir() => self.upper.create_dir_all(parent),
+            Some(_) => Err(VfsError::NotADirectory(parent.into())),
+            None => Err(VfsError::NotFound(parent.into())),
+        }
+    }
+
+    /// Copy the entry at `path` from the lower layer up, unless the upper
+    /// layer has it already.
+    fn copy_up(&mut self, path: &Path) -> VfsResult<()> {
+        if self.in_upper(path)?.is_some() {
+            return Ok(());
+        }
+        let meta = self
+            .in_lower(path)?
+            .ok_or_else(|| VfsError::NotFound(path.into()))?;
+        self.copy_up_parents(path)?;
+        if meta.is_dir() {
+            self.upper.create_dir(path)?;
+        } else {
+            copy_file(&self.lower, &mut self.upper, path)?;
+        }
+        match meta
+            .modified
+            .map(|time| self.upper.set_modified(path, time))
+        {
+            // Copies get a new mtime where the upper layer cannot keep it
+            Some(Err(VfsError::Unsupported { .. })) | None => Ok(()),
+            Some(result) => result,
+        }
+    }
+
+    /// Record the removal of `path` from the lower layer, if it has it.
+    fn white_out(&mut self, path: &Path) -> VfsResult<()> {
+        if self.in_lower(path)?.is_some() {
+            // Whiteouts below are covered by the new one
+            self.whiteouts.retain(|w| !w.starts_with(path));
+            self.whiteouts.insert(path.to_path_buf());
+        }
+        Ok(())
+    }
+
+    /// Copy the entry at `from` to `to` within the merged view.
+    fn copy_tree(&mut self, from: &Path, to: &Path) -> VfsResult<()> {
+        if !self.metadata(from)?.is_dir() {
+            return self.copy(from, to).map(drop);
+        }
+        self.create_dir(to)?;
+        for entry in self.read_dir(from)? {
+            self.copy_tree(&from.join(&entry.name), &to.join(&entry.name))?;
+        }
+        Ok(())
+    }
+
+    /// Whether the directory `dir` may differ from the lower layer.
+    fn touched(&self, dir: &Path) -> VfsResult<bool> {
+        if self.in_upper(dir)?.is_some() {
+            return Ok(true);
+        }
+        Ok(self
+            .whiteouts
+            .range(dir.to_path_buf()..)
+            .next()
+            .is_some_and(|w| w.starts_with(dir)))
+    }
+
+    /// Compare the directory `dir` of the merged view with the same
+    /// directory of the lower layer.
+    fn diff_dir(&self, dir: &Path, changes: &mut Vec<VfsChange>) -> VfsResult<()> {
+        let merged = types(self.read_dir(dir)?);
+        let lower = types(self.lower.read_dir(dir)?);
+        let names: BTreeSet<_> = merged.keys().chain(lower.keys()).collect();
+        for name in names {
+            let path = dir.join(name);
+            match (merged.get(name), lower.get(name)) {
+                (Some(&new), None) => self.added(&path, new, changes)?,
+                (None, Some(&old)) => changes.push(change(VfsChangeKind::Removed, path, old)),
+                (Some(&new), Some(&old)) if new != old => {
+                    changes.push(change(VfsChangeKind::Modified, path.clone(), new));
+                    if new == VfsFileType::Dir {
+                        self.added_children(&path, changes)?;
+                    }
+                }
+                (Some(VfsFileType::Dir), Some(_)) => {
+                    if self.touched(&path)? {
+                        self.diff_dir(&path, changes)?;
+                    }
+                }
+                (Some(&new), Some(_)) => {
+                    if self.in_upper(&path)?.is_some() {
+                        changes.push(change(VfsChangeKind::Modified, path, new));
+                    }
+                }
+                (None, None) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn added(
+        &self,
+        path: &Path,
+        file_type: VfsFileType,
+        changes: &mut Vec<VfsChange>,
+    ) -> VfsResult<()> {
+        changes.push(change(VfsChangeKind::Added, path.to_path_buf(), file_type));
+        if file_type == VfsFileType::Dir {
+            self.added_children(path, changes)?;
+        }
+        Ok(())
+    }
+
+    fn added_children(&self, dir: &Path, changes: &mut Vec<VfsChange>) -> VfsResult<()> {
+        for (name, file_type) in types(self.read_dir(dir)?) {
+            self.added(&dir.join(name), file_type, changes)?;
+        }
+        Ok(())
+    }
+}
+
+/// Turn [`VfsError::NotFound`] into `None`. A file where a directory was
+/// expected means the path does not exist either.
+fn found(result: VfsResult<VfsMetadata>) -> VfsResult<Option<VfsMetadata>> {
+    match result {
+        Ok(meta) => Ok(Some(meta)),
+        Err(VfsError::NotFound(_) | VfsError::NotADirectory(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Directory entries by name, for comparing listings.
+fn types(entries: Vec<VfsDirEntry>) -> BTreeMap<OsString, VfsFileType> {
+    entries.into_iter().map(|e| (e.name, e.file_type)).collect()
+}
+
+const fn change(kind: VfsChangeKind, path: PathBuf, file_type: VfsFileType) -> VfsChange {
+    VfsChange {
+        kind,
+        path,
+        file_type,
+    }
+}
+
+/// Stream the file at `path` from one layer to the other.
+fn copy_file(from: &dyn Vfs, to: &mut dyn Vfs, path: &Path) -> VfsResult<()> {
+    let mut reader = from.open_read(path)?;
+    let mut writer = to.open_write(path, VfsWriteMode::Truncate)?;
+    let io_err = |source| VfsError::Io {
+        path: path.into(),
+        source,
+    };
+    std::io::copy(&mut reader, &mut writer).map_err(io_err)?;
+    writer.flush().map_err(io_err)
+}
+
+impl<L: Vfs, U: Vfs> Vfs for OverlayVfs<L, U> {
+    fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    fn set_cwd(&mut self, path: &Path) -> VfsResult<()> {
+        self.cwd = self.resolve(path);
+        Ok(())
+    }
+
+    fn read_dir(&self, path: &Path) -> VfsResult<Vec<VfsDirEntry>> {
+        let path = self.resolve(path);
+        let upper = self.in_upper(&path)?;
+        let lower = match &upper {
+            Some(meta) if !meta.is_dir() => return Err(VfsError::NotADirectory(path)),
+            _ => self.in_lower(&path)?,
+        };
+        let mut entries = BTreeMap::new();
+        match lower {
+            Some(meta) if meta.is_dir() => {
+                for entry in self.lower.read_dir(&path)? {
+                    if !self.whiteouts.contains(&path.join(&entry.name)) {
+                        entries.insert(entry.name.clone(), entry);
+                    }
+                }
+            }
+            Some(_) if upper.is_none() => return Err(VfsError::NotADirectory(path)),
+            None if upper.is_none() => return Err(VfsError::NotFound(path)),
+            _ => {}
+        }
+        if upper.is_some() {
+            // The upper layer wins where both have an entry
+            for entry in self.upper.read_dir(&path)? {
+                entries.insert(entry.name.clone(), entry);
+            }
+        }
+        Ok(entries.into_values().collect())
+    }
+
+    fn metadata(&self, path: &Path) -> VfsResult<VfsMetadata> {
+        let path = self.resolve(path);
+        self.lookup(&path)?.ok_or(VfsError::NotFound(path))
+    }
+
+    fn open_read(&self, path: &Path) -> VfsResult<Box<dyn Read + Send>> {
+        let path = self.resolve(path);
+        if self.in_upper(&path)?.is_some() {
+            self.upper.open_read(&path)
+        } else if self.in_lower(&path)?.is_some() {
+            self.lower.open_read(&path)
+        } else {
+            Err(VfsError::NotFound(path))
+        }
+    }
+
+    fn open_write(&mut self, path: &Path, mode: VfsWriteMode) -> VfsResult<Box<dyn Write + Send>> {
+        let path = self.resolve(path);
+        match (self.lookup(&path)?, mode) {
+            (Some(meta), _) if meta.is_dir() => return Err(VfsError::IsADirectory(path)),
+            (Some(_), VfsWriteMode::CreateNew) => return Err(VfsError::AlreadyExists(path)),
+            (Some(_), VfsWriteMode::Append) => self.copy_up(&path)?,
+            (Some(_), VfsWriteMode::Truncate) | (None, _) => self.copy_up_parents(&path)?,
+        }
+        self.upper.open_write(&path, mode)
+    }
+
+    fn create_dir(&mut self, path: &Path) -> VfsResult<()> {
+        let path = self.resolve(path);
+        if self.lookup(&path)?.is_some() {
+            return Err(VfsError::AlreadyExists(path));
+        }
+        self.copy_up_parents(&path)?;
+        // A whiteout at `path` stays and hides the old contents
+        self.upper.create_dir(&path)
+    }
+
+    fn remove_file(&mut self, path: &Path) -> VfsResult<()> {
+        let path = self.resolve(path);
+        if self.metadata(&path)?.is_dir() {
+            return Err(VfsError::IsADirectory(path));
+        }
+        if self.in_upper(&path)?.is_some() {
+            self.upper.remove_file(&path)?;
+        }
+        self.white_out(&path)
+    }
+
+    fn remove_dir(&mut self, path: &Path) -> VfsResult<()> {
+        let path = self.resolve(path);
+        if !self.read_dir(&path)?.is_empty() {
+            return Err(VfsError::NotEmpty(path));
+        }
+        self.remove_dir_all(&path)
+    }
+
+    fn remove_dir_all(&mut self, path: &Path) -> VfsResult<()> {
+        let path = self.resolve(path);
+        if !self.metadata(&path)?.is_dir() {
+            return Err(VfsError::NotADirectory(path));
+        }
+        if path.parent().is_none() {
+            return Err(VfsError::PermissionDenied(path));
+        }
+        if self.in_upper(&path)?.is_some() {
+            self.upper.remove_dir_all(&path)?;
+        }
+        self.white_out(&path)
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path) -> VfsResult<()> {
+        let from = self.resolve(from);
+        let to = self.resolve(to);
+        let moving_dir = self.metadata(&from)?.is_dir();
+        if from == to {
+            return Ok(());
+        }
+        if from.parent().is_none() || to.starts_with(&from) {
+            return Err(VfsError::PermissionDenied(from));
+        }
+        if let Some(existing) = self.lookup(&to)? {
+            match (existing.is_dir(), moving_dir) {
+                (true, true) => self.remove_dir(&to)?,
+                (true, false) => return Err(VfsError::IsADirectory(to)),
+                (false, true) => return Err(VfsError::NotADirectory(to)),
+                (false, false) => self.remove_file(&to)?,
+            }
+        }
+        // Entries of the lower layer cannot move, so they are copied up
+        self.copy_tree(&from, &to)?;
+        if moving_dir {
+            self.remove_dir_all(&from)
+        } else {
+            self.remove_file(&from)
+        }
+    }
+
+    fn set_modified(&mut self, path: &Path, time: SystemTime) -> VfsResult<()> {
+        let path = self.resolve(path);
+        self.copy_up(&path)?;
+        self.upper.set_modified(&path, time)
+    }
+
+    fn changes(&self) -> VfsResult<Vec<VfsChange>> {
+        let mut changes = Vec::new();
+        self.diff_dir(Path::new("/"), &mut changes)?;
+        Ok(changes)
+    }
+
+    fn commit(&mut self) -> VfsResult<usize> {
+        let changes = self.changes()?;
+        for change in &changes {
+            let path = change.path.as_path();
+            let lower_is_dir = found(self.lower.metadata(path))?.map(|meta| meta.is_dir());
+            let new_is_dir = change.file_type == VfsFileType::Dir;
+            // Files are overwritten in place, anything else is replaced
+            let keep = change.kind != VfsChangeKind::Removed && lower_is_dir == Some(new_is_dir);
+            match lower_is_dir {
+                Some(true) if !keep => self.lower.remove_dir_all(path)?,
+                Some(false) if !keep => self.lower.remove_file(path)?,
+                _ => {}
+            }
+            match change.kind {
+                VfsChangeKind::Removed => {}
+                _ if !new_is_dir => copy_file(&self.upper, &mut self.lower, path)?,
+                _ if !keep => self.lower.create_dir(path)?,
+                _ => {}
+            }
+        }
+        self.discard()?;
+        Ok(changes.len())
+    }
+
+    fn discard(&mut self) -> VfsResult<()> {
+        let root = Path::new("/");
+        for entry in self.upper.read_dir(root)? {
+            let path = root.join(&entry.name);
+            if entry.file_type == VfsFileType::Dir {
+                self.upper.remove_dir_all(&path)?;
+            } else {
+                self.upper.remove_file(&path)?;
+            }
+        }
+        self.whiteouts.clear();
+        let cwd = self.cwd.clone();
+        if !self.lookup(&cwd)?.is_some_and(|meta| meta.is_dir()) {
+            self.cwd = root.to_path_buf();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemVfs;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn p(path: &str) -> &Path {
+        Path::new(path)
+    }
+
+    fn overlay() -> OverlayVfs<MemVfs, MemVfs> {
+        let lower = MemVfs::from_files([
+            ("/etc/motd", "hello\n"),
+            ("/etc/hosts", "localhost\n"),
+            ("/data/a.txt", "a\n"),
+            ("/data/sub/b.txt", "b\n"),
+        ]);
+        OverlayVfs::new(lower, MemVfs::new())
+    }
+
+    fn read(vfs: &dyn Vfs, path: &str) -> String {
+        let mut s = String::new();
+        vfs.open_read(p(path))
+            .expect("open")
+            .read_to_string(&mut s)
+            .expect("read");
+        s
+    }
+
+    fn write(vfs: &mut dyn Vfs, path: &str, mode: VfsWriteMode, data: &str) {
+        vfs.open_write(p(path), mode)
+            .expect("open")
+            .write_all(data.as_bytes())
+            .expect("write");
+    }
+
+    fn names(vfs: &dyn Vfs, path: &str) -> Vec<String> {
+        vfs.read_dir(p(path))
+            .expect("read_dir")
+            .into_iter()
+            .map(|e| e.name.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    fn changes(vfs: &dyn Vfs) -> Vec<String> {
+        vfs.changes()
+            .expect("changes")
+            .into_iter()
+            .map(|c| {
+                let kind = match c.kind {
+                    VfsChangeKind::Added => 'A',
+                    VfsChangeKind::Modified => 'M',
+                    VfsChangeKind::Removed => 'D',
+                };
+                format!("{kind} {}", c.path.display())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn writes_go_to_the_upper_layer() {
+        let mut vfs = overlay();
+        write(&mut vfs, "/etc/motd", VfsWriteMode::Append, "world\n");
+        write(&mut vfs, "/etc/new", VfsWriteMode::CreateNew, "new\n");
+
+        assert_eq!(read(&vfs, "/etc/motd"), "hello\nworld\n");
+        assert_eq!(read(vfs.lower(), "/etc/motd"), "hello\n");
+        assert_eq!(names(&vfs, "/etc"), ["hosts", "motd", "new"]);
+        assert_eq!(names(vfs.upper(), "/etc"), ["motd", "new"]);
+        assert!(!vfs.lower().exists(p("/etc/new")).expect("exists"));
+        assert_eq!(changes(&vfs), ["M /etc/motd", "A /etc/new"]);
+    }
+
+    #[test]
+    fn whiteouts_hide_lower_entries() {
+        let mut vfs = overlay();
+        vfs.remove_file(p("/etc/motd")).expect("rm");
+        vfs.remove_dir_all(p("/data/sub")).expect("rm -r");
+
+        assert!(!vfs.exists(p("/etc/motd")).expect("exists"));
+        assert!(!vfs.exists(p("/data/sub/b.txt")).expect("exists"));
+        assert!(matches!(
+            vfs.open_read(p("/data/sub/b.txt")),
+            Err(VfsError::NotFound(_))
+        ));
+        assert_eq!(names(&vfs, "/data"), ["a.txt"]);
+        assert!(vfs.lower().exists(p("/data/sub/b.txt")).expect("exists"));
+        assert_eq!(changes(&vfs), ["D /data/sub", "D /etc/motd"]);
+
+        assert!(matches!(
+            vfs.remove_dir(p("/data")),
+            Err(VfsError::NotEmpty(_))
+        ));
+        assert!(matches!(
+            vfs.remove_dir_all(p("/")),
+            Err(VfsError::PermissionDenied(_))
+        ));
+    }
+
+    #[test]
+    fn recreated_directories_hide_the_old_contents() {
+        let mut vfs = overlay();
+        vfs.remove_dir_all(p("/data")).expect("rm -r");
+        vfs.create_dir(p("/data")).expect("mkdir");
+        write(&mut vfs, "/data/a.txt", VfsWriteMode::CreateNew, "new a\n");
+
+        assert_eq!(names(&vfs, "/data"), ["a.txt"]);
+        assert_eq!(read(&vfs, "/data/a.txt"), "new a\n");
+        assert_eq!(changes(&vfs), ["M /data/a.txt", "D /data/sub"]);
+
+        // A file replacing a directory
+        vfs.remove_dir_all(p("/data")).expect("rm -r");
+        write(&mut vfs, "/data", VfsWriteMode::CreateNew, "flat\n");
+        assert!(!vfs.exists(p("/data/a.txt")).expect("exists"));
+        assert_eq!(changes(&vfs), ["M /data"]);
+    }
+
+    #[test]
+    fn rename_copies_up_and_whites_out() {
+        let mut vfs = overlay();
+        vfs.rename(p("/data"), p("/moved")).expect("mv");
+
+        assert!(!vfs.exists(p("/data")).expect("exists"));
+        assert_eq!(read(&vfs, "/moved/sub/b.txt"), "b\n");
+        assert_eq!(
+            changes(&vfs),
+            [
+                "D /data",
+                "A /moved",
+                "A /moved/a.txt",
+                "A /moved/sub",
+                "A /moved/sub/b.txt"
+            ]
+        );
+
+        vfs.rename(p("/etc/motd"), p("/etc/hosts")).expect("mv");
+        assert_eq!(read(&vfs, "/etc/hosts"), "hello\n");
+        assert!(matches!(
+            vfs.rename(p("/moved"), p("/moved/sub")),
+            Err(VfsError::PermissionDenied(_))
+        ));
+    }
+
+    #[test]
+    fn set_modified_copies_up() {
+        let mut vfs = overlay();
+        let time = UNIX_EPOCH + Duration::from_secs(86_400);
+        vfs.set_modified(p("/data/sub"), time).expect("touch");
+
+        assert_eq!(
+            vfs.metadata(p("/data/sub")).expect("meta").modified,
+            Some(time)
+        );
+        assert_ne!(
+            vfs.lower().metadata(p("/data/sub")).expect("meta").modified,
+            Some(time)
+        );
+        // Touching a directory changes nothing of its contents
+        assert!(changes(&vfs).is_empty());
+        assert_eq!(names(&vfs, "/data/sub"), ["b.txt"]);
+    }
+
+    #[test]
+    fn commit_applies_the_changes() {
+        let mut vfs = overlay();
+        write(&mut vfs, "/etc/motd", VfsWriteMode::Truncate, "bye\n");
+        vfs.remove_file(p("/etc/hosts")).expect("rm");
+        vfs.remove_dir_all(p("/data")).expect("rm -r");
+        vfs.create_dir(p("/data")).expect("mkdir");
+        vfs.create_dir_all(p("/new/deep")).expect("mkdir -p");
+        write(&mut vfs, "/new/deep/f", VfsWriteMode::CreateNew, "f\n");
+        vfs.cd(p("/new/deep")).expect("cd");
+
+        assert_eq!(vfs.commit().expect("commit"), 7);
+        assert!(changes(&vfs).is_empty());
+        assert_eq!(names(vfs.upper(), "/"), Vec::<String>::new());
+        assert_eq!(vfs.cwd(), p("/new/deep"));
+
+        let lower = vfs.lower().snapshot();
+        assert_eq!(
+            lower.files().collect::<Vec<_>>(),
+            [
+                (p("/etc/motd"), &b"bye\n"[..]),
+                (p("/new/deep/f"), &b"f\n"[..])
+            ]
+        );
+        assert_eq!(
+            lower.dirs().collect::<Vec<_>>(),
+            [p("/"), p("/data"), p("/etc"), p("/new"), p("/new/deep")]
+        );
+    }
+
+    #[test]
+    fn discard_drops_the_changes() {
+        let mut vfs = overlay();
+        let before = vfs.lower().snapshot();
+        write(&mut vfs, "/etc/motd", VfsWriteMode::Append, "more\n");
+        vfs.remove_dir_all(p("/data")).expect("rm -r");
+        vfs.create_dir(p("/tmp")).expect("mkdir");
+        vfs.cd(p("/tmp")).expect("cd");
+
+        vfs.discard().expect("discard");
+        assert!(changes(&vfs).is_empty());
+        assert_eq!(read(&vfs, "/etc/motd"), "hello\n");
+        assert_eq!(names(&vfs, "/data"), ["a.txt", "sub"]);
+        assert_eq!(vfs.cwd(), p("/"));
+        assert_eq!(vfs.lower().snapshot(), before);
+    }
+
+    #[test]
+    fn other_backends_have_no_changes() {
+        let mut vfs = MemVfs::new();
+        assert!(matches!(
+            vfs.changes(),
+            Err(VfsError::Unsupported { op: "changes", .. })
+        ));
+        assert!(matches!(
+            vfs.commit(),
+            Err(VfsError::Unsupported { op: "commit", .. })
+        ));
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use super::{Vfs, VfsChange, VfsChangeKind, VfsError, VfsFileType, VfsResult};
+
+/// Reports changes below a watched path, see [`Vfs::watch`].
+pub trait VfsWatcher {
+    /// Wait up to `timeout` for changes and return them in the order they
+    /// were seen, or nothing if the timeout passed first.
+    ///
+    /// `vfs` is the one the watcher came from. It is passed to every call
+    /// instead of being kept, so it can be shared, e.g. behind a lock that
+    /// is released between calls.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error of looking for changes. Changes that cannot be
+    /// reported completely, e.g. because the host dropped some, are an
+    /// error too.
+    fn wait(&mut self, vfs: &dyn Vfs, timeout: Duration) -> VfsResult<Vec<VfsChange>>;
+}
+
+/// A [`VfsWatcher`] for any backend: it compares the kind, size and
+/// modification time of everything below the watched path at intervals.
+///
+/// This is what [`Vfs::watch`] uses unless a backend has something better.
+/// Changes that undo each other between two looks go unnoticed, and so do
+/// writes that keep both size and modification time.
+///
+/// ```
+/// use std::path::Path;
+/// use std::time::Duration;
+/// use esh::{HostVfs, PollWatcher, VfsChangeKind, VfsWatcher};
+///
+/// let dir = tempfile::tempdir()?;
+/// let vfs = HostVfs::new(dir.path())?;
+/// let mut watcher = PollWatcher::new(&vfs, Path::new("/"))?.interval(Duration::from_millis(10));
+///
+/// std::fs::write(dir.path().join("new.csv"), "x,y\n")?;
+/// let changes = watcher.wait(&vfs, Duration::from_secs(5))?;
+/// assert_eq!(changes[0].kind, VfsChangeKind::Added);
+/// assert_eq!(changes[0].path, Path::new("/new.csv"));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct PollWatcher {
+    path: PathBuf,
+    interval: Duration,
+    /// When `seen` was taken
+    last: Instant,
+    seen: BTreeMap<PathBuf, Stamp>,
+}
+
+/// What a [`PollWatcher`] compares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    file_type: VfsFileType,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl PollWatcher {
+    /// How long [`wait`](VfsWatcher::wait) leaves between two looks, unless
+    /// set with [`interval`](Self::interval).
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+    /// Watch `path` of `vfs`, taking the first look right away.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`VfsError::NotFound`] if `path` does not exist, or any error
+    /// of reading the entries below it, except that they vanish.
+    pub fn new<V: Vfs + ?Sized>(vfs: &V, path: &Path) -> VfsResult<Self> {
+        let path = vfs.resolve(path);
+        vfs.metadata(&path)?;
+        let mut watcher = Self {
+            path,
+            interval: Self::DEFAULT_INTERVAL,
+            last: Instant::now(),
+            seen: BTreeMap::new(),
+        };
+        watcher.seen = watcher.scan(vfs)?;
+        Ok(watcher)
+    }
+
+    /// Look for changes every `interval` instead of every
+    /// [`DEFAULT_INTERVAL`](Self::DEFAULT_INTERVAL).
+    #[must_use]
+    pub const fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// The stamps of the watched path and everything below it. Entries that
+    /// vanish while being looked at are left out.
+    fn scan<V: Vfs + ?Sized>(&self, vfs: &V) -> VfsResult<BTreeMap<PathBuf, Stamp>> {
+        let mut stamps = BTreeMap::new();
+        let mut todo = vec![self.path.clone()];
+        while let Some(path) = todo.pop() {
+            let meta = match vfs.metadata(&path) {
+                Ok(meta) => meta,
+                Err(VfsError::NotFound(_)) => continue,
+                Err(e) => return Err(e),
+            };
+            let stamp = if meta.is_dir() {
+                match vfs.read_dir(&path) {
+                    Ok(entries) => todo.extend(entries.into_iter().map(|e| path.join(e.name))),
+                    Err(VfsError::NotFound(_) | VfsError::NotADirectory(_)) => continue,
+                    Err(e) => return Err(e),
+                }
+                // Entries coming and going are reported by themselves
+                Stamp {
+                    file_type: meta.file_type,
+                    len: 0,
+                    modified: None,
+                }
+            } else {
+                Stamp {
+                    file_type: meta.file_type,
+                    len: meta.len,
+                    modified: meta.modified,
+                }
+            };
+            stamps.insert(path, stamp);
+        }
+        Ok(stamps)
+    }
+}
+
+impl VfsWatcher for PollWatcher {
+    /// Looks at most once per interval, so waiting with a short `timeout`
+    /// again and again does not look more often.
+    fn wait(&mut self, vfs: &dyn Vfs, timeout: Duration) -> VfsResult<Vec<VfsChange>> {
+        let start = Instant::now();
+        loop {
+            if self.last.elapsed() >= self.interval {
+                let last = Instant::now();
+                let seen = self.scan(vfs)?;
+                let changes = diff(&self.seen, &seen);
+                (self.seen, self.last) = (seen, last);
+                if !changes.is_empty() {
+                    return Ok(changes);
+                }
+            }
+            let left = timeout.saturating_sub(start.elapsed());
+            if left.is_zero() {
+                return Ok(Vec::new());
+            }
+            std::thread::sleep(left.min(self.interval.saturating_sub(self.last.elapsed())));
+        }
+    }
+}
+
+/// The changes from `before` to `after`, in path order.
+fn diff(before: &BTreeMap<PathBuf, Stamp>, after: &BTreeMap<PathBuf, Stamp>) -> Vec<VfsChange> {
+    let change = |kind, path: &PathBuf, stamp: &Stamp| VfsChange {
+        kind,
+        path: path.clone(),
+        file_type: stamp.file_type,
+    };
+    let mut changes: Vec<VfsChange> = after
+        .iter()
+        .filter_map(|(path, stamp)| match before.get(path) {
+            None => Some(change(VfsChangeKind::Added, path, stamp)),
+            Some(old) if old != stamp => Some(change(VfsChangeKind::Modified, path, stamp)),
+            Some(_) => None,
+        })
+        .collect();
+    changes.extend(
+        before
+            .iter()
+            .filter(|(path, _)| !after.contains_key(*path))
+            .map(|(path, stamp)| change(VfsChangeKind::Removed, path, stamp)),
+    );
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    changes
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::vfs::{HostVfs, MemVfs};
+
+    fn p(path: &str) -> &Path {
+        Path::new(path)
+    }
+
+    fn kinds(changes: &[VfsChange]) -> Vec<(VfsChangeKind, &Path)> {
+        changes.iter().map(|c| (c.kind, c.path.as_path())).collect()
+    }
+
+    #[test]
+    fn reports_added_modified_and_removed_entries() {
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        std::fs::create_dir(dir.path().join("docs")).expect("mkdir");
+        std::fs::write(dir.path().join("docs/a.txt"), "a\n").expect("write");
+        std::fs::write(dir.path().join("b.txt"), "b\n").expect("write");
+        let vfs = HostVfs::new(dir.path()).expect("host vfs");
+        let mut watcher = PollWatcher::new(&vfs, p("/"))
+            .expect("watch")
+            .interval(Duration::from_millis(10));
+
+        std::fs::write(dir.path().join("docs/a.txt"), "a\nmore\n").expect("append");
+        std::fs::create_dir(dir.path().join("new")).expect("mkdir");
+        std::fs::write(dir.path().join("new/c.txt"), "c\n").expect("write");
+        std::fs::remove_file(dir.path().join("b.txt")).expect("rm");
+
+        let changes = watcher.wait(&vfs, Duration::from_secs(5)).expect("wait");
+        assert_eq!(
+            kinds(&changes),
+            [
+                (VfsChangeKind::Removed, p("/b.txt")),
+                (VfsChangeKind::Modified, p("/docs/a.txt")),
+                (VfsChangeKind::Added, p("/new")),
+                (VfsChangeKind::Added, p("/new/c.txt")),
+            ]
+        );
+        let types: Vec<_> = changes.iter().map(|c| c.file_type).collect();
+        assert_eq!(
+            types,
+            [
+                VfsFileType::File,
+                VfsFileType::File,
+                VfsFileType::Dir,
+                VfsFileType::File
+            ]
+        );
+        assert!(watcher.wait(&vfs, Duration::ZERO).expect("wait").is_empty());
+    }
+
+    #[test]
+    fn watches_single_files_relative_to_the_cwd() {
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        std::fs::create_dir(dir.path().join("logs")).expect("mkdir");
+        std::fs::write(dir.path().join("logs/app.log"), "").expect("write");
+        let mut vfs = HostVfs::new(dir.path()).expect("host vfs");
+        vfs.cd(p("logs")).expect("cd");
+        let mut watcher = PollWatcher::new(&vfs, p("app.log"))
+            .expect("watch")
+            .interval(Duration::from_millis(10));
+
+        std::fs::write(dir.path().join("logs/other.log"), "x\n").expect("write");
+        assert!(watcher
+            .wait(&vfs, Duration::from_millis(50))
+            .expect("wait")
+            .is_empty());
+
+        std::fs::write(dir.path().join("logs/app.log"), "line\n").expect("write");
+        let changes = watcher.wait(&vfs, Duration::from_secs(5)).expect("wait");
+        assert_eq!(
+            kinds(&changes),
+            [(VfsChangeKind::Modified, p("/logs/app.log"))]
+        );
+
+        std::fs::remove_file(dir.path().join("logs/app.log")).expect("rm");
+        let changes = watcher.wait(&vfs, Duration::from_secs(5)).expect("wait");
+        assert_eq!(
+            kinds(&changes),
+            [(VfsChangeKind::Removed, p("/logs/app.log"))]
+        );
+    }
+
+    #[test]
+    fn default_watch_polls_every_backend() {
+        let vfs = MemVfs::new().with_file("/a", "");
+        let mut watcher = vfs.watch(p("/")).expect("watch");
+        assert!(watcher.wait(&vfs, Duration::ZERO).expect("wait").is_empty());
+        assert!(matches!(
+            vfs.watch(p("/missing")).map(drop),
+            Err(VfsError::NotFound(_))
+        ));
+    }
+}
@@ -41,6 +41,463 @@ fn pwd_with_tempdir() {
         .stdout(predicate::str::contains("/"));
 }
 
+// -- cd / ls / tree --------------------------------------------------------
+
+fn populated_tempdir() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().expect("failed to create tempdir");
+    std::fs::create_dir_all(dir.path().join("sub/inner")).expect("failed to create dirs");
+    std::fs::write(dir.path().join("top.txt"), "hello").expect("failed to write file");
+    std::fs::write(dir.path().join("sub/a.txt"), "a").expect("failed to write file");
+    std::fs::write(dir.path().join(".dot"), "").expect("failed to write file");
+    dir
+}
+
+#[test]
+fn ls_lists_vfs_root() {
+    let dir = populated_tempdir();
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "ls"])
+        .assert()
+        .success()
+        .stdout("sub\ntop.txt\n");
+}
+
+#[test]
+fn ls_long_shows_sizes() {
+    let dir = populated_tempdir();
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "ls", "-la", "top.txt"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("-").and(predicate::str::contains(" 5 ")));
+}
+
+#[test]
+fn cd_persists_across_shell_commands() {
+    let dir = populated_tempdir();
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "shell"])
+        .write_stdin("cd sub\npwd\nls\ncd ..\npwd\n")
+        .assert()
+        .success()
+        .stdout("/sub\na.txt\ninner\n/\n");
+}
+
+#[test]
+fn cd_into_missing_directory_fails() {
+    let dir = populated_tempdir();
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "shell"])
+        .write_stdin("cd nope\npwd\n")
+        .assert()
+        .success()
+        .stdout("/\n")
+        .stderr(predicate::str::contains("/nope: no such file or directory"));
+}
+
+#[test]
+fn cd_cannot_leave_the_vfs_root() {
+    let dir = populated_tempdir();
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "shell"])
+        .write_stdin("cd ../../..\nls\n")
+        .assert()
+        .success()
+        .stdout("sub\ntop.txt\n");
+}
+
+#[test]
+fn tree_with_depth_limit() {
+    let dir = populated_tempdir();
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "tree", "-L", "1"])
+        .assert()
+        .success()
+        .stdout(".\n├── sub\n└── top.txt\n\n1 directory, 1 file\n");
+}
+
+// -- cat / head / tail / hexdump -------------------------------------------
+
+#[test]
+fn cat_prints_file_contents() {
+    let dir = populated_tempdir();
+    esh()
+        .args([
+            "-p",
+            dir.path().to_str().unwrap(),
+            "cat",
+            "top.txt",
+            "sub/a.txt",
+        ])
+        .assert()
+        .success()
+        .stdout("helloa");
+}
+
+#[test]
+fn cat_cannot_escape_the_vfs_root() {
+    let dir = populated_tempdir();
+    let outside = dir.path().parent().unwrap().join("esh-outside.txt");
+    let name = format!("../{}", outside.file_name().unwrap().to_str().unwrap());
+    std::fs::write(&outside, "secret").expect("failed to write file");
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "cat", &name])
+        .assert()
+        .failure()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("no such file or directory"));
+    std::fs::remove_file(outside).expect("failed to remove file");
+}
+
+#[test]
+#[cfg(unix)]
+fn symlinks_cannot_escape_the_vfs_root() {
+    let outside = tempfile::tempdir().expect("failed to create tempdir");
+    std::fs::write(outside.path().join("secret"), "secret").expect("failed to write file");
+    let dir = populated_tempdir();
+    std::os::unix::fs::symlink(outside.path(), dir.path().join("out")).expect("symlink");
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "shell"])
+        .write_stdin("cat out/secret\nls out\ncp top.txt out/copy\nls\n")
+        .assert()
+        .success()
+        .stdout("out\nsub\ntop.txt\n")
+        .stderr(predicate::str::contains("/out/secret: permission denied"))
+        .stderr(predicate::str::contains("/out/copy: permission denied"));
+    assert!(!outside.path().join("copy").exists());
+}
+
+#[test]
+fn head_and_tail_select_lines() {
+    let dir = populated_tempdir();
+    std::fs::write(dir.path().join("lines.txt"), "1\n2\n3\n4\n").expect("failed to write file");
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "shell"])
+        .write_stdin("head -n 1 lines.txt\ntail -n 2 lines.txt\n")
+        .assert()
+        .success()
+        .stdout("1\n3\n4\n");
+}
+
+#[test]
+fn hexdump_canonical() {
+    let dir = populated_tempdir();
+    esh()
+        .args([
+            "-p",
+            dir.path().to_str().unwrap(),
+            "hexdump",
+            "-C",
+            "top.txt",
+        ])
+        .assert()
+        .success()
+        .stdout("00000000  68 65 6c 6c 6f                                    |hello|\n00000005\n");
+}
+
+#[test]
+fn mkdir_cp_mv_and_rm_change_the_host_tree() {
+    let dir = populated_tempdir();
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "shell"])
+        .write_stdin("mkdir -p new/deeper\ncp -r sub new/deeper\nmv top.txt new\nrm -r sub\n")
+        .assert()
+        .success();
+    assert!(!dir.path().join("sub").exists());
+    assert!(!dir.path().join("top.txt").exists());
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("new/deeper/sub/a.txt")).unwrap(),
+        "a"
+    );
+    assert!(dir.path().join("new/deeper/sub/inner").is_dir());
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("new/top.txt")).unwrap(),
+        "hello"
+    );
+}
+
+#[test]
+fn rm_interactive_reads_the_answer_from_stdin() {
+    let dir = populated_tempdir();
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "shell"])
+        .write_stdin("rm -i top.txt\nn\nrm -i sub/a.txt\ny\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("rm: remove file 'top.txt'?"));
+    assert!(dir.path().join("top.txt").exists());
+    assert!(!dir.path().join("sub/a.txt").exists());
+}
+
+#[test]
+fn rm_cannot_remove_the_vfs_root() {
+    let dir = populated_tempdir();
+    esh()
+        .args([
+            "-p",
+            dir.path().to_str().unwrap(),
+            "rm",
+            "-rf",
+            "/",
+            "sub/..",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("refusing to remove '/'"));
+    assert!(dir.path().join("top.txt").exists());
+}
+
+#[test]
+fn find_by_name() {
+    let dir = populated_tempdir();
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "find", "-name", "*.txt"])
+        .assert()
+        .success()
+        .stdout("./sub/a.txt\n./top.txt\n");
+}
+
+#[test]
+fn grep_recursive_with_line_numbers() {
+    let dir = populated_tempdir();
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "grep", "-rn", "^[ah]"])
+        .assert()
+        .success()
+        .stdout("sub/a.txt:1:a\ntop.txt:1:hello\n");
+}
+
+#[test]
+fn grep_without_matches_fails() {
+    let dir = populated_tempdir();
+    esh()
+        .args([
+            "-p",
+            dir.path().to_str().unwrap(),
+            "grep",
+            "nothing",
+            "top.txt",
+        ])
+        .assert()
+        .code(1)
+        .stdout("");
+}
+
+#[test]
+fn overlay_keeps_changes_until_commit() {
+    let dir = populated_tempdir();
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "--overlay", "shell"])
+        .write_stdin("rm top.txt\nmkdir new\ncp sub/a.txt new\ndiff\n")
+        .assert()
+        .success()
+        .stdout("A /new/\nA /new/a.txt\nD /top.txt\n");
+    assert!(dir.path().join("top.txt").exists());
+    assert!(!dir.path().join("new").exists());
+
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "--overlay", "shell"])
+        .write_stdin("rm top.txt\nmkdir new\ncp sub/a.txt new\ncommit\n")
+        .assert()
+        .success()
+        .stdout("3 changes committed\n");
+    assert!(!dir.path().join("top.txt").exists());
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("new/a.txt")).unwrap(),
+        "a"
+    );
+}
+
+#[test]
+fn commit_without_overlay_fails() {
+    let dir = populated_tempdir();
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "shell"])
+        .write_stdin("commit\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("commit not supported"));
+}
+
+#[test]
+fn mount_memory_scratch_area() {
+    let dir = populated_tempdir();
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "shell"])
+        .write_stdin(
+            "mount --mem /scratch\ncp top.txt /scratch\nmounts\nls /scratch\numount /scratch\nls\n",
+        )
+        .assert()
+        .success()
+        .stdout("/\n/scratch\ntop.txt\nsub\ntop.txt\n");
+    assert!(!dir.path().join("scratch").exists());
+}
+
+#[test]
+fn diff_compares_files_and_trees() {
+    let dir = populated_tempdir();
+    std::fs::write(dir.path().join("sub/b.txt"), "b").expect("failed to write file");
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "diff", "-u", "top.txt", "sub/a.txt"])
+        .assert()
+        .failure()
+        .stdout("--- /top.txt\n+++ /sub/a.txt\n@@ -1 +1 @@\n-hello\n\\ No newline at end of file\n+a\n\\ No newline at end of file\n");
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "diff", "-r", "/", "sub"])
+        .assert()
+        .failure()
+        .stdout("D .dot\nA a.txt\nA b.txt\nA inner/\nD sub/\nD top.txt\n");
+}
+
+// -- checksums / du / stat ---------------------------------------------
+
+#[test]
+fn checksums_are_verified_against_the_vfs() {
+    let dir = populated_tempdir();
+    std::fs::write(
+        dir.path().join("SHA256SUMS"),
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824  top.txt\n\
+         ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb  sub/a.txt\n",
+    )
+    .expect("failed to write file");
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "md5sum", "top.txt"])
+        .assert()
+        .success()
+        .stdout("5d41402abc4b2a76b9719d911017c592  top.txt\n");
+    esh()
+        .args([
+            "-p",
+            dir.path().to_str().unwrap(),
+            "sha256sum",
+            "-c",
+            "SHA256SUMS",
+        ])
+        .assert()
+        .success()
+        .stdout("top.txt: OK\nsub/a.txt: OK\n");
+}
+
+#[test]
+fn du_and_stat_describe_the_vfs() {
+    let dir = populated_tempdir();
+    esh()
+        .args([
+            "-p",
+            dir.path().to_str().unwrap(),
+            "du",
+            "-s",
+            "sub",
+            "top.txt",
+        ])
+        .assert()
+        .success()
+        .stdout("1\tsub\n5\ttop.txt\n");
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "stat", "sub/a.txt"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("Size: 1 ")
+                .and(predicate::str::contains("Type: regular file")),
+        );
+}
+
+// -- get / put ----------------------------------------------------------
+
+#[test]
+fn get_and_put_copy_between_host_and_vfs() {
+    let dir = populated_tempdir();
+    let host = tempfile::tempdir().expect("failed to create tempdir");
+    std::fs::write(host.path().join("new.txt"), "new").expect("failed to write file");
+    let host_path = host.path().to_str().unwrap();
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "shell"])
+        .write_stdin(format!(
+            "get -r sub {host_path}\nput {host_path}/new.txt sub\nput {host_path}/new.txt sub\n"
+        ))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("exists, use -f to overwrite"));
+    assert_eq!(
+        std::fs::read_to_string(host.path().join("sub/a.txt")).unwrap(),
+        "a"
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("sub/new.txt")).unwrap(),
+        "new"
+    );
+}
+
+// -- edit ---------------------------------------------------------------
+
+#[test]
+fn edit_runs_the_editor_from_the_environment() {
+    let dir = populated_tempdir();
+    let editor = tempfile::tempdir().expect("failed to create tempdir");
+    let script = editor.path().join("editor.sh");
+    std::fs::write(&script, "echo ' world' >> \"$1\"\n").expect("failed to write file");
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "edit", "top.txt"])
+        .env_remove("VISUAL")
+        .env("EDITOR", format!("sh {}", script.to_str().unwrap()))
+        .assert()
+        .success();
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("top.txt")).unwrap(),
+        "hello world\n"
+    );
+}
+
+// -- open / close / reopen ------------------------------------------------
+
+#[test]
+fn open_switches_the_vfs_root() {
+    let dir = populated_tempdir();
+    esh()
+        .args(["-p", dir.path().to_str().unwrap(), "shell"])
+        .write_stdin("cd sub\nopen mem:\npwd\nls\nclose\nls\nreopen\ntouch new\nls\n")
+        .assert()
+        .success()
+        .stdout("/\nnew\n")
+        .stderr(predicate::str::contains(
+            "No VFS open, use `open` or `reopen`",
+        ));
+    assert!(!dir.path().join("new").exists());
+}
+
+#[test]
+fn watch_reports_changes_from_the_host() {
+    let dir = populated_tempdir();
+    let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_esh"))
+        .args([
+            "-p",
+            dir.path().to_str().unwrap(),
+            "watch",
+            "-c",
+            "1",
+            "-t",
+            "20",
+            "sub",
+        ])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("failed to start esh");
+    // The watch starts at some point, every file before that goes unseen
+    for i in 0.. {
+        if child.try_wait().expect("wait").is_some() {
+            break;
+        }
+        std::fs::write(dir.path().join(format!("sub/new{i}")), "").expect("write");
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    let output = child.wait_with_output().expect("output");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("A /sub/new"), "{stdout}");
+    assert_eq!(stdout.lines().count(), 1);
+}
+
 // -- error cases -----------------------------------------------------------
 
 #[test]
@@ -49,8 +506,19 @@ fn nonexistent_path_fails() {
         .args(["-p", "/nonexistent", "pwd"])
         .assert()
         .failure()
-        .code(2)
-        .stderr(predicate::str::contains("cannot open path"));
+        .code(1)
+        .stderr(predicate::str::contains(
+            "/nonexistent: no such file or directory",
+        ));
+}
+
+#[test]
+fn nonexistent_path_is_only_opened_when_needed() {
+    esh()
+        .args(["-p", "/nonexistent", "version"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(env!("CARGO_PKG_VERSION")));
 }
 
 #[test]
@@ -62,11 +530,8 @@ fn path_pointing_to_file_fails() {
         .args(["-p", file_path.to_str().unwrap(), "pwd"])
         .assert()
         .failure()
-        .code(2)
-        .stderr(
-            predicate::str::contains("not a directory")
-                .or(predicate::str::contains("cannot open path")),
-        );
+        .code(1)
+        .stderr(predicate::str::contains("not a directory"));
 }
 
 #[test]
@@ -80,12 +545,12 @@ fn unknown_subcommand_fails_with_usage() {
 }
 
 #[test]
-fn no_args_shows_help() {
+fn no_args_runs_commands_from_stdin() {
     esh()
+        .write_stdin("version\nexit 4\n")
         .assert()
-        .failure()
-        .code(2)
-        .stderr(predicate::str::contains("Usage").and(predicate::str::contains("COMMAND")));
+        .code(4)
+        .stdout(predicate::str::contains(env!("CARGO_PKG_VERSION")));
 }
 
 // -- flags -----------------------------------------------------------------
@@ -138,12 +603,46 @@ fn version_flag_shows_version() {
 // -- shell subcommand ------------------------------------------------------
 
 #[test]
-fn shell_subcommand_exits_with_error() {
+fn shell_subcommand_runs_commands_from_stdin() {
     let dir = tempfile::tempdir().expect("failed to create tempdir");
     esh()
         .args(["-p", dir.path().to_str().unwrap(), "shell"])
+        .write_stdin("version\npwd\nexit\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(env!("CARGO_PKG_VERSION")))
+        .stdout(predicate::str::contains("/"));
+}
+
+#[test]
+fn shell_subcommand_exit_code() {
+    esh()
+        .arg("shell")
+        .write_stdin("exit 7\nversion\n")
         .assert()
-        .failure();
+        .code(7)
+        .stdout(predicate::str::is_empty());
+}
+
+#[test]
+fn shell_subcommand_ends_at_eof() {
+    esh()
+        .arg("shell")
+        .write_stdin("version\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(env!("CARGO_PKG_VERSION")));
+}
+
+#[test]
+fn shell_subcommand_reports_errors_and_continues() {
+    esh()
+        .arg("shell")
+        .write_stdin("nosuchcmd\nversion\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("unrecognized subcommand"))
+        .stdout(predicate::str::contains(env!("CARGO_PKG_VERSION")));
 }
 
 // -- combined flags and commands -------------------------------------------